@@ -4,7 +4,11 @@ use adventofcode_2019::common::day_input_filename;
 use adventofcode_2019::common::get_file_lines;
 use adventofcode_2019::common::Solution;
 use adventofcode_2019::days;
+use adventofcode_2019::intcode::parse_program;
+use adventofcode_2019::intcode::Engine;
 use adventofcode_2019::intcode::IntcodeComputer;
+use adventofcode_2019::solver::DynSolver;
+use adventofcode_2019::solver::Registry;
 use test::Bencher;
 
 macro_rules! run_bench {
@@ -12,8 +16,9 @@ macro_rules! run_bench {
         #[bench]
         fn $name(b: &mut Bencher) {
             let input_lines = get_file_lines(&day_input_filename($day)).unwrap();
-            let solve = days::get_solver($day).unwrap();
-            b.iter(|| solve(&input_lines));
+            let registry = Registry::all();
+            let solver = registry.get(days::YEAR, $day).unwrap();
+            b.iter(|| solver.solve(&input_lines).unwrap());
         }
     };
 }
@@ -46,11 +51,13 @@ run_bench!(day25, 25);
 
 #[bench]
 fn days_all(b: &mut Bencher) {
-    let solvers_and_inputs: Vec<(fn(&[String]) -> Solution, Vec<String>)> = days::all_numbers()
+    let registry = Registry::all();
+    let solvers_and_inputs: Vec<(&dyn DynSolver, Vec<String>)> = registry
+        .days(days::YEAR)
         .into_iter()
         .map(|day| {
             (
-                days::get_solver(day).unwrap(),
+                registry.get(days::YEAR, day).unwrap(),
                 get_file_lines(&day_input_filename(day)).unwrap(),
             )
         })
@@ -59,7 +66,7 @@ fn days_all(b: &mut Bencher) {
     b.iter(|| {
         solvers_and_inputs
             .iter()
-            .map(|(solver, input)| solver(&input))
+            .map(|(solver, input)| solver.solve(input).unwrap())
             .collect::<Vec<Solution>>()
     })
 }
@@ -67,6 +74,25 @@ fn days_all(b: &mut Bencher) {
 mod intcode {
     use super::*;
 
+    fn run_with_engine(b: &mut Bencher, day: u8, input: &[i64], engine: Engine) {
+        let program = parse_program(&get_file_lines(&day_input_filename(day)).unwrap()).unwrap();
+        b.iter(|| {
+            let mut computer = IntcodeComputer::new(program.clone());
+            computer.engine = engine;
+            computer.run(input.iter().copied())
+        });
+    }
+
+    #[bench]
+    fn day09_b_step(b: &mut Bencher) {
+        run_with_engine(b, 9, &[2], Engine::Step);
+    }
+
+    #[bench]
+    fn day09_b_cached(b: &mut Bencher) {
+        run_with_engine(b, 9, &[2], Engine::Cached);
+    }
+
     #[bench]
     fn day9_example_1_new(b: &mut Bencher) {
         let program = vec![
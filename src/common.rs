@@ -1,10 +1,169 @@
+use crate::intcode::IntcodeError;
+use crate::intcode::StopReason;
+use std::fmt;
 use std::fs::File;
 use std::io::Read;
 use std::path::Path;
 use std::path::PathBuf;
+use std::str::FromStr;
 
 pub type Solution = (String, String);
 
+#[derive(Debug)]
+pub enum AocErrorKind {
+    Io(std::io::Error),
+    Intcode(IntcodeError),
+    InvalidDay(String),
+    UnknownDay { year: u16, day: u8 },
+    InvalidInput(String),
+    NoSolution(String),
+}
+
+/// An error while loading or solving a puzzle, with as much as is known about
+/// where it happened. Lines and columns start at 1.
+#[derive(Debug)]
+pub struct AocError {
+    pub day: Option<u8>,
+    pub file: Option<PathBuf>,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub kind: AocErrorKind,
+}
+
+impl AocError {
+    pub fn new(kind: AocErrorKind) -> Self {
+        AocError {
+            day: None,
+            file: None,
+            line: None,
+            column: None,
+            kind,
+        }
+    }
+
+    pub fn invalid_input<S: Into<String>>(message: S) -> Self {
+        Self::new(AocErrorKind::InvalidInput(message.into()))
+    }
+
+    pub fn no_solution<S: Into<String>>(message: S) -> Self {
+        Self::new(AocErrorKind::NoSolution(message.into()))
+    }
+
+    pub fn at_line(mut self, line: usize) -> Self {
+        self.line = Some(line);
+        self
+    }
+
+    pub fn at(mut self, line: usize, column: usize) -> Self {
+        self.line = Some(line);
+        self.column = Some(column);
+        self
+    }
+
+    /// Sets the day, unless it is already set.
+    pub fn in_day(mut self, day: u8) -> Self {
+        self.day = self.day.or(Some(day));
+        self
+    }
+
+    /// Sets the file, unless it is already set.
+    pub fn in_file(mut self, file: &Path) -> Self {
+        self.file = self.file.or_else(|| Some(file.to_path_buf()));
+        self
+    }
+}
+
+impl fmt::Display for AocError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(day) = self.day {
+            write!(f, "Day {}: ", day)?;
+        }
+        match (&self.file, self.line, self.column) {
+            (Some(file), Some(line), Some(column)) => {
+                write!(f, "{}:{}:{}: ", file.display(), line, column)?
+            }
+            (Some(file), Some(line), None) => write!(f, "{}:{}: ", file.display(), line)?,
+            (Some(file), None, _) => write!(f, "{}: ", file.display())?,
+            (None, Some(line), Some(column)) => write!(f, "line {}, column {}: ", line, column)?,
+            (None, Some(line), None) => write!(f, "line {}: ", line)?,
+            (None, None, _) => {}
+        }
+        match &self.kind {
+            AocErrorKind::Io(err) => write!(f, "{}", err),
+            AocErrorKind::Intcode(err) => write!(f, "Intcode error: {}", err),
+            AocErrorKind::InvalidDay(day) => write!(f, "Invalid day number: {}", day),
+            AocErrorKind::UnknownDay { year, day } => {
+                write!(f, "No solution for {} day {}", year, day)
+            }
+            AocErrorKind::InvalidInput(message) => write!(f, "Invalid input: {}", message),
+            AocErrorKind::NoSolution(message) => write!(f, "No solution found: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for AocError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            AocErrorKind::Io(err) => Some(err),
+            AocErrorKind::Intcode(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for AocError {
+    fn from(err: std::io::Error) -> Self {
+        Self::new(AocErrorKind::Io(err))
+    }
+}
+
+impl From<IntcodeError> for AocError {
+    fn from(err: IntcodeError) -> Self {
+        Self::new(AocErrorKind::Intcode(err))
+    }
+}
+
+/// For a program that stopped before it was done, for example by running out
+/// of steps.
+impl From<StopReason> for AocError {
+    fn from(reason: StopReason) -> Self {
+        match reason {
+            StopReason::Error(err) => err.into(),
+            reason => Self::no_solution(format!("The program stopped: {:?}", reason)),
+        }
+    }
+}
+
+/// Parses `s`, found at `line` and `column` of the input.
+pub fn parse_at<T>(s: &str, line: usize, column: usize) -> Result<T, AocError>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    s.parse()
+        .map_err(|err| AocError::invalid_input(format!("{:?}: {}", s, err)).at(line, column))
+}
+
+/// Splits `line` at `separator`, with the column where each part starts.
+pub fn split_columns<'a>(
+    line: &'a str,
+    separator: &'a str,
+) -> impl Iterator<Item = (usize, &'a str)> + 'a {
+    line.split(separator).scan(1, move |column, part| {
+        let start = *column;
+        *column += part.chars().count() + separator.chars().count();
+        Some((start, part))
+    })
+}
+
+/// Returns the first line of input, for puzzles that have only one.
+pub fn first_line(lines: &[String]) -> Result<&str, AocError> {
+    lines
+        .first()
+        .map(|line| line.as_str())
+        .ok_or_else(|| AocError::invalid_input("Empty input"))
+}
+
 pub fn day_input_filename(day: u8) -> PathBuf {
     let padded_day = format!("{:02}", day);
     Path::new("inputs").join(format!("day{}.in", padded_day))
@@ -14,10 +173,7 @@ pub fn get_file_lines(path: &Path) -> Result<Vec<String>, std::io::Error> {
     if path == Path::new("-") {
         read_lines(std::io::stdin())
     } else {
-        read_lines(
-            File::open(&path)
-                .unwrap_or_else(|_| panic!(format!("Input file not found: {:?}", path))),
-        )
+        read_lines(File::open(path)?)
     }
 }
 
@@ -26,3 +182,34 @@ fn read_lines<I: Read>(mut source: I) -> Result<Vec<String>, std::io::Error> {
     source.read_to_string(&mut contents)?;
     Ok(contents.lines().map(&str::to_string).collect())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describes_where_errors_happened() {
+        let err = parse_at::<i64>("1x", 3, 5).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            r#"line 3, column 5: Invalid input: "1x": invalid digit found in string"#
+        );
+        assert_eq!(
+            err.in_day(2)
+                .in_file(Path::new("inputs/day02.in"))
+                .in_day(3)
+                .to_string(),
+            r#"Day 2: inputs/day02.in:3:5: Invalid input: "1x": invalid digit found in string"#
+        );
+        assert_eq!(
+            split_columns("1,22,,4", ",").collect::<Vec<_>>(),
+            vec![(1, "1"), (3, "22"), (6, ""), (7, "4")]
+        );
+    }
+
+    #[test]
+    fn missing_file_is_an_error() {
+        let err = get_file_lines(Path::new("inputs/day00.in")).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+    }
+}
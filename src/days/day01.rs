@@ -1,4 +1,7 @@
-use crate::common::Solution;
+use crate::common::parse_at;
+use crate::common::AocError;
+use crate::solver::Info;
+use crate::solver::Solver;
 
 fn base_fuel_mass(mass: i32) -> i32 {
     mass / 3 - 2
@@ -13,14 +16,39 @@ fn full_fuel_mass(mass: i32) -> i32 {
     }
 }
 
-pub fn solve(lines: &[String]) -> Solution {
-    let (a_solution, b_solution) =
+pub struct Day01;
+
+impl Solver for Day01 {
+    type Input = Vec<i32>;
+
+    fn info(&self) -> Info {
+        super::info(1, "The Tyranny of the Rocket Equation", false)
+    }
+
+    fn parse(&self, lines: &[String]) -> Result<Vec<i32>, AocError> {
         lines
             .iter()
-            .map(|line| line.parse::<i32>().unwrap())
-            .fold((0, 0), |(suma, sumb), i| {
-                let base = base_fuel_mass(i);
-                (suma + base, sumb + base + full_fuel_mass(base))
-            });
-    (a_solution.to_string(), b_solution.to_string())
+            .enumerate()
+            .map(|(i, line)| parse_at(line, i + 1, 1))
+            .collect()
+    }
+
+    fn part_a(&self, masses: &Vec<i32>) -> Result<String, AocError> {
+        Ok(masses
+            .iter()
+            .map(|mass| base_fuel_mass(*mass))
+            .sum::<i32>()
+            .to_string())
+    }
+
+    fn part_b(&self, masses: &Vec<i32>) -> Result<String, AocError> {
+        Ok(masses
+            .iter()
+            .map(|mass| {
+                let base = base_fuel_mass(*mass);
+                base + full_fuel_mass(base)
+            })
+            .sum::<i32>()
+            .to_string())
+    }
 }
@@ -1,34 +1,54 @@
-use crate::common::Solution;
+use crate::common::AocError;
 use crate::intcode::parse_program;
 use crate::intcode::IntcodeComputer;
+use crate::intcode::Word;
+use crate::solver::Info;
+use crate::solver::Solver;
 
-fn solve_a(mut computer: IntcodeComputer) -> i64 {
+fn solve_a(mut computer: IntcodeComputer) -> Result<i64, AocError> {
     computer.prog[1] = 12;
     computer.prog[2] = 2;
-    computer.run(None).prog[0]
+    Ok(computer.try_run(None)?.prog[0])
 }
 
 #[allow(clippy::unreadable_literal)]
 const B_OUTPUT_TARGET: i64 = 19690720;
 
-fn solve_b(program: Vec<i64>) -> i64 {
+fn solve_b(program: Vec<i64>) -> Result<i64, AocError> {
     for noun in 0..=99 {
         for verb in 0..=99 {
-            let mut prog = program.clone();
-            prog[1] = noun;
-            prog[2] = verb;
-            if IntcodeComputer::new(prog).run(None).prog[0] == B_OUTPUT_TARGET {
-                return 100 * noun + verb;
+            let mut computer = IntcodeComputer::new(program.clone());
+            computer.prog[1] = noun;
+            computer.prog[2] = verb;
+            if computer.try_run(None)?.prog[0] == B_OUTPUT_TARGET {
+                return Ok(100 * noun + verb);
             }
         }
     }
-    panic!("No solution found!");
+    Err(AocError::no_solution(format!(
+        "no noun and verb give {}",
+        B_OUTPUT_TARGET
+    )))
 }
 
-pub fn solve(lines: &[String]) -> Solution {
-    let program = parse_program(lines);
-    (
-        solve_a(IntcodeComputer::new(program.clone())).to_string(),
-        solve_b(program).to_string(),
-    )
+pub struct Day02;
+
+impl Solver for Day02 {
+    type Input = Vec<Word>;
+
+    fn info(&self) -> Info {
+        super::info(2, "1202 Program Alarm", true)
+    }
+
+    fn parse(&self, lines: &[String]) -> Result<Vec<Word>, AocError> {
+        parse_program(lines)
+    }
+
+    fn part_a(&self, program: &Vec<Word>) -> Result<String, AocError> {
+        Ok(solve_a(IntcodeComputer::new(program.clone()))?.to_string())
+    }
+
+    fn part_b(&self, program: &Vec<Word>) -> Result<String, AocError> {
+        Ok(solve_b(program.clone())?.to_string())
+    }
 }
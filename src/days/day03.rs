@@ -1,19 +1,28 @@
+use crate::common::parse_at;
+use crate::common::split_columns;
+use crate::common::AocError;
 use crate::common::Solution;
+use crate::solver::Info;
+use crate::solver::Solver;
 use std::cmp::Ordering;
 
-fn parse_wire(desc: &str) -> Vec<LineSegment> {
+fn parse_wire(desc: &str, line: usize) -> Result<Vec<LineSegment>, AocError> {
     let mut points: Vec<LineSegment> = Vec::new();
     let mut pos = (0, 0);
     let mut tot_len = 0;
-    for step in desc.split(',') {
-        let dir = match step.chars().next().unwrap() {
-            'R' => (1, 0),
-            'L' => (-1, 0),
-            'U' => (0, 1),
-            'D' => (0, -1),
-            _ => unreachable!(),
+    for (column, step) in split_columns(desc, ",") {
+        let dir = match step.chars().next() {
+            Some('R') => (1, 0),
+            Some('L') => (-1, 0),
+            Some('U') => (0, 1),
+            Some('D') => (0, -1),
+            _ => {
+                return Err(
+                    AocError::invalid_input(format!("Invalid step: {:?}", step)).at(line, column)
+                )
+            }
         };
-        let len: i32 = step[1..].parse().unwrap();
+        let len: i32 = parse_at(&step[1..], line, column + 1)?;
         let pos_off_origin = if pos == (0, 0) { dir } else { pos };
         let end = (pos.0 + dir.0 * len, pos.1 + dir.1 * len);
         points.push(LineSegment {
@@ -28,10 +37,10 @@ fn parse_wire(desc: &str) -> Vec<LineSegment> {
         pos = end;
         tot_len += len;
     }
-    points
+    Ok(points)
 }
 
-struct LineSegment {
+pub struct LineSegment {
     x_start: i32,
     x_end: i32,
     y_start: i32,
@@ -126,10 +135,9 @@ where
     i_min
 }
 
-pub fn solve(lines: &[String]) -> Solution {
-    let wire1 = parse_wire(&lines[0]);
-    let wire2 = parse_wire(&lines[1]);
-
+/// Returns the smallest Manhattan distance and the smallest combined walk
+/// length to an intersection.
+fn closest_intersections(wire1: &[LineSegment], wire2: &[LineSegment]) -> (i32, i32) {
     let (mut wire1_x, mut wire1_y): (Vec<&LineSegment>, Vec<&LineSegment>) =
         wire1.iter().partition(|seg| seg.x_start == seg.x_end);
 
@@ -147,7 +155,7 @@ pub fn solve(lines: &[String]) -> Solution {
                 .skip(wire1_i_min)
                 .take_while(|seg| seg.y_start <= seg2.y_end)
             {
-                if let Some(isct) = seg1.intersection(&seg2) {
+                if let Some(isct) = seg1.intersection(seg2) {
                     a_solution = std::cmp::min(a_solution, isct.x.abs() + isct.y.abs());
                     b_solution = std::cmp::min(b_solution, isct.walk_len);
                 }
@@ -159,7 +167,7 @@ pub fn solve(lines: &[String]) -> Solution {
                 .skip(wire1_i_min)
                 .take_while(|seg| seg.x_start <= seg2.x_end)
             {
-                if let Some(isct) = seg1.intersection(&seg2) {
+                if let Some(isct) = seg1.intersection(seg2) {
                     a_solution = std::cmp::min(a_solution, isct.x.abs() + isct.y.abs());
                     b_solution = std::cmp::min(b_solution, isct.walk_len);
                 }
@@ -167,5 +175,35 @@ pub fn solve(lines: &[String]) -> Solution {
         }
     }
 
-    (a_solution.to_string(), b_solution.to_string())
+    (a_solution, b_solution)
+}
+
+pub struct Day03;
+
+impl Solver for Day03 {
+    type Input = (Vec<LineSegment>, Vec<LineSegment>);
+
+    fn info(&self) -> Info {
+        super::info(3, "Crossed Wires", false)
+    }
+
+    fn parse(&self, lines: &[String]) -> Result<Self::Input, AocError> {
+        match lines {
+            [wire1, wire2, ..] => Ok((parse_wire(wire1, 1)?, parse_wire(wire2, 2)?)),
+            _ => Err(AocError::invalid_input("Expected two wires")),
+        }
+    }
+
+    fn part_a(&self, (wire1, wire2): &Self::Input) -> Result<String, AocError> {
+        Ok(closest_intersections(wire1, wire2).0.to_string())
+    }
+
+    fn part_b(&self, (wire1, wire2): &Self::Input) -> Result<String, AocError> {
+        Ok(closest_intersections(wire1, wire2).1.to_string())
+    }
+
+    fn solve(&self, (wire1, wire2): &Self::Input) -> Result<Solution, AocError> {
+        let (a_solution, b_solution) = closest_intersections(wire1, wire2);
+        Ok((a_solution.to_string(), b_solution.to_string()))
+    }
 }
@@ -1,4 +1,10 @@
+use crate::common::first_line;
+use crate::common::parse_at;
+use crate::common::split_columns;
+use crate::common::AocError;
 use crate::common::Solution;
+use crate::solver::Info;
+use crate::solver::Solver;
 use std::collections::HashMap;
 
 fn satisfies_conditions_a(pw: &PasswordNumber) -> bool {
@@ -68,14 +74,9 @@ impl From<u32> for PasswordNumber {
     }
 }
 
-pub fn solve(lines: &[String]) -> Solution {
-    let bounds = lines[0]
-        .split('-')
-        .map(|s| s.parse().unwrap())
-        .collect::<Vec<u32>>();
-    let low_bound: u32 = bounds[0];
-    let high_bound: u32 = bounds[1];
-
+/// Counts the passwords in the range that satisfy the conditions of part A,
+/// and of both parts.
+fn count_passwords(low_bound: u32, high_bound: u32) -> (u32, u32) {
     let mut count_a = 0;
     let mut count_b = 0;
     let mut num = PasswordNumber::from(low_bound);
@@ -89,5 +90,38 @@ pub fn solve(lines: &[String]) -> Solution {
         num.next();
     }
 
-    (count_a.to_string(), count_b.to_string())
+    (count_a, count_b)
+}
+
+pub struct Day04;
+
+impl Solver for Day04 {
+    type Input = (u32, u32);
+
+    fn info(&self) -> Info {
+        super::info(4, "Secure Container", false)
+    }
+
+    fn parse(&self, lines: &[String]) -> Result<(u32, u32), AocError> {
+        let bounds = split_columns(first_line(lines)?, "-")
+            .map(|(column, s)| parse_at(s, 1, column))
+            .collect::<Result<Vec<u32>, AocError>>()?;
+        match bounds[..] {
+            [low, high] => Ok((low, high)),
+            _ => Err(AocError::invalid_input("Expected a range like 123-456").at_line(1)),
+        }
+    }
+
+    fn part_a(&self, (low_bound, high_bound): &(u32, u32)) -> Result<String, AocError> {
+        Ok(count_passwords(*low_bound, *high_bound).0.to_string())
+    }
+
+    fn part_b(&self, (low_bound, high_bound): &(u32, u32)) -> Result<String, AocError> {
+        Ok(count_passwords(*low_bound, *high_bound).1.to_string())
+    }
+
+    fn solve(&self, (low_bound, high_bound): &(u32, u32)) -> Result<Solution, AocError> {
+        let (count_a, count_b) = count_passwords(*low_bound, *high_bound);
+        Ok((count_a.to_string(), count_b.to_string()))
+    }
 }
@@ -1,29 +1,47 @@
-use crate::common::Solution;
+use crate::common::AocError;
+use crate::intcode::parse_program;
 use crate::intcode::IntcodeComputer;
+use crate::solver::Info;
+use crate::solver::Solver;
 
-fn solve_a(computer: IntcodeComputer) -> Option<i64> {
-    let output = computer.run(Some(1)).output;
-    if output
-        .iter()
-        .enumerate()
-        .all(|(i, o)| *o == 0 || i == output.len() - 1)
-    {
-        Some(*output.back().unwrap())
-    } else {
-        None
+fn solve_a(computer: IntcodeComputer) -> Result<i64, AocError> {
+    let output = computer.try_run(Some(1))?.output;
+    match output.back() {
+        Some(code) if output.iter().rev().skip(1).all(|o| *o == 0) => Ok(*code),
+        _ => Err(AocError::no_solution(format!(
+            "Diagnostic tests failed: {:?}",
+            output
+        ))),
     }
 }
 
-fn solve_b(computer: IntcodeComputer) -> i64 {
-    *computer.run(Some(5)).output.back().unwrap()
+fn solve_b(computer: IntcodeComputer) -> Result<i64, AocError> {
+    computer
+        .try_run(Some(5))?
+        .output
+        .back()
+        .copied()
+        .ok_or_else(|| AocError::no_solution("The program gave no output"))
 }
 
-pub fn solve(lines: &[String]) -> Solution {
-    let computer: IntcodeComputer = lines.into();
-    (
-        solve_a(computer.clone())
-            .map(|i| i.to_string())
-            .unwrap_or_else(|| "Failure!".to_string()),
-        solve_b(computer).to_string(),
-    )
+pub struct Day05;
+
+impl Solver for Day05 {
+    type Input = IntcodeComputer;
+
+    fn info(&self) -> Info {
+        super::info(5, "Sunny with a Chance of Asteroids", true)
+    }
+
+    fn parse(&self, lines: &[String]) -> Result<IntcodeComputer, AocError> {
+        parse_program(lines).map(IntcodeComputer::new)
+    }
+
+    fn part_a(&self, computer: &IntcodeComputer) -> Result<String, AocError> {
+        Ok(solve_a(computer.clone())?.to_string())
+    }
+
+    fn part_b(&self, computer: &IntcodeComputer) -> Result<String, AocError> {
+        Ok(solve_b(computer.clone())?.to_string())
+    }
 }
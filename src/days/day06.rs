@@ -1,13 +1,15 @@
 use std::collections::HashMap;
 
-use crate::common::Solution;
+use crate::common::AocError;
+use crate::solver::Info;
+use crate::solver::Solver;
 
-fn solve_a(orbits: &HashMap<&str, &str>) -> u32 {
+fn solve_a(orbits: &HashMap<String, String>) -> u32 {
     let mut num_orbits: HashMap<&str, u32> = HashMap::new();
 
     fn get_orbit_nums<'obt>(
         child: &'obt str,
-        orbits: &'obt HashMap<&str, &str>,
+        orbits: &'obt HashMap<String, String>,
         num_orbits: &mut HashMap<&'obt str, u32>,
     ) -> u32 {
         num_orbits.get(child).copied().unwrap_or_else(|| {
@@ -18,22 +20,23 @@ fn solve_a(orbits: &HashMap<&str, &str>) -> u32 {
             num_orbits.insert(child, result);
             result
         })
-    };
+    }
 
     orbits
         .keys()
-        .map(|child| get_orbit_nums(&child, &orbits, &mut num_orbits))
+        .map(|child| get_orbit_nums(child, orbits, &mut num_orbits))
         .sum()
 }
 
-fn solve_b(orbits: &HashMap<&str, &str>) -> Option<usize> {
+fn solve_b(orbits: &HashMap<String, String>) -> Option<usize> {
     let mut steps_from_you: HashMap<&str, usize> = HashMap::new();
     let mut steps_from_san: HashMap<&str, usize> = HashMap::new();
 
-    let mut seq_from_you = std::iter::successors(Some(&"YOU"), |pos| orbits.get(*pos))
+    let parent = |pos: &&str| orbits.get(*pos).map(String::as_str);
+    let mut seq_from_you = std::iter::successors(Some("YOU"), parent)
         .skip(1)
         .enumerate();
-    let mut seq_from_san = std::iter::successors(Some(&"SAN"), |pos| orbits.get(*pos))
+    let mut seq_from_san = std::iter::successors(Some("SAN"), parent)
         .skip(1)
         .enumerate();
 
@@ -60,21 +63,37 @@ fn solve_b(orbits: &HashMap<&str, &str>) -> Option<usize> {
     }
 }
 
-pub fn solve(lines: &[String]) -> Solution {
-    let orbits: HashMap<&str, &str> = lines
-        .iter()
-        .map(|line| {
-            let mut splt = line.split(')');
-            let a = splt.next().unwrap();
-            let b = splt.next().unwrap();
-            (b, a)
-        })
-        .collect();
+pub struct Day06;
+
+impl Solver for Day06 {
+    type Input = HashMap<String, String>;
+
+    fn info(&self) -> Info {
+        super::info(6, "Universal Orbit Map", false)
+    }
 
-    (
-        solve_a(&orbits).to_string(),
-        solve_b(&orbits)
+    fn parse(&self, lines: &[String]) -> Result<HashMap<String, String>, AocError> {
+        lines
+            .iter()
+            .enumerate()
+            .map(|(i, line)| match line.split_once(')') {
+                Some((a, b)) => Ok((b.to_string(), a.to_string())),
+                None => Err(AocError::invalid_input(format!(
+                    "Expected an orbit like A)B: {:?}",
+                    line
+                ))
+                .at_line(i + 1)),
+            })
+            .collect()
+    }
+
+    fn part_a(&self, orbits: &HashMap<String, String>) -> Result<String, AocError> {
+        Ok(solve_a(orbits).to_string())
+    }
+
+    fn part_b(&self, orbits: &HashMap<String, String>) -> Result<String, AocError> {
+        solve_b(orbits)
             .map(|b| b.to_string())
-            .unwrap_or_else(|| "Impossible".to_string()),
-    )
+            .ok_or_else(|| AocError::no_solution("YOU and SAN orbit nothing in common"))
+    }
 }
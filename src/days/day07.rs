@@ -1,84 +1,82 @@
-use crate::common::Solution;
+use crate::common::AocError;
+use crate::intcode::network::Network;
+use crate::intcode::network::NetworkStop;
+use crate::intcode::network::Pipeline;
+use crate::intcode::parse_program;
 use crate::intcode::IntcodeComputer;
+use crate::solver::Info;
+use crate::solver::Solver;
 use crate::util::Permutations;
-use std::collections::VecDeque;
 
-fn run(computer: IntcodeComputer, input: (u8, i64)) -> VecDeque<i64> {
-    computer.run(vec![i64::from(input.0), input.1]).output
-}
-
-fn solve_a(computer: IntcodeComputer) -> i64 {
-    let mut max_output = None;
+fn amplify(computer: &IntcodeComputer, phases: &[u8], feedback: bool) -> Result<i64, AocError> {
+    let mut amplifiers: Vec<IntcodeComputer> = phases
+        .iter()
+        .map(|phase| {
+            let mut amplifier = computer.clone();
+            amplifier.input.push_back(i64::from(*phase));
+            amplifier
+        })
+        .collect();
+    amplifiers[0].input.push_back(0);
 
-    for perm in Permutations::from(0..=4) {
-        match perm.as_slice() {
-            [i0, i1, i2, i3, i4] => {
-                let output0 = run(computer.clone(), (*i0, 0));
-                let output1 = run(computer.clone(), (*i1, output0[0]));
-                let output2 = run(computer.clone(), (*i2, output1[0]));
-                let output3 = run(computer.clone(), (*i3, output2[0]));
-                let output4 = run(computer.clone(), (*i4, output3[0]));
-                if output4[0] > max_output.unwrap_or(output4[0] - 1) {
-                    max_output = Some(output4[0]);
-                }
-            }
-            _ => unreachable!(),
-        }
+    let mut network = Network::new(
+        amplifiers,
+        Pipeline {
+            feedback,
+            last_output: None,
+        },
+    );
+    match network.run() {
+        NetworkStop::AllHalted => network
+            .router
+            .last_output
+            .ok_or_else(|| AocError::no_solution("The amplifiers gave no output")),
+        NetworkStop::Error { error, .. } => Err(error.into()),
+        stop => Err(AocError::no_solution(format!(
+            "The amplifiers stopped: {:?}",
+            stop
+        ))),
     }
-
-    max_output.unwrap()
 }
 
-fn solve_b(computer: IntcodeComputer) -> i64 {
-    let mut max_output: Option<i64> = None;
+fn max_output(
+    computer: &IntcodeComputer,
+    phases: Permutations<u8>,
+    feedback: bool,
+) -> Result<i64, AocError> {
+    let mut max = None;
+    for perm in phases {
+        max = max.max(Some(amplify(computer, &perm, feedback)?));
+    }
+    Ok(max.unwrap())
+}
 
-    for perm in Permutations::from(5..=9) {
-        match perm.as_slice() {
-            [i1, i2, i3, i4, i5] => {
-                let mut comp1 = computer.clone();
-                let mut comp2 = computer.clone();
-                let mut comp3 = computer.clone();
-                let mut comp4 = computer.clone();
-                let mut comp5 = computer.clone();
+fn solve_a(computer: &IntcodeComputer) -> Result<i64, AocError> {
+    max_output(computer, Permutations::from(0..=4), false)
+}
 
-                comp1.input.push_back(*i1);
-                comp2.input.push_back(*i2);
-                comp3.input.push_back(*i3);
-                comp4.input.push_back(*i4);
-                comp5.input.push_back(*i5);
+fn solve_b(computer: &IntcodeComputer) -> Result<i64, AocError> {
+    max_output(computer, Permutations::from(5..=9), true)
+}
 
-                comp1.input.push_back(0);
+pub struct Day07;
 
-                while comp5.is_running() {
-                    comp1.input.extend(comp5.output.drain(..));
-                    comp2.input.extend(comp1.output.drain(..));
-                    comp3.input.extend(comp2.output.drain(..));
-                    comp4.input.extend(comp3.output.drain(..));
-                    comp5.input.extend(comp4.output.drain(..));
+impl Solver for Day07 {
+    type Input = IntcodeComputer;
 
-                    comp1 = comp1.run(None);
-                    comp2 = comp2.run(None);
-                    comp3 = comp3.run(None);
-                    comp4 = comp4.run(None);
-                    comp5 = comp5.run(None);
-                }
+    fn info(&self) -> Info {
+        super::info(7, "Amplification Circuit", true)
+    }
 
-                let out = comp5.output.pop_front().unwrap();
-                if out > max_output.unwrap_or(out - 1) {
-                    max_output = Some(out);
-                }
-            }
-            _ => unreachable!(),
-        }
+    fn parse(&self, lines: &[String]) -> Result<IntcodeComputer, AocError> {
+        parse_program(lines).map(IntcodeComputer::new)
     }
 
-    max_output.unwrap()
-}
+    fn part_a(&self, computer: &IntcodeComputer) -> Result<String, AocError> {
+        Ok(solve_a(computer)?.to_string())
+    }
 
-pub fn solve(lines: &[String]) -> Solution {
-    let computer: IntcodeComputer = lines.into();
-    (
-        solve_a(computer.clone()).to_string(),
-        solve_b(computer).to_string(),
-    )
+    fn part_b(&self, computer: &IntcodeComputer) -> Result<String, AocError> {
+        Ok(solve_b(computer)?.to_string())
+    }
 }
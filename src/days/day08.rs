@@ -1,6 +1,8 @@
 #![allow(clippy::naive_bytecount)]
 
-use crate::common::Solution;
+use crate::common::AocError;
+use crate::solver::Info;
+use crate::solver::Solver;
 
 const W: usize = 25;
 const H: usize = 6;
@@ -37,32 +39,50 @@ pub fn solve_b(layers: &[Vec<u8>]) -> String {
     )
 }
 
-pub fn solve(lines: &[String]) -> Solution {
-    let mut digits = lines.iter().flat_map(|line| line.chars());
-    let mut images: Vec<Vec<u8>> = Vec::new();
-    while let Some(digit0) = digits.next() {
-        let mut img = Vec::new();
-        img.push(digit0.to_string().parse().unwrap());
-        for _ in 1..W {
-            img.push(digits.next().unwrap().to_string().parse().unwrap());
-        }
-        for _ in 1..H {
-            for _ in 0..W {
-                img.push(digits.next().unwrap().to_string().parse().unwrap());
+pub struct Day08;
+
+impl Solver for Day08 {
+    type Input = Vec<Vec<u8>>;
+
+    fn info(&self) -> Info {
+        super::info(8, "Space Image Format", false)
+    }
+
+    fn parse(&self, lines: &[String]) -> Result<Vec<Vec<u8>>, AocError> {
+        let mut digits = Vec::new();
+        for (i, line) in lines.iter().enumerate() {
+            for (j, c) in line.chars().enumerate() {
+                match c.to_digit(10) {
+                    Some(digit) => digits.push(digit as u8),
+                    None => {
+                        return Err(AocError::invalid_input(format!("Not a digit: {:?}", c))
+                            .at(i + 1, j + 1))
+                    }
+                }
             }
         }
-        images.push(img);
+        if digits.is_empty() || digits.len() % (W * H) != 0 {
+            return Err(AocError::invalid_input(format!(
+                "Expected layers of {} digits, got {} digits",
+                W * H,
+                digits.len()
+            )));
+        }
+        Ok(digits.chunks(W * H).map(|img| img.to_vec()).collect())
     }
 
-    let fewest_zeros = images
-        .iter()
-        .min_by_key(|img| img.iter().filter(|d| **d == 0).count())
-        .unwrap();
+    fn part_a(&self, images: &Vec<Vec<u8>>) -> Result<String, AocError> {
+        let fewest_zeros = images
+            .iter()
+            .min_by_key(|img| img.iter().filter(|d| **d == 0).count())
+            .unwrap();
 
-    let num_ones = fewest_zeros.iter().filter(|d| **d == 1).count();
-    let num_twos = fewest_zeros.iter().filter(|d| **d == 2).count();
-    let a_solution = num_ones * num_twos;
-    let b_solution = solve_b(&images);
+        let num_ones = fewest_zeros.iter().filter(|d| **d == 1).count();
+        let num_twos = fewest_zeros.iter().filter(|d| **d == 2).count();
+        Ok((num_ones * num_twos).to_string())
+    }
 
-    (a_solution.to_string(), b_solution)
+    fn part_b(&self, images: &Vec<Vec<u8>>) -> Result<String, AocError> {
+        Ok(solve_b(images))
+    }
 }
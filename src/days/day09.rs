@@ -1,22 +1,46 @@
-use crate::common::Solution;
+use crate::common::AocError;
+use crate::intcode::parse_program;
 use crate::intcode::IntcodeComputer;
+use crate::solver::Info;
+use crate::solver::Solver;
 
-fn solve_a(computer: IntcodeComputer) -> i64 {
-    let output = computer.run(Some(1)).output;
-    assert_eq!(output.len(), 1, "{:?}", output);
-    output[0]
+fn boost(computer: IntcodeComputer, mode: i64) -> Result<i64, AocError> {
+    let output = computer.try_run(Some(mode))?.output;
+    match output.len() {
+        1 => Ok(output[0]),
+        _ => Err(AocError::no_solution(format!(
+            "Expected one output, got {:?}",
+            output
+        ))),
+    }
 }
 
-fn solve_b(computer: IntcodeComputer) -> i64 {
-    let output = computer.run(Some(2)).output;
-    assert_eq!(output.len(), 1, "{:?}", output);
-    output[0]
+fn solve_a(computer: IntcodeComputer) -> Result<i64, AocError> {
+    boost(computer, 1)
 }
 
-pub fn solve(lines: &[String]) -> Solution {
-    let computer: IntcodeComputer = lines.into();
-    (
-        solve_a(computer.clone()).to_string(),
-        solve_b(computer).to_string(),
-    )
+fn solve_b(computer: IntcodeComputer) -> Result<i64, AocError> {
+    boost(computer, 2)
+}
+
+pub struct Day09;
+
+impl Solver for Day09 {
+    type Input = IntcodeComputer;
+
+    fn info(&self) -> Info {
+        super::info(9, "Sensor Boost", true)
+    }
+
+    fn parse(&self, lines: &[String]) -> Result<IntcodeComputer, AocError> {
+        parse_program(lines).map(IntcodeComputer::new)
+    }
+
+    fn part_a(&self, computer: &IntcodeComputer) -> Result<String, AocError> {
+        Ok(solve_a(computer.clone())?.to_string())
+    }
+
+    fn part_b(&self, computer: &IntcodeComputer) -> Result<String, AocError> {
+        Ok(solve_b(computer.clone())?.to_string())
+    }
 }
@@ -1,4 +1,7 @@
+use crate::common::AocError;
 use crate::common::Solution;
+use crate::solver::Info;
+use crate::solver::Solver;
 use std::collections::HashMap;
 use std::convert::TryInto;
 
@@ -31,20 +34,10 @@ fn ray_atan((r, c): &Point) -> f64 {
     ((*c as f64).atan2(-*r as f64) + 2.0 * std::f64::consts::PI) % (2.0 * std::f64::consts::PI)
 }
 
-pub fn solve(lines: &[String]) -> Solution {
-    let map: Vec<Point> = lines
-        .iter()
-        .enumerate()
-        .flat_map(|(r, line)| {
-            line.chars()
-                .enumerate()
-                .filter(|(_, ch)| *ch == '#')
-                .map(move |(c, _)| (r.try_into().unwrap(), c.try_into().unwrap()))
-        })
-        .collect();
-
-    let (laser_pos, asteroid_rays): (Point, HashMap<Point, Vec<Point>>) = map
-        .iter()
+/// Finds the asteroid that sees the most other asteroids, and groups the
+/// others by the direction to them.
+fn best_station(map: &[Point]) -> (Point, HashMap<Point, Vec<Point>>) {
+    map.iter()
         .map(|(r0, c0)| {
             let recentered_map = map
                 .iter()
@@ -60,9 +53,15 @@ pub fn solve(lines: &[String]) -> Solution {
             ((*r0, *c0), asteroid_rays)
         })
         .max_by_key(|(_, rays)| rays.len())
-        .unwrap();
-    let a_solution = asteroid_rays.len();
+        .unwrap()
+}
 
+fn solve_b(laser_pos: Point, asteroid_rays: HashMap<Point, Vec<Point>>) -> Result<i64, AocError> {
+    if asteroid_rays.values().map(Vec::len).sum::<usize>() < 200 {
+        return Err(AocError::no_solution(
+            "Fewer than 200 asteroids to vaporize",
+        ));
+    }
     let mut asteroid_rays: Vec<(Point, Vec<Point>)> = asteroid_rays
         .into_iter()
         .map(|(p, mut ray)| {
@@ -92,6 +91,50 @@ pub fn solve(lines: &[String]) -> Solution {
         }
         i = (i + 1) % asteroid_rays.len();
     }
+    Ok(b_solution)
+}
+
+pub struct Day10;
+
+impl Solver for Day10 {
+    type Input = Vec<Point>;
+
+    fn info(&self) -> Info {
+        super::info(10, "Monitoring Station", false)
+    }
 
-    (a_solution.to_string(), b_solution.to_string())
+    fn parse(&self, lines: &[String]) -> Result<Vec<Point>, AocError> {
+        let map: Vec<Point> = lines
+            .iter()
+            .enumerate()
+            .flat_map(|(r, line)| {
+                line.chars()
+                    .enumerate()
+                    .filter(|(_, ch)| *ch == '#')
+                    .map(move |(c, _)| (r.try_into().unwrap(), c.try_into().unwrap()))
+            })
+            .collect();
+        if map.is_empty() {
+            Err(AocError::invalid_input("No asteroids"))
+        } else {
+            Ok(map)
+        }
+    }
+
+    fn part_a(&self, map: &Vec<Point>) -> Result<String, AocError> {
+        Ok(best_station(map).1.len().to_string())
+    }
+
+    fn part_b(&self, map: &Vec<Point>) -> Result<String, AocError> {
+        let (laser_pos, asteroid_rays) = best_station(map);
+        Ok(solve_b(laser_pos, asteroid_rays)?.to_string())
+    }
+
+    fn solve(&self, map: &Vec<Point>) -> Result<Solution, AocError> {
+        let (laser_pos, asteroid_rays) = best_station(map);
+        Ok((
+            asteroid_rays.len().to_string(),
+            solve_b(laser_pos, asteroid_rays)?.to_string(),
+        ))
+    }
 }
@@ -1,5 +1,9 @@
-use crate::common::Solution;
+use crate::common::AocError;
+use crate::intcode::parse_program;
 use crate::intcode::IntcodeComputer;
+use crate::intcode::StopReason;
+use crate::solver::Info;
+use crate::solver::Solver;
 use std::collections::HashMap;
 
 type Point = (i64, i64);
@@ -7,12 +11,13 @@ type Point = (i64, i64);
 fn run(
     mut computer: IntcodeComputer,
     mut white_panels: HashMap<Point, bool>,
-) -> HashMap<Point, bool> {
+) -> Result<HashMap<Point, bool>, AocError> {
     let mut pos = (0, 0);
     let mut dir = (0, 1);
 
-    while computer.is_running() {
-        computer.run_mut(Some(i64::from(*white_panels.get(&pos).unwrap_or(&false))));
+    loop {
+        let stop_reason =
+            computer.run_mut(Some(i64::from(*white_panels.get(&pos).unwrap_or(&false))));
 
         if let Some(out) = computer.output.pop_front() {
             white_panels.insert(pos, out == 1);
@@ -21,30 +26,47 @@ fn run(
             dir = match out {
                 0 => (-dir.1, dir.0),
                 1 => (dir.1, -dir.0),
-                _ => unreachable!(),
+                _ => {
+                    return Err(AocError::no_solution(format!(
+                        "The robot turned in an unknown direction: {}",
+                        out
+                    )))
+                }
             };
             pos = (pos.0 + dir.0, pos.1 + dir.1);
         }
+
+        match stop_reason {
+            StopReason::Halted => break,
+            StopReason::NeedsInput => {}
+            StopReason::Error(err) => return Err(err.into()),
+            reason => {
+                return Err(AocError::no_solution(format!(
+                    "The robot stopped: {:?}",
+                    reason
+                )))
+            }
+        }
     }
 
-    white_panels
+    Ok(white_panels)
 }
 
-fn solve_a(computer: IntcodeComputer) -> usize {
-    run(computer, HashMap::new()).len()
+fn solve_a(computer: IntcodeComputer) -> Result<usize, AocError> {
+    Ok(run(computer, HashMap::new())?.len())
 }
 
-fn solve_b(computer: IntcodeComputer) -> String {
+fn solve_b(computer: IntcodeComputer) -> Result<String, AocError> {
     let mut white_panels = HashMap::new();
     white_panels.insert((0, 0), true);
-    let white_panels = run(computer, white_panels);
+    let white_panels = run(computer, white_panels)?;
 
     let minx = *white_panels.keys().map(|(x, _)| x).min().unwrap();
     let maxx = *white_panels.keys().map(|(x, _)| x).max().unwrap();
     let miny = *white_panels.keys().map(|(_, y)| y).min().unwrap();
     let maxy = *white_panels.keys().map(|(_, y)| y).max().unwrap();
 
-    format!(
+    Ok(format!(
         "\n{}",
         (miny..=maxy)
             .rev()
@@ -62,10 +84,27 @@ fn solve_b(computer: IntcodeComputer) -> String {
             })
             .collect::<Vec<String>>()
             .join("\n")
-    )
+    ))
 }
 
-pub fn solve(lines: &[String]) -> Solution {
-    let computer: IntcodeComputer = lines.into();
-    (solve_a(computer.clone()).to_string(), solve_b(computer))
+pub struct Day11;
+
+impl Solver for Day11 {
+    type Input = IntcodeComputer;
+
+    fn info(&self) -> Info {
+        super::info(11, "Space Police", true)
+    }
+
+    fn parse(&self, lines: &[String]) -> Result<IntcodeComputer, AocError> {
+        parse_program(lines).map(IntcodeComputer::new)
+    }
+
+    fn part_a(&self, computer: &IntcodeComputer) -> Result<String, AocError> {
+        Ok(solve_a(computer.clone())?.to_string())
+    }
+
+    fn part_b(&self, computer: &IntcodeComputer) -> Result<String, AocError> {
+        solve_b(computer.clone())
+    }
 }
@@ -1,8 +1,13 @@
+use crate::common::parse_at;
+use crate::common::split_columns;
+use crate::common::AocError;
 use crate::common::Solution;
+use crate::solver::Info;
+use crate::solver::Solver;
 use crate::util::sign;
 
 #[derive(Clone, Eq, PartialEq)]
-struct Moon {
+pub struct Moon {
     pos: i64,
     vel: i64,
 }
@@ -59,51 +64,87 @@ fn find_period(initial_state: Vec<Moon>, mut moons: Vec<Moon>) -> usize {
     unreachable!();
 }
 
-pub fn solve(lines: &[String]) -> Solution {
-    let mut moons: Vec<Vec<Moon>> = vec![Vec::new(); 3];
-    for line in lines {
-        let mut pos = line.split(',').map(|part| {
-            part.split('=')
-                .nth(1)
-                .unwrap()
-                .split('>')
-                .next()
-                .unwrap()
-                .parse()
-                .unwrap()
-        });
-
-        for m in moons.iter_mut() {
-            m.push(Moon {
-                pos: pos.next().unwrap(),
-                vel: 0,
-            });
-        }
-    }
-
-    let initial_states = moons.clone();
-
-    for _ in 0..1000 {
+fn simulate(mut moons: Vec<Vec<Moon>>, steps: usize) -> Vec<Vec<Moon>> {
+    for _ in 0..steps {
         for m in moons.iter_mut() {
             step(m);
         }
     }
+    moons
+}
 
-    let a_solution: i64 = moons[0]
+fn solve_a(moons: &[Vec<Moon>]) -> i64 {
+    moons[0]
         .iter()
         .zip(moons[1].iter())
         .zip(moons[2].iter())
         .map(|((x, y), z)| energy(x, y, z))
-        .sum();
+        .sum()
+}
 
+fn solve_b(initial_states: &[Vec<Moon>], moons: Vec<Vec<Moon>>) -> usize {
     let periods: Vec<usize> = initial_states
-        .into_iter()
-        .zip(moons.into_iter())
+        .iter()
+        .cloned()
+        .zip(moons)
         .map(|(i, s)| find_period(i, s))
         .collect();
+    lcm(periods[0], periods[1], periods[2])
+}
 
-    (
-        a_solution.to_string(),
-        lcm(periods[0], periods[1], periods[2]).to_string(),
-    )
+pub struct Day12;
+
+impl Solver for Day12 {
+    /// The moons by axis.
+    type Input = Vec<Vec<Moon>>;
+
+    fn info(&self) -> Info {
+        super::info(12, "The N-Body Problem", false)
+    }
+
+    fn parse(&self, lines: &[String]) -> Result<Vec<Vec<Moon>>, AocError> {
+        let mut moons: Vec<Vec<Moon>> = vec![Vec::new(); 3];
+        for (i, line) in lines.iter().enumerate() {
+            let pos = split_columns(line, ",")
+                .map(|(column, part)| match part.find('=') {
+                    Some(eq) => {
+                        parse_at(part[eq + 1..].trim_end_matches('>'), i + 1, column + eq + 1)
+                    }
+                    None => Err(AocError::invalid_input(format!(
+                        "Expected a coordinate like x=1: {:?}",
+                        part
+                    ))
+                    .at(i + 1, column)),
+                })
+                .collect::<Result<Vec<i64>, AocError>>()?;
+            if pos.len() != moons.len() {
+                return Err(
+                    AocError::invalid_input("Expected a position like <x=1, y=2, z=3>")
+                        .at_line(i + 1),
+                );
+            }
+
+            for (m, pos) in moons.iter_mut().zip(pos) {
+                m.push(Moon { pos, vel: 0 });
+            }
+        }
+        Ok(moons)
+    }
+
+    fn part_a(&self, moons: &Vec<Vec<Moon>>) -> Result<String, AocError> {
+        Ok(solve_a(&simulate(moons.clone(), 1000)).to_string())
+    }
+
+    /// `find_period` continues from the state after part A.
+    fn part_b(&self, moons: &Vec<Vec<Moon>>) -> Result<String, AocError> {
+        Ok(solve_b(moons, simulate(moons.clone(), 1000)).to_string())
+    }
+
+    fn solve(&self, moons: &Vec<Vec<Moon>>) -> Result<Solution, AocError> {
+        let simulated = simulate(moons.clone(), 1000);
+        Ok((
+            solve_a(&simulated).to_string(),
+            solve_b(moons, simulated).to_string(),
+        ))
+    }
 }
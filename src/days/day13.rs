@@ -1,75 +1,106 @@
-use crate::common::Solution;
+use crate::common::AocError;
+use crate::intcode::parse_program;
 use crate::intcode::IntcodeComputer;
+use crate::intcode::StopReason;
+use crate::solver::Info;
+use crate::solver::Solver;
 use crate::util::sign;
 use std::collections::HashSet;
 
-fn solve_a(mut computer: IntcodeComputer) -> usize {
-    computer.run_mut(None);
+fn solve_a(computer: IntcodeComputer) -> Result<usize, AocError> {
+    let output: Vec<i64> = computer.try_run(None)?.output.into_iter().collect();
 
     let mut blocks = HashSet::new();
 
-    while !computer.output.is_empty() {
-        let pos = (
-            computer.output.pop_front().unwrap(),
-            computer.output.pop_front().unwrap(),
-        );
-
-        if pos == (-1, 0) {
-            computer.output.pop_front();
-        } else if computer.output.pop_front().unwrap() == 2 {
-            blocks.insert(pos);
+    for tile in output.chunks(3) {
+        match *tile {
+            [-1, 0, _] => {}
+            [x, y, 2] => {
+                blocks.insert((x, y));
+            }
+            [_, _, _] => {}
+            _ => return Err(AocError::no_solution("The game drew an incomplete tile")),
         }
     }
 
-    blocks.len()
+    Ok(blocks.len())
 }
 
-fn solve_b(mut computer: IntcodeComputer) -> i64 {
+fn solve_b(mut computer: IntcodeComputer) -> Result<i64, AocError> {
     computer.prog[0] = 2;
+    computer.pause_on_output = Some(3);
 
     let mut ball_x = 0;
     let mut paddle_x = 0;
     let mut score = 0;
 
-    while computer.is_running() {
-        let joystick = sign(ball_x - paddle_x);
-        computer.run_mut(Some(joystick));
-
-        while !computer.output.is_empty() {
-            let (x, y) = (
-                computer.output.pop_front().unwrap(),
-                computer.output.pop_front().unwrap(),
-            );
-
-            if (x, y) == (-1, 0) {
-                score = computer.output.pop_front().unwrap();
-            } else {
-                match computer.output.pop_front().unwrap() {
-                    3 => {
-                        paddle_x = x;
-                    }
-                    4 => {
-                        ball_x = x;
+    loop {
+        match computer.run_mut(None) {
+            StopReason::OutputReady(_) => {
+                let (x, y, value) = match (
+                    computer.output.pop_front(),
+                    computer.output.pop_front(),
+                    computer.output.pop_front(),
+                ) {
+                    (Some(x), Some(y), Some(value)) => (x, y, value),
+                    _ => return Err(AocError::no_solution("The game drew an incomplete tile")),
+                };
+
+                if (x, y) == (-1, 0) {
+                    score = value;
+                } else {
+                    match value {
+                        3 => {
+                            paddle_x = x;
+                        }
+                        4 => {
+                            ball_x = x;
+                        }
+                        _ => {}
                     }
-                    _ => {}
                 }
             }
+            StopReason::NeedsInput => computer.input.push_back(sign(ball_x - paddle_x)),
+            StopReason::Halted if computer.output.is_empty() => return Ok(score),
+            StopReason::Halted => {
+                return Err(AocError::no_solution("The game drew an incomplete tile"))
+            }
+            StopReason::Error(err) => return Err(err.into()),
+            reason => {
+                return Err(AocError::no_solution(format!(
+                    "The game stopped: {:?}",
+                    reason
+                )))
+            }
         }
     }
-
-    score
 }
 
-pub fn solve(lines: &[String]) -> Solution {
-    let computer: IntcodeComputer = lines.into();
-    (
-        solve_a(computer.clone()).to_string(),
-        solve_b(computer).to_string(),
-    )
+pub struct Day13;
+
+impl Solver for Day13 {
+    type Input = IntcodeComputer;
+
+    fn info(&self) -> Info {
+        super::info(13, "Care Package", true)
+    }
+
+    fn parse(&self, lines: &[String]) -> Result<IntcodeComputer, AocError> {
+        parse_program(lines).map(IntcodeComputer::new)
+    }
+
+    fn part_a(&self, computer: &IntcodeComputer) -> Result<String, AocError> {
+        Ok(solve_a(computer.clone())?.to_string())
+    }
+
+    fn part_b(&self, computer: &IntcodeComputer) -> Result<String, AocError> {
+        Ok(solve_b(computer.clone())?.to_string())
+    }
 }
 
 mod pretty {
     use crate::intcode::IntcodeComputer;
+    use crate::intcode::StopReason;
     use crate::util::sign;
     use std::collections::HashMap;
 
@@ -139,11 +170,15 @@ mod pretty {
     #[allow(dead_code)]
     fn play_game(mut computer: IntcodeComputer) -> State {
         let mut state: State = State::new();
-        computer.run_mut(Some(0));
+        let mut stop_reason = computer.run_mut(Some(0));
+
+        while stop_reason != StopReason::Halted {
+            if let StopReason::Error(err) = stop_reason {
+                panic!("{}", err);
+            }
 
-        while computer.is_running() {
             let joystick = sign(state.ball_x - state.paddle_x);
-            computer.run_mut(Some(joystick));
+            stop_reason = computer.run_mut(Some(joystick));
 
             while !computer.output.is_empty() {
                 let (x, y) = (
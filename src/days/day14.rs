@@ -1,53 +1,66 @@
+use crate::common::parse_at;
+use crate::common::split_columns;
+use crate::common::AocError;
 use crate::common::Solution;
+use crate::solver::Info;
+use crate::solver::Solver;
 use std::collections::HashMap;
 
-fn parse_qtyname(s: &str) -> (&str, i64) {
-    let parts: Vec<&str> = s.trim().split(' ').map(&str::trim).collect();
-    (parts[1], parts[0].parse().unwrap())
+/// Parses a quantity and a chemical like `7 ORE`, found at `line` and `column`.
+fn parse_qtyname(s: &str, line: usize, column: usize) -> Result<(&str, i64), AocError> {
+    match s.split_whitespace().collect::<Vec<&str>>()[..] {
+        [qty, name] => {
+            let column = column + s.len() - s.trim_start().len();
+            Ok((name, parse_at(qty, line, column)?))
+        }
+        _ => Err(AocError::invalid_input(format!(
+            "Expected a quantity and a chemical: {:?}",
+            s.trim()
+        ))
+        .at(line, column)),
+    }
 }
 
-fn produce_everything<'a>(
-    ingredients: &mut HashMap<&'a str, i64>,
-    formulae: &HashMap<&'a str, (i64, HashMap<&'a str, i64>)>,
-) {
+/// The quantity produced by each reaction and the ingredients it takes, by
+/// the name of the product.
+type Formulae = HashMap<String, (i64, HashMap<String, i64>)>;
+
+fn produce_everything<'a>(ingredients: &mut HashMap<&'a str, i64>, formulae: &'a Formulae) {
     while let Some((ingredient, needed)) = ingredients
         .iter_mut()
         .filter(|(_, needed)| **needed > 0)
         .find(|(k, _)| **k != "ORE")
     {
-        let (producing, metaingredients) = formulae.get(ingredient).unwrap();
+        let (producing, metaingredients) = formulae.get(*ingredient).unwrap();
         let times = *needed / producing + (if *needed % producing == 0 { 0 } else { 1 });
         *needed -= times * producing;
 
         for (metaingredient, needed) in metaingredients {
-            *ingredients.entry(metaingredient).or_insert(0) += times * needed;
+            *ingredients.entry(metaingredient.as_str()).or_insert(0) += times * needed;
         }
     }
 }
 
-pub fn solve(lines: &[String]) -> Solution {
-    let formulae: HashMap<&str, (i64, HashMap<&str, i64>)> = lines
-        .iter()
-        .map(|line| {
-            let sides: Vec<&str> = line.split("=>").collect();
-            let (out_name, out_qty) = parse_qtyname(sides[1]);
-            let lhs: HashMap<&str, i64> = sides[0].split(',').map(parse_qtyname).collect();
-            (out_name, (out_qty, lhs))
-        })
-        .collect();
-
+/// Returns the ore needed for one fuel, and the leftover ingredients.
+fn solve_a(formulae: &Formulae) -> (i64, HashMap<&str, i64>) {
     let mut ingredients: HashMap<&str, i64> = vec![("FUEL", 1)].into_iter().collect();
 
-    produce_everything(&mut ingredients, &formulae);
-    let a_solution: i64 = *ingredients.get("ORE").unwrap();
+    produce_everything(&mut ingredients, formulae);
+    (*ingredients.get("ORE").unwrap(), ingredients)
+}
 
+fn solve_b<'a>(
+    formulae: &'a Formulae,
+    ore_per_fuel: i64,
+    mut ingredients: HashMap<&'a str, i64>,
+) -> i64 {
     let mut b_solution: i64 = 1;
     loop {
         let ore_remaining = 1_000_000_000_000 - ingredients.get("ORE").unwrap();
         if ore_remaining >= 0 {
-            let additional_fuel = std::cmp::max(1, ore_remaining / a_solution);
+            let additional_fuel = std::cmp::max(1, ore_remaining / ore_per_fuel);
             *ingredients.get_mut("FUEL").unwrap() += additional_fuel;
-            produce_everything(&mut ingredients, &formulae);
+            produce_everything(&mut ingredients, formulae);
 
             if *ingredients.get("ORE").unwrap() <= 1_000_000_000_000 {
                 b_solution += additional_fuel;
@@ -56,22 +69,105 @@ pub fn solve(lines: &[String]) -> Solution {
             break;
         }
     }
+    b_solution
+}
+
+pub struct Day14;
+
+impl Solver for Day14 {
+    type Input = Formulae;
+
+    fn info(&self) -> Info {
+        super::info(14, "Space Stoichiometry", false)
+    }
+
+    fn parse(&self, lines: &[String]) -> Result<Formulae, AocError> {
+        let mut formulae = Formulae::new();
+        for (i, line) in lines.iter().enumerate() {
+            let (lhs, rhs) = line.split_once("=>").ok_or_else(|| {
+                AocError::invalid_input("Expected a reaction like 1 A, 2 B => 3 C").at_line(i + 1)
+            })?;
+            let (out_name, out_qty) = parse_qtyname(rhs, i + 1, lhs.len() + 3)?;
+            let lhs = split_columns(lhs, ",")
+                .map(|(column, s)| {
+                    parse_qtyname(s, i + 1, column).map(|(name, qty)| (name.to_string(), qty))
+                })
+                .collect::<Result<HashMap<String, i64>, AocError>>()?;
+            formulae.insert(out_name.to_string(), (out_qty, lhs));
+        }
 
-    (a_solution.to_string(), b_solution.to_string())
+        if !formulae.contains_key("FUEL") {
+            return Err(AocError::invalid_input("No reaction produces FUEL"));
+        }
+        for (_, ingredients) in formulae.values() {
+            if let Some(name) = ingredients
+                .keys()
+                .find(|name| *name != "ORE" && !formulae.contains_key(*name))
+            {
+                return Err(AocError::invalid_input(format!(
+                    "No reaction produces {}",
+                    name
+                )));
+            }
+        }
+        Ok(formulae)
+    }
+
+    fn part_a(&self, formulae: &Formulae) -> Result<String, AocError> {
+        Ok(solve_a(formulae).0.to_string())
+    }
+
+    fn part_b(&self, formulae: &Formulae) -> Result<String, AocError> {
+        let (ore_per_fuel, ingredients) = solve_a(formulae);
+        Ok(solve_b(formulae, ore_per_fuel, ingredients).to_string())
+    }
+
+    fn solve(&self, formulae: &Formulae) -> Result<Solution, AocError> {
+        let (ore_per_fuel, ingredients) = solve_a(formulae);
+        Ok((
+            ore_per_fuel.to_string(),
+            solve_b(formulae, ore_per_fuel, ingredients).to_string(),
+        ))
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::solve;
+    use super::Day14;
+    use crate::solver::DynSolver;
 
     #[test]
     fn even_ingredients() {
         assert_eq!(
-            solve(&[
-                "100 ORE => 20 FOO".to_string(),
-                "1 FOO => 1 FUEL".to_string()
-            ]),
+            (&Day14 as &dyn DynSolver)
+                .solve(&[
+                    "100 ORE => 20 FOO".to_string(),
+                    "1 FOO => 1 FUEL".to_string()
+                ])
+                .unwrap(),
             ("100".to_string(), "200000000000".to_string())
         );
     }
+
+    #[test]
+    fn reports_malformed_reactions() {
+        let solve = |line: &str| {
+            (&Day14 as &dyn DynSolver)
+                .solve(&[line.to_string()])
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            solve("10 ORE => 1 FUEL, 2 FOO"),
+            r#"Day 14: line 1, column 10: Invalid input: Expected a quantity and a chemical: "1 FUEL, 2 FOO""#
+        );
+        assert_eq!(
+            solve("10 ORE, x FOO => 1 FUEL"),
+            r#"Day 14: line 1, column 9: Invalid input: "x": invalid digit found in string"#
+        );
+        assert_eq!(
+            solve("10 ORE, 1 FOO => 1 FUEL"),
+            "Day 14: Invalid input: No reaction produces FOO"
+        );
+    }
 }
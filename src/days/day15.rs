@@ -1,5 +1,10 @@
+use crate::common::AocError;
 use crate::common::Solution;
+use crate::intcode::parse_program;
 use crate::intcode::IntcodeComputer;
+use crate::intcode::StopReason;
+use crate::solver::Info;
+use crate::solver::Solver;
 use std::collections::HashMap;
 use std::collections::VecDeque;
 
@@ -100,7 +105,7 @@ fn print_state(state: &State, queue: &VecDeque<State>, world: &World) {
     );
 }
 
-fn build_map(computer: IntcodeComputer) -> World {
+fn build_map(computer: IntcodeComputer) -> Result<World, AocError> {
     let mut queue: VecDeque<State> = VecDeque::new();
     let mut world = World::new();
 
@@ -130,9 +135,22 @@ fn build_map(computer: IntcodeComputer) -> World {
                 println!();
             }
 
-            state.computer.run_mut(Some(dir_to_cmd(state.dir)));
+            match state.computer.run_mut(Some(dir_to_cmd(state.dir))) {
+                StopReason::NeedsInput => {}
+                StopReason::Error(err) => return Err(err.into()),
+                reason => {
+                    return Err(AocError::no_solution(format!(
+                        "The droid stopped: {:?}",
+                        reason
+                    )))
+                }
+            }
 
-            let output = state.computer.output.pop_front().unwrap();
+            let output = state
+                .computer
+                .output
+                .pop_front()
+                .ok_or_else(|| AocError::no_solution("The droid did not report its status"))?;
             if output == 0 {
                 world.tiles.insert(new_pos, Tile::Wall);
             } else {
@@ -170,12 +188,15 @@ fn build_map(computer: IntcodeComputer) -> World {
         }
     }
 
-    world
+    Ok(world)
 }
 
-fn solve_a(computer: IntcodeComputer) -> (u32, World) {
-    let world = build_map(computer);
-    (world.goal.unwrap().1, world)
+fn solve_a(computer: IntcodeComputer) -> Result<(u32, World), AocError> {
+    let world = build_map(computer)?;
+    match world.goal {
+        Some((_, dist)) => Ok((dist, world)),
+        None => Err(AocError::no_solution("Found no oxygen system")),
+    }
 }
 
 fn solve_b(mut world: World) -> u32 {
@@ -208,10 +229,31 @@ fn solve_b(mut world: World) -> u32 {
     time - 1
 }
 
-pub fn solve(lines: &[String]) -> Solution {
-    let computer: IntcodeComputer = lines.into();
-    let (a_solution, world) = solve_a(computer);
-    (a_solution.to_string(), solve_b(world).to_string())
+pub struct Day15;
+
+impl Solver for Day15 {
+    type Input = IntcodeComputer;
+
+    fn info(&self) -> Info {
+        super::info(15, "Oxygen System", true)
+    }
+
+    fn parse(&self, lines: &[String]) -> Result<IntcodeComputer, AocError> {
+        parse_program(lines).map(IntcodeComputer::new)
+    }
+
+    fn part_a(&self, computer: &IntcodeComputer) -> Result<String, AocError> {
+        Ok(solve_a(computer.clone())?.0.to_string())
+    }
+
+    fn part_b(&self, computer: &IntcodeComputer) -> Result<String, AocError> {
+        Ok(solve_b(solve_a(computer.clone())?.1).to_string())
+    }
+
+    fn solve(&self, computer: &IntcodeComputer) -> Result<Solution, AocError> {
+        let (a_solution, world) = solve_a(computer.clone())?;
+        Ok((a_solution.to_string(), solve_b(world).to_string()))
+    }
 }
 
 #[cfg(test)]
@@ -1,4 +1,7 @@
-use crate::common::Solution;
+use crate::common::first_line;
+use crate::common::AocError;
+use crate::solver::Info;
+use crate::solver::Solver;
 use crate::util::pascal::PASCAL_DIAGONAL_100;
 
 const NUM_PHASES: usize = 100;
@@ -55,7 +58,7 @@ fn lcm(a: usize, b: usize) -> usize {
     (a / gcdab) * b
 }
 
-fn solve_b(digits: Vec<i32>) -> String {
+fn solve_b(digits: Vec<i32>) -> Result<String, AocError> {
     fn transform(digits: Vec<i32>, msg_offset: usize) -> Vec<String> {
         let l = digits.len();
         let digits_offset: Vec<i32> = digits
@@ -105,17 +108,42 @@ fn solve_b(digits: Vec<i32>) -> String {
         .fold(0, |result, d| result * 10 + (*d as usize));
 
     if msg_offset >= digits.len() * 10000 / 2 {
-        transform(digits, msg_offset).join("")
+        Ok(transform(digits, msg_offset).join(""))
     } else {
-        panic!("Don't know how to solve when message offset is not past half the sequence!");
+        Err(AocError::no_solution(
+            "Don't know how to solve when message offset is not past half the sequence",
+        ))
     }
 }
 
-pub fn solve(lines: &[String]) -> Solution {
-    let digits: Vec<i32> = lines[0].chars().map(|c| (c as i32) - 48).collect();
-    let a_solution = solve_a(digits.clone());
-    let b_solution = solve_b(digits);
-    (a_solution, b_solution)
+pub struct Day16;
+
+impl Solver for Day16 {
+    type Input = Vec<i32>;
+
+    fn info(&self) -> Info {
+        super::info(16, "Flawed Frequency Transmission", false)
+    }
+
+    fn parse(&self, lines: &[String]) -> Result<Vec<i32>, AocError> {
+        first_line(lines)?
+            .chars()
+            .enumerate()
+            .map(|(i, c)| {
+                c.to_digit(10).map(|d| d as i32).ok_or_else(|| {
+                    AocError::invalid_input(format!("Not a digit: {:?}", c)).at(1, i + 1)
+                })
+            })
+            .collect()
+    }
+
+    fn part_a(&self, digits: &Vec<i32>) -> Result<String, AocError> {
+        Ok(solve_a(digits.clone()))
+    }
+
+    fn part_b(&self, digits: &Vec<i32>) -> Result<String, AocError> {
+        solve_b(digits.clone())
+    }
 }
 
 #[cfg(test)]
@@ -129,7 +157,7 @@ mod tests {
 
     fn check_b(input: &str, expected_output: &str) {
         let digits: Vec<i32> = input.chars().map(|c| (c as i32) - 48).collect();
-        let sol = super::solve_b(digits);
+        let sol = super::solve_b(digits).unwrap();
         assert_eq!(sol, expected_output);
     }
 
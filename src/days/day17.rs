@@ -1,5 +1,10 @@
+use crate::common::AocError;
 use crate::common::Solution;
+use crate::intcode::ascii::AsciiIntcode;
+use crate::intcode::parse_program;
 use crate::intcode::IntcodeComputer;
+use crate::solver::Info;
+use crate::solver::Solver;
 use std::collections::HashSet;
 use std::collections::LinkedList;
 
@@ -108,63 +113,65 @@ fn print_state(state: &State) {
     );
 }
 
-fn build_map(mut computer: IntcodeComputer) -> State {
+fn build_map(computer: IntcodeComputer) -> Result<State, AocError> {
     let mut state = State::new();
 
     let mut read_x = 0;
     let mut read_y = 0;
 
-    while computer.is_running() {
-        computer.run_mut(None);
-
-        for output in computer.output.drain(..) {
-            match output as u8 as char {
-                '.' => {
-                    read_x += 1;
-                }
-                '#' => {
-                    state.world.insert((read_x, read_y));
-                    read_x += 1;
-                }
-                '^' | '>' | 'v' | '<' => {
-                    state.robot_pos = (read_x, read_y);
-                    state.robot_dir = match output as u8 as char {
-                        '^' => (0, -1),
-                        '>' => (1, 0),
-                        'v' => (0, 1),
-                        '<' => (-1, 0),
-                        _ => unreachable!(),
-                    };
-                    state.world.insert((read_x, read_y));
-                    read_x += 1;
-                }
-                'X' => {
-                    state.robot_pos = (read_x, read_y);
-                    read_x += 1;
-                }
-                '\n' => {
-                    read_y += 1;
-                    read_x = 0;
-                }
-                _ => unreachable!(),
-            };
-        }
+    let output = AsciiIntcode::new(computer).read_until_prompt()?;
+    for c in output.chars() {
+        match c {
+            '.' => {
+                read_x += 1;
+            }
+            '#' => {
+                state.world.insert((read_x, read_y));
+                read_x += 1;
+            }
+            '^' | '>' | 'v' | '<' => {
+                state.robot_pos = (read_x, read_y);
+                state.robot_dir = match c {
+                    '^' => (0, -1),
+                    '>' => (1, 0),
+                    'v' => (0, 1),
+                    '<' => (-1, 0),
+                    _ => unreachable!(),
+                };
+                state.world.insert((read_x, read_y));
+                read_x += 1;
+            }
+            'X' => {
+                state.robot_pos = (read_x, read_y);
+                read_x += 1;
+            }
+            '\n' => {
+                read_y += 1;
+                read_x = 0;
+            }
+            _ => {
+                return Err(AocError::no_solution(format!(
+                    "Unexpected character in camera image: {:?}",
+                    c
+                )))
+            }
+        };
+    }
 
-        if ENABLE_OUTPUT {
-            println!();
-            print_state(&state);
-        }
+    if ENABLE_OUTPUT {
+        println!();
+        print_state(&state);
     }
-    state
+    Ok(state)
 }
 
-fn solve_a(computer: IntcodeComputer) -> (State, i64) {
-    let finish = build_map(computer);
+fn solve_a(computer: IntcodeComputer) -> Result<(State, i64), AocError> {
+    let finish = build_map(computer)?;
 
     let intrsct = intersections(&finish.world);
     let solution = intrsct.into_iter().map(|(x, y)| x * y).sum::<i64>();
 
-    (finish, solution)
+    Ok((finish, solution))
 }
 
 type Route = Vec<Step>;
@@ -197,12 +204,20 @@ fn compress_route(route: Route) -> Route {
     })
 }
 
-fn simplest_path(world: &HashSet<Point>, start_pos: Point, start_dir: Point) -> Route {
+fn simplest_path(
+    world: &HashSet<Point>,
+    start_pos: Point,
+    start_dir: Point,
+) -> Result<Route, AocError> {
     let mut pos = start_pos;
     let mut dir = start_dir;
     let mut route = Vec::new();
+    let mut visited = HashSet::new();
 
     loop {
+        if !visited.insert((pos, dir)) {
+            return Err(AocError::no_solution("The scaffold loops back on itself"));
+        }
         let next = add(&pos, &dir);
         if is_path(world, &next) {
             route.push(Step::F(1));
@@ -220,7 +235,7 @@ fn simplest_path(world: &HashSet<Point>, start_pos: Point, start_dir: Point) ->
                     dir = dir_right;
                     pos = add(&pos, &dir);
                 } else {
-                    return route;
+                    return Ok(route);
                 }
             }
         }
@@ -295,16 +310,17 @@ where
             subseqs
         } else if let Some(prefix) = subseqs.iter().find(|subseq| seq.starts_with(subseq)) {
             fill_subseqs(&seq[prefix.len()..], num_subseqs, subseqs)
-        } else {
-            let next = find_longest_repeated_subseq(seq).unwrap();
+        } else if let Some(next) = find_longest_repeated_subseq(seq) {
             subseqs.push(next);
             fill_subseqs(&seq[next.len()..], num_subseqs, subseqs)
+        } else {
+            subseqs
         }
     }
 
     let mut subseqs: Vec<&[T]> = fill_subseqs(seq, num_subseqs, Vec::new());
 
-    while !subseqs[0].is_empty() {
+    while subseqs.first().is_some_and(|subseq| !subseq.is_empty()) {
         if let Some(covering) = find_subseq_covering(seq, &subseqs) {
             return Some((subseqs, covering));
         } else {
@@ -327,53 +343,73 @@ where
     None
 }
 
-fn solve_b(finish_a: State, mut computer: IntcodeComputer) -> i64 {
+fn solve_b(finish_a: State, mut computer: IntcodeComputer) -> Result<i64, AocError> {
     computer.prog[0] = 2;
 
-    let full_route = simplest_path(&finish_a.world, finish_a.robot_pos, finish_a.robot_dir);
+    let full_route = simplest_path(&finish_a.world, finish_a.robot_pos, finish_a.robot_dir)?;
     let compressed_route = compress_route(full_route.clone());
 
-    let (segments, mut sequence) = find_covering_subseqs(&compressed_route, 3)
+    let (segments, sequence) = find_covering_subseqs(&compressed_route, 3)
         .or_else(|| find_covering_subseqs(&full_route, 3))
-        .expect("Found no solution!");
+        .ok_or_else(|| AocError::no_solution("The route doesn't fit in three functions"))?;
 
-    let mut input_sequence = vec!['A' as i64 + sequence.pop_front().unwrap() as i64];
-    for i in sequence {
-        input_sequence.push(b',' as i64);
-        input_sequence.push('A' as i64 + i as i64);
-    }
-    input_sequence.push(b'\n' as i64);
+    let mut robot = AsciiIntcode::new(computer);
+    robot.send_line(
+        &sequence
+            .into_iter()
+            .map(|i| ((b'A' + i as u8) as char).to_string())
+            .collect::<Vec<String>>()
+            .join(","),
+    );
     for seg in segments {
-        for cmd in seg {
-            let chars: Vec<char> = match cmd {
-                Step::F(d) => d.to_string().chars().collect(),
-                Step::L(d) => vec!['L', ',']
-                    .into_iter()
-                    .chain(d.to_string().chars())
-                    .collect(),
-                Step::R(d) => vec!['R', ',']
-                    .into_iter()
-                    .chain(d.to_string().chars())
-                    .collect(),
-            };
-            input_sequence.append(&mut chars.into_iter().map(|c| c as u8 as i64).collect());
-            input_sequence.push(b',' as i64);
-        }
-        input_sequence.pop();
-        input_sequence.push(b'\n' as i64);
+        robot.send_line(
+            &seg.iter()
+                .map(|cmd| match cmd {
+                    Step::F(d) => d.to_string(),
+                    Step::L(d) => format!("L,{}", d),
+                    Step::R(d) => format!("R,{}", d),
+                })
+                .collect::<Vec<String>>()
+                .join(","),
+        );
     }
-    input_sequence.push(b'n' as i64);
-    input_sequence.push(b'\n' as i64);
+    robot.send_line("n");
 
-    let output = computer.run(input_sequence).output;
-    output[output.len() - 1]
+    robot.read_until_prompt()?;
+    robot
+        .read_values()
+        .last()
+        .copied()
+        .ok_or_else(|| AocError::no_solution("The robot reported no dust"))
 }
 
-pub fn solve(lines: &[String]) -> Solution {
-    let computer: IntcodeComputer = lines.into();
-    let (a_finish, a_solution) = solve_a(computer.clone());
-    let b_solution = solve_b(a_finish, computer);
-    (a_solution.to_string(), b_solution.to_string())
+pub struct Day17;
+
+impl Solver for Day17 {
+    type Input = IntcodeComputer;
+
+    fn info(&self) -> Info {
+        super::info(17, "Set and Forget", true)
+    }
+
+    fn parse(&self, lines: &[String]) -> Result<IntcodeComputer, AocError> {
+        parse_program(lines).map(IntcodeComputer::new)
+    }
+
+    fn part_a(&self, computer: &IntcodeComputer) -> Result<String, AocError> {
+        Ok(solve_a(computer.clone())?.1.to_string())
+    }
+
+    fn part_b(&self, computer: &IntcodeComputer) -> Result<String, AocError> {
+        let (a_finish, _) = solve_a(computer.clone())?;
+        Ok(solve_b(a_finish, computer.clone())?.to_string())
+    }
+
+    fn solve(&self, computer: &IntcodeComputer) -> Result<Solution, AocError> {
+        let (a_finish, a_solution) = solve_a(computer.clone())?;
+        let b_solution = solve_b(a_finish, computer.clone())?;
+        Ok((a_solution.to_string(), b_solution.to_string()))
+    }
 }
 
 #[cfg(test)]
@@ -1,4 +1,7 @@
+use crate::common::AocError;
 use crate::common::Solution;
+use crate::solver::Info;
+use crate::solver::Solver;
 use std::cmp::Reverse;
 use std::collections::BinaryHeap;
 use std::collections::HashMap;
@@ -114,7 +117,7 @@ impl std::fmt::Debug for KeySet {
 }
 
 #[derive(Eq, PartialEq)]
-struct World {
+pub struct World {
     tiles: Vec<Vec<Tile>>,
     keys: KeySet,
 }
@@ -135,7 +138,7 @@ struct Route {
 }
 
 impl<'world> Navigation<'world> {
-    fn new(world: &World, part_b_walls_enabled: bool) -> Navigation {
+    fn new(world: &World, part_b_walls_enabled: bool) -> Navigation<'_> {
         Navigation {
             world,
             moves: HashMap::new(),
@@ -154,7 +157,7 @@ impl<'world> Navigation<'world> {
             len: usize,
         }
 
-        if self.moves.get(&from).is_none() {
+        if !self.moves.contains_key(&from) {
             let mut moves: Vec<Route> = Vec::new();
             let mut visited: HashSet<Point> = HashSet::new();
             let mut queue: VecDeque<PartialRoute> = VecDeque::new();
@@ -300,44 +303,48 @@ fn print_state(world: &World, state: &State) {
     );
 }
 
-fn parse_world(lines: &[String]) -> (World, Point) {
-    let mut player_pos = (0, 0);
+fn parse_world(lines: &[String]) -> Result<(World, Point), AocError> {
+    let mut player_pos = None;
     let mut keys = KeySet::new();
-    let mut tiles: Vec<Vec<Tile>> = lines
-        .iter()
-        .enumerate()
-        .map(|(y, line)| {
-            line.chars()
-                .enumerate()
-                .map(|(x, c)| match c {
-                    '#' => Wall,
-                    '.' => Floor,
-                    '@' => {
-                        player_pos = (x, y);
-                        Floor
-                    }
-                    a => {
-                        if a == a.to_ascii_uppercase() {
-                            Door(a.into())
-                        } else {
-                            keys = keys.with(a.into());
-                            Key(a.into())
+    let mut tiles: Vec<Vec<Tile>> =
+        lines
+            .iter()
+            .enumerate()
+            .map(|(y, line)| {
+                line.chars()
+                    .enumerate()
+                    .map(|(x, c)| match c {
+                        '#' => Ok(Wall),
+                        '.' => Ok(Floor),
+                        '@' => {
+                            player_pos = Some((x, y));
+                            Ok(Floor)
                         }
-                    }
-                })
-                .collect()
-        })
-        .collect();
-
-    if tiles[player_pos.1 - 1][player_pos.0 - 1] == Floor
-        && tiles[player_pos.1 - 1][player_pos.0] == Floor
-        && tiles[player_pos.1 - 1][player_pos.0 + 1] == Floor
-        && tiles[player_pos.1][player_pos.0 - 1] == Floor
-        && tiles[player_pos.1][player_pos.0] == Floor
-        && tiles[player_pos.1][player_pos.0 + 1] == Floor
-        && tiles[player_pos.1 + 1][player_pos.0 - 1] == Floor
-        && tiles[player_pos.1 + 1][player_pos.0] == Floor
-        && tiles[player_pos.1 + 1][player_pos.0 + 1] == Floor
+                        'A'..='Z' => Ok(Door(c.into())),
+                        'a'..='z' => {
+                            keys = keys.with(c.into());
+                            Ok(Key(c.into()))
+                        }
+                        _ => Err(AocError::invalid_input(format!("Unknown tile: {:?}", c))
+                            .at(y + 1, x + 1)),
+                    })
+                    .collect()
+            })
+            .collect::<Result<_, AocError>>()?;
+
+    let player_pos = match player_pos {
+        Some((x, y)) if x > 0 && y > 0 => (x, y),
+        Some((x, y)) => {
+            return Err(
+                AocError::invalid_input("The entrance is on the edge of the map").at(y + 1, x + 1),
+            )
+        }
+        None => return Err(AocError::invalid_input("The map has no entrance")),
+    };
+
+    let is_floor = |x: usize, y: usize| tiles.get(y).and_then(|row| row.get(x)) == Some(&Floor);
+    if (player_pos.1 - 1..=player_pos.1 + 1)
+        .all(|y| (player_pos.0 - 1..=player_pos.0 + 1).all(|x| is_floor(x, y)))
     {
         tiles[player_pos.1][player_pos.0 - 1] = FloorThenWall;
         tiles[player_pos.1 - 1][player_pos.0] = FloorThenWall;
@@ -345,7 +352,7 @@ fn parse_world(lines: &[String]) -> (World, Point) {
         tiles[player_pos.1 + 1][player_pos.0] = FloorThenWall;
     }
 
-    (World { tiles, keys }, player_pos)
+    Ok((World { tiles, keys }, player_pos))
 }
 
 fn duplication_key(keys: KeySet, points: &[Point]) -> u128 {
@@ -358,8 +365,8 @@ fn duplication_key(keys: KeySet, points: &[Point]) -> u128 {
     result
 }
 
-fn dijkstra<'world>(
-    world: &'world World,
+fn dijkstra(
+    world: &World,
     start_positions: Vec<Point>,
     navigation: &mut Navigation,
 ) -> Option<State> {
@@ -414,12 +421,14 @@ fn dijkstra<'world>(
     None
 }
 
-fn solve_a(world: &World, pos: Point, navigation: &mut Navigation) -> usize {
+fn solve_a(world: &World, pos: Point, navigation: &mut Navigation) -> Result<usize, AocError> {
     let found = dijkstra(world, vec![pos], navigation);
-    found.unwrap().len
+    found
+        .map(|state| state.len)
+        .ok_or_else(|| AocError::no_solution("Some keys can't be reached"))
 }
 
-fn solve_b(world: &World, pos: Point, navigation: &mut Navigation) -> usize {
+fn solve_b(world: &World, pos: Point, navigation: &mut Navigation) -> Result<usize, AocError> {
     let pos = vec![
         (pos.0 - 1, pos.1 - 1),
         (pos.0 - 1, pos.1 + 1),
@@ -430,17 +439,40 @@ fn solve_b(world: &World, pos: Point, navigation: &mut Navigation) -> usize {
     navigation.enable_part_b_walls();
 
     let found = dijkstra(world, pos, navigation);
-    found.unwrap().len
+    found
+        .map(|state| state.len)
+        .ok_or_else(|| AocError::no_solution("Some keys can't be reached by the four robots"))
 }
 
-pub fn solve(lines: &[String]) -> Solution {
-    let (world, pos) = parse_world(lines);
+pub struct Day18;
 
-    let mut navigation = Navigation::new(&world, false);
+impl Solver for Day18 {
+    type Input = (World, Point);
 
-    let a_solution = solve_a(&world, pos, &mut navigation);
-    let b_solution = solve_b(&world, pos, &mut navigation);
-    (a_solution.to_string(), b_solution.to_string())
+    fn info(&self) -> Info {
+        super::info(18, "Many-Worlds Interpretation", false)
+    }
+
+    fn parse(&self, lines: &[String]) -> Result<(World, Point), AocError> {
+        parse_world(lines)
+    }
+
+    fn part_a(&self, (world, pos): &(World, Point)) -> Result<String, AocError> {
+        Ok(solve_a(world, *pos, &mut Navigation::new(world, false))?.to_string())
+    }
+
+    fn part_b(&self, (world, pos): &(World, Point)) -> Result<String, AocError> {
+        Ok(solve_b(world, *pos, &mut Navigation::new(world, true))?.to_string())
+    }
+
+    /// Part B reuses the routes found in part A.
+    fn solve(&self, (world, pos): &(World, Point)) -> Result<Solution, AocError> {
+        let mut navigation = Navigation::new(world, false);
+
+        let a_solution = solve_a(world, *pos, &mut navigation)?;
+        let b_solution = solve_b(world, *pos, &mut navigation)?;
+        Ok((a_solution.to_string(), b_solution.to_string()))
+    }
 }
 
 #[cfg(test)]
@@ -451,18 +483,18 @@ mod tests {
 
     fn parse(input: &str) -> (World, Point) {
         let lines: Vec<String> = input.trim().lines().map(|l| l.trim().to_string()).collect();
-        super::parse_world(&lines)
+        super::parse_world(&lines).unwrap()
     }
 
     fn check_a(expected_output: usize, input: &str) {
         let (world, pos) = parse(input);
-        let solution = super::solve_a(&world, pos, &mut Navigation::new(&world, false));
+        let solution = super::solve_a(&world, pos, &mut Navigation::new(&world, false)).unwrap();
         assert_eq!(solution, expected_output);
     }
 
     fn check_b(expected_output: usize, input: &str) {
         let (world, pos) = parse(input);
-        let solution = super::solve_b(&world, pos, &mut Navigation::new(&world, true));
+        let solution = super::solve_b(&world, pos, &mut Navigation::new(&world, true)).unwrap();
         assert_eq!(solution, expected_output);
     }
 
@@ -1,54 +1,72 @@
+use crate::common::AocError;
 use crate::common::Solution;
+use crate::intcode::parse_program;
 use crate::intcode::IntcodeComputer;
+use crate::solver::Info;
+use crate::solver::Solver;
 
 type Point = (usize, usize);
 
-fn check(computer: &IntcodeComputer, (x, y): Point) -> bool {
-    computer.clone().run(vec![x as i64, y as i64]).output[0] == 1
+fn check(computer: &IntcodeComputer, (x, y): Point) -> Result<bool, AocError> {
+    Ok(computer
+        .clone()
+        .try_run(vec![x as i64, y as i64])?
+        .output
+        .front()
+        == Some(&1))
 }
 
-fn compute_minx(computer: &IntcodeComputer, prev_minx: usize, y: usize) -> usize {
+fn compute_minx(computer: &IntcodeComputer, prev_minx: usize, y: usize) -> Result<usize, AocError> {
     for x in prev_minx..(prev_minx + 10) {
-        if check(computer, (x, y)) {
-            return x;
+        if check(computer, (x, y))? {
+            return Ok(x);
         }
     }
-    0
+    Ok(0)
 }
 
-fn compute_maxx(computer: &IntcodeComputer, prev_maxx: usize, y: usize) -> usize {
-    if check(computer, (prev_maxx, y)) {
+fn compute_maxx(computer: &IntcodeComputer, prev_maxx: usize, y: usize) -> Result<usize, AocError> {
+    if check(computer, (prev_maxx, y))? {
         for x in (prev_maxx + 1).. {
-            if !check(computer, (x, y)) {
-                return x;
+            if !check(computer, (x, y))? {
+                return Ok(x);
             }
         }
     } else {
         for x in (1..prev_maxx).rev() {
-            if check(computer, (x, y)) {
-                return x + 1;
+            if check(computer, (x, y))? {
+                return Ok(x + 1);
             }
         }
     }
-    0
+    Ok(0)
 }
 
-fn solve_b(computer: IntcodeComputer) -> (usize, usize) {
-    const DIM_WANTED: usize = 100;
-
+/// Counts the affected points in the 50x50 area closest to the emitter, and
+/// returns the count with the edges of the beam at y = 49.
+fn solve_a(computer: &IntcodeComputer) -> Result<(usize, usize, usize), AocError> {
     let mut maxx = 0;
     let mut minx = 0;
 
     let mut a_solution = 0;
 
     for y in 0..50 {
-        minx = compute_minx(&computer, minx, y);
-        maxx = compute_maxx(&computer, maxx, y);
+        minx = compute_minx(computer, minx, y)?;
+        maxx = compute_maxx(computer, maxx, y)?;
         if maxx < minx {
-            maxx = compute_maxx(&computer, minx + 1, y);
+            maxx = compute_maxx(computer, minx + 1, y)?;
         }
         a_solution += std::cmp::min(50, maxx) - minx;
     }
+    Ok((a_solution, minx, maxx))
+}
+
+fn solve_b(computer: &IntcodeComputer, minx: usize, maxx: usize) -> Result<usize, AocError> {
+    const DIM_WANTED: usize = 100;
+
+    if maxx <= minx {
+        return Err(AocError::no_solution("The beam doesn't widen"));
+    }
 
     let k1: f64 = (maxx as f64) / 49_f64;
     let k2: f64 = (minx as f64) / 49_f64;
@@ -56,26 +74,53 @@ fn solve_b(computer: IntcodeComputer) -> (usize, usize) {
     let y_guess: usize = ((DIM_WANTED as f64 * (1_f64 + k2) - k2) / (k1 - k2)).round() as usize;
 
     let mut y_min = 50;
-    let mut y_max = y_guess * 2;
+    let mut y_max = y_guess.saturating_mul(2);
     let mut x_max = maxx;
 
     while y_max > y_min {
-        let y = (y_max + y_min) / 2;
+        let y = y_min + (y_max - y_min) / 2;
         let maxx_guess = (y as f64 * k1).round() as usize;
-        let maxx = compute_maxx(&computer, maxx_guess, y);
-        let x = maxx - DIM_WANTED;
-        if check(&computer, (x, y + DIM_WANTED - 1)) {
-            y_max = y;
-            x_max = x;
-        } else {
-            y_min = y + 1;
+        let maxx = compute_maxx(computer, maxx_guess, y)?;
+        match maxx.checked_sub(DIM_WANTED) {
+            Some(x) if check(computer, (x, y + DIM_WANTED - 1))? => {
+                y_max = y;
+                x_max = x;
+            }
+            _ => {
+                y_min = y + 1;
+            }
         }
     }
-    (a_solution, x_max * 10000 + y_max)
+    Ok(x_max * 10000 + y_max)
 }
 
-pub fn solve(lines: &[String]) -> Solution {
-    let computer: IntcodeComputer = lines.into();
-    let (a_solution, b_solution) = solve_b(computer);
-    (a_solution.to_string(), b_solution.to_string())
+pub struct Day19;
+
+impl Solver for Day19 {
+    type Input = IntcodeComputer;
+
+    fn info(&self) -> Info {
+        super::info(19, "Tractor Beam", true)
+    }
+
+    fn parse(&self, lines: &[String]) -> Result<IntcodeComputer, AocError> {
+        parse_program(lines).map(IntcodeComputer::new)
+    }
+
+    fn part_a(&self, computer: &IntcodeComputer) -> Result<String, AocError> {
+        Ok(solve_a(computer)?.0.to_string())
+    }
+
+    fn part_b(&self, computer: &IntcodeComputer) -> Result<String, AocError> {
+        let (_, minx, maxx) = solve_a(computer)?;
+        Ok(solve_b(computer, minx, maxx)?.to_string())
+    }
+
+    fn solve(&self, computer: &IntcodeComputer) -> Result<Solution, AocError> {
+        let (a_solution, minx, maxx) = solve_a(computer)?;
+        Ok((
+            a_solution.to_string(),
+            solve_b(computer, minx, maxx)?.to_string(),
+        ))
+    }
 }
@@ -1,4 +1,6 @@
-use crate::common::Solution;
+use crate::common::AocError;
+use crate::solver::Info;
+use crate::solver::Solver;
 use std::collections::BinaryHeap;
 use std::collections::HashMap;
 use std::collections::HashSet;
@@ -96,7 +98,7 @@ impl PartialOrd for State {
     }
 }
 
-struct World {
+pub struct World {
     tiles: HashMap<Point, Tile>,
     warps: HashMap<Point, Warp>,
     start: Point,
@@ -106,29 +108,34 @@ struct World {
 
 #[derive(Debug)]
 struct Warp {
+    #[allow(dead_code)]
     name: String,
     to: Point,
 }
 
 impl World {
-    fn parse(lines: &[String]) -> World {
-        let tiles: HashMap<Point, Tile> = lines
-            .iter()
-            .enumerate()
-            .flat_map(|(y, line)| {
-                line.chars().enumerate().flat_map(move |(x, c)| match c {
-                    ' ' => None,
-                    '#' => Some(((x as i32, y as i32), Tile::Wall)),
-                    '.' => Some(((x as i32, y as i32), Tile::Floor)),
-                    a => Some(((x as i32, y as i32), Tile::Warp(a.to_string()))),
-                })
-            })
-            .collect();
+    fn parse(lines: &[String]) -> Result<World, AocError> {
+        let mut tiles: HashMap<Point, Tile> = HashMap::new();
+        for (y, line) in lines.iter().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                let tile = match c {
+                    ' ' => continue,
+                    '#' => Tile::Wall,
+                    '.' => Tile::Floor,
+                    'A'..='Z' => Tile::Warp(c.to_string()),
+                    _ => {
+                        return Err(AocError::invalid_input(format!("Unknown tile: {:?}", c))
+                            .at(y + 1, x + 1))
+                    }
+                };
+                tiles.insert((x as i32, y as i32), tile);
+            }
+        }
 
         fn find_start_of_warp_name(tiles: &HashMap<Point, Tile>, pos: Point) -> Point {
             let continuation = &[(pos.0 - 1, pos.1), (pos.0, pos.1 - 1)]
                 .iter()
-                .flat_map(|next_pos| match tiles.get(&next_pos) {
+                .flat_map(|next_pos| match tiles.get(next_pos) {
                     Some(Tile::Warp(_)) => Some(find_start_of_warp_name(tiles, *next_pos)),
                     _ => None,
                 })
@@ -139,7 +146,7 @@ impl World {
         fn read_warp_name(tiles: &HashMap<Point, Tile>, pos: Point) -> String {
             let continuation = &[(pos.0 + 1, pos.1), (pos.0, pos.1 + 1)]
                 .iter()
-                .flat_map(|next_pos| match tiles.get(&next_pos) {
+                .flat_map(|next_pos| match tiles.get(next_pos) {
                     Some(Tile::Warp(_)) => Some(read_warp_name(tiles, *next_pos)),
                     _ => None,
                 })
@@ -154,10 +161,13 @@ impl World {
         let mut warp_names: HashMap<String, Vec<Point>> = HashMap::new();
         for (pos, tile) in tiles.iter() {
             if let Tile::Warp(_) = tile {
-                let warp_pos = walk_to_edge(&tiles, *pos, None).unwrap();
+                let warp_pos = walk_to_edge(&tiles, *pos, None).ok_or_else(|| {
+                    AocError::invalid_input("Portal label is not next to the maze")
+                        .at(pos.1 as usize + 1, pos.0 as usize + 1)
+                })?;
                 let name_start = find_start_of_warp_name(&tiles, *pos);
                 let name = read_warp_name(&tiles, name_start);
-                let points = warp_names.entry(name).or_insert_with(Vec::new);
+                let points = warp_names.entry(name).or_default();
                 if !points.contains(&warp_pos) {
                     points.push(warp_pos);
                 }
@@ -194,42 +204,55 @@ impl World {
             })
         }
 
-        let start = walk_to_edge(&tiles, warp_names.get("AA").unwrap()[0], None).unwrap();
-        let goal = walk_to_edge(&tiles, warp_names.get("ZZ").unwrap()[0], None).unwrap();
-
-        let warps = warp_names
-            .into_iter()
-            .filter(|(name, _)| name != "AA" && name != "ZZ")
-            .fold(HashMap::new(), |mut warps, (name, points)| {
-                warps.insert(
-                    points[0],
-                    Warp {
-                        name: name.clone(),
-                        to: walk_to_floor(&tiles, points[1]).unwrap(),
-                    },
-                );
-                warps.insert(
-                    points[1],
-                    Warp {
+        let endpoint = |name: &str| {
+            warp_names
+                .get(name)
+                .and_then(|points| walk_to_edge(&tiles, points[0], None))
+                .ok_or_else(|| AocError::invalid_input(format!("The maze has no {}", name)))
+        };
+        let start = endpoint("AA")?;
+        let goal = endpoint("ZZ")?;
+
+        let mut warps = HashMap::new();
+        for (name, points) in warp_names {
+            if name == "AA" || name == "ZZ" {
+                continue;
+            }
+            match points[..] {
+                [p0, p1] => {
+                    let to0 = walk_to_floor(&tiles, p0).unwrap();
+                    let to1 = walk_to_floor(&tiles, p1).unwrap();
+                    warps.insert(
+                        p0,
+                        Warp {
+                            name: name.clone(),
+                            to: to1,
+                        },
+                    );
+                    warps.insert(p1, Warp { name, to: to0 });
+                }
+                _ => {
+                    return Err(AocError::invalid_input(format!(
+                        "Portal {} should have two ends, not {}",
                         name,
-                        to: walk_to_floor(&tiles, points[0]).unwrap(),
-                    },
-                );
-                warps
-            });
-
-        let warps_min_x = *warps.keys().map(|(x, _)| x).min().unwrap();
-        let warps_max_x = *warps.keys().map(|(x, _)| x).max().unwrap();
-        let warps_min_y = *warps.keys().map(|(_, y)| y).min().unwrap();
-        let warps_max_y = *warps.keys().map(|(_, y)| y).max().unwrap();
-
-        World {
+                        points.len()
+                    )))
+                }
+            }
+        }
+
+        let warps_min_x = *warps.keys().map(|(x, _)| x).min().unwrap_or(&0);
+        let warps_max_x = *warps.keys().map(|(x, _)| x).max().unwrap_or(&0);
+        let warps_min_y = *warps.keys().map(|(_, y)| y).min().unwrap_or(&0);
+        let warps_max_y = *warps.keys().map(|(_, y)| y).max().unwrap_or(&0);
+
+        Ok(World {
             tiles,
             warps,
             start,
             goal,
             outer_warp_ring: ((warps_min_x, warps_min_y), (warps_max_x, warps_max_y)),
-        }
+        })
     }
 }
 
@@ -246,7 +269,7 @@ struct Route {
 
 impl<'world> Navigation<'world> {
     fn available_moves(&mut self, from: Point) -> &Vec<Route> {
-        if self.moves.get(&from).is_none() {
+        if !self.moves.contains_key(&from) {
             let mut moves: Vec<Route> = Vec::new();
             let mut visited: HashSet<Point> = HashSet::new();
             let mut queue: VecDeque<Route> = VecDeque::new();
@@ -287,7 +310,7 @@ impl<'world> Navigation<'world> {
     }
 }
 
-fn dijkstra(world: &World, levels: bool) -> usize {
+fn dijkstra(world: &World, levels: bool) -> Option<usize> {
     let mut queue: BinaryHeap<State> = BinaryHeap::new();
 
     queue.push(State {
@@ -300,13 +323,13 @@ fn dijkstra(world: &World, levels: bool) -> usize {
 
     let mut visited = HashSet::new();
     let mut nav = Navigation {
-        world: &world,
+        world,
         moves: HashMap::new(),
     };
 
     while let Some(state) = queue.pop() {
         if state.loc.pos == world.goal && state.loc.level == 0 {
-            return state.len;
+            return Some(state.len);
         } else {
             visited.insert(state.loc);
             for (next_loc, next_len) in steps_from(world, &mut nav, &state.loc, levels) {
@@ -321,20 +344,39 @@ fn dijkstra(world: &World, levels: bool) -> usize {
         }
     }
 
-    unreachable!()
+    None
 }
 
-fn solve_a(world: &World) -> usize {
-    dijkstra(world, false) - 2
+fn solve_a(world: &World) -> Result<usize, AocError> {
+    dijkstra(world, false)
+        .map(|len| len - 2)
+        .ok_or_else(|| AocError::no_solution("There is no path from AA to ZZ"))
 }
 
-fn solve_b(world: &World) -> usize {
-    dijkstra(world, true) - 2
+fn solve_b(world: &World) -> Result<usize, AocError> {
+    dijkstra(world, true)
+        .map(|len| len - 2)
+        .ok_or_else(|| AocError::no_solution("There is no path from AA to ZZ"))
 }
 
-pub fn solve(lines: &[String]) -> Solution {
-    let world = World::parse(lines);
-    let a_solution = solve_a(&world);
-    let b_solution = solve_b(&world);
-    (a_solution.to_string(), b_solution.to_string())
+pub struct Day20;
+
+impl Solver for Day20 {
+    type Input = World;
+
+    fn info(&self) -> Info {
+        super::info(20, "Donut Maze", false)
+    }
+
+    fn parse(&self, lines: &[String]) -> Result<World, AocError> {
+        World::parse(lines)
+    }
+
+    fn part_a(&self, world: &World) -> Result<String, AocError> {
+        Ok(solve_a(world)?.to_string())
+    }
+
+    fn part_b(&self, world: &World) -> Result<String, AocError> {
+        Ok(solve_b(world)?.to_string())
+    }
 }
@@ -1,27 +1,28 @@
-use crate::common::Solution;
+use crate::common::AocError;
+use crate::intcode::ascii::AsciiIntcode;
+use crate::intcode::parse_program;
 use crate::intcode::IntcodeComputer;
+use crate::solver::Info;
+use crate::solver::Solver;
 
-fn simulate(computer: IntcodeComputer, run: bool, script: &str) -> i64 {
-    let walk_input = if run { "RUN\n" } else { "WALK\n" };
-    let input = script
-        .chars()
-        .chain(walk_input.chars())
-        .map(|i| i as u8 as i64);
-    let output = computer.run(input).output;
-
-    if output.contains(&(b'D' as i64)) {
-        println!("{:?}", output);
-        println!(
-            "{}",
-            output.iter().map(|i| *i as u8 as char).collect::<String>()
-        );
-        -1
-    } else {
-        *output.back().unwrap()
+fn simulate(computer: IntcodeComputer, run: bool, script: &str) -> Result<i64, AocError> {
+    let mut droid = AsciiIntcode::new(computer);
+    for line in script.lines() {
+        droid.send_line(line);
+    }
+    droid.send_line(if run { "RUN" } else { "WALK" });
+    let output = droid.read_until_prompt()?;
+
+    match droid.read_values().last() {
+        Some(damage) => Ok(*damage),
+        None => Err(AocError::no_solution(format!(
+            "The springdroid fell into space:\n{}",
+            output
+        ))),
     }
 }
 
-fn solve_a(computer: IntcodeComputer) -> i64 {
+fn solve_a(computer: IntcodeComputer) -> Result<i64, AocError> {
     simulate(
         computer,
         false,
@@ -36,7 +37,7 @@ AND D J
     )
 }
 
-fn solve_b(computer: IntcodeComputer) -> i64 {
+fn solve_b(computer: IntcodeComputer) -> Result<i64, AocError> {
     simulate(
         computer,
         true,
@@ -54,9 +55,24 @@ AND T J
     )
 }
 
-pub fn solve(lines: &[String]) -> Solution {
-    let computer: IntcodeComputer = lines.into();
-    let a_solution = solve_a(computer.clone());
-    let b_solution = solve_b(computer);
-    (a_solution.to_string(), b_solution.to_string())
+pub struct Day21;
+
+impl Solver for Day21 {
+    type Input = IntcodeComputer;
+
+    fn info(&self) -> Info {
+        super::info(21, "Springdroid Adventure", true)
+    }
+
+    fn parse(&self, lines: &[String]) -> Result<IntcodeComputer, AocError> {
+        parse_program(lines).map(IntcodeComputer::new)
+    }
+
+    fn part_a(&self, computer: &IntcodeComputer) -> Result<String, AocError> {
+        Ok(solve_a(computer.clone())?.to_string())
+    }
+
+    fn part_b(&self, computer: &IntcodeComputer) -> Result<String, AocError> {
+        Ok(solve_b(computer.clone())?.to_string())
+    }
 }
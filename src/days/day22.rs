@@ -1,4 +1,7 @@
-use crate::common::Solution;
+use crate::common::parse_at;
+use crate::common::AocError;
+use crate::solver::Info;
+use crate::solver::Solver;
 
 fn modinv(n: u128, prime_modulus: u128) -> u128 {
     modpow(n, prime_modulus - 2, prime_modulus)
@@ -69,16 +72,14 @@ impl Deck {
         }
     }
 
-    fn shuffle(self, lines: &[String]) -> Deck {
+    fn shuffle(self, techniques: &[Technique]) -> Deck {
         let mut deck = self;
-        for line in lines {
-            if &line[0..3] == "cut" {
-                deck = deck.cut(line[4..].parse().unwrap());
-            } else if &line[0..9] == "deal with" {
-                deck = deck.deal(line[20..].parse().unwrap());
-            } else {
-                deck = deck.stack();
-            }
+        for technique in techniques {
+            deck = match technique {
+                Technique::Stack => deck.stack(),
+                Technique::Cut(n) => deck.cut(*n),
+                Technique::Deal(n) => deck.deal(*n),
+            };
         }
         deck
     }
@@ -113,27 +114,49 @@ impl Deck {
         }
     }
 
-    fn polynomial_for(modulus: u128, lines: &[String]) -> ModPolynomial {
-        lines.iter().fold(
+    fn polynomial_for(modulus: u128, techniques: &[Technique]) -> ModPolynomial {
+        techniques.iter().fold(
             ModPolynomial {
                 k: vec![0, 1],
                 modulus,
             },
-            |poly, line| {
-                if &line[0..3] == "cut" {
-                    let n: i128 = line[4..].parse().unwrap();
+            |poly, technique| match technique {
+                Technique::Cut(n) => {
                     poly.compose_deg1_raw((modulus as i128 + n) as u128 % modulus, 1)
-                } else if &line[0..9] == "deal with" {
-                    let ninv = modinv(line[20..].parse().unwrap(), modulus);
-                    poly.compose_deg1_raw(0, ninv)
-                } else {
-                    poly.compose_deg1_raw(modulus - 1, modulus - 1)
                 }
+                Technique::Deal(n) => poly.compose_deg1_raw(0, modinv(*n, modulus)),
+                Technique::Stack => poly.compose_deg1_raw(modulus - 1, modulus - 1),
             },
         )
     }
 }
 
+pub enum Technique {
+    Stack,
+    Cut(i128),
+    Deal(u128),
+}
+
+impl Technique {
+    fn parse(line: &str, line_num: usize) -> Result<Technique, AocError> {
+        const CUT: &str = "cut ";
+        const DEAL: &str = "deal with increment ";
+        if line == "deal into new stack" {
+            Ok(Technique::Stack)
+        } else if let Some(n) = line.strip_prefix(CUT) {
+            parse_at(n, line_num, CUT.len() + 1).map(Technique::Cut)
+        } else if let Some(n) = line.strip_prefix(DEAL) {
+            match parse_at(n, line_num, DEAL.len() + 1)? {
+                0 => Err(AocError::invalid_input("Cannot deal with increment 0")
+                    .at(line_num, DEAL.len() + 1)),
+                n => Ok(Technique::Deal(n)),
+            }
+        } else {
+            Err(AocError::invalid_input(format!("Unknown technique: {:?}", line)).at_line(line_num))
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 struct ModPolynomial {
     k: Vec<u128>,
@@ -195,26 +218,49 @@ impl ModPolynomial {
     }
 }
 
-fn solve_a(lines: &[String]) -> u128 {
-    Deck::polynomial_for(10007, lines).invert().apply(2019)
+fn solve_a(techniques: &[Technique]) -> u128 {
+    Deck::polynomial_for(10007, techniques).invert().apply(2019)
 }
 
 #[allow(clippy::unreadable_literal)]
-fn solve_b(lines: &[String]) -> u128 {
-    let poly = Deck::polynomial_for(119315717514047, lines);
+fn solve_b(techniques: &[Technique]) -> u128 {
+    let poly = Deck::polynomial_for(119315717514047, techniques);
     poly.self_composed_deg1(101741582076661).apply(2020)
 }
 
-pub fn solve(lines: &[String]) -> Solution {
-    let a_solution = solve_a(lines);
-    let b_solution = solve_b(lines);
-    (a_solution.to_string(), b_solution.to_string())
+pub struct Day22;
+
+impl Solver for Day22 {
+    type Input = Vec<Technique>;
+
+    fn info(&self) -> Info {
+        super::info(22, "Slam Shuffle", false)
+    }
+
+    fn parse(&self, lines: &[String]) -> Result<Vec<Technique>, AocError> {
+        lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| !line.is_empty())
+            .map(|(i, line)| Technique::parse(line, i + 1))
+            .collect()
+    }
+
+    fn part_a(&self, techniques: &Vec<Technique>) -> Result<String, AocError> {
+        Ok(solve_a(techniques).to_string())
+    }
+
+    fn part_b(&self, techniques: &Vec<Technique>) -> Result<String, AocError> {
+        Ok(solve_b(techniques).to_string())
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::Day22;
     use super::Deck;
     use super::ModPolynomial;
+    use crate::solver::Solver;
 
     fn testit(deck: Deck, expected: Vec<u128>) {
         let poly = deck.simplify();
@@ -418,7 +464,8 @@ mod tests {
         .map(|s| s.to_string())
         .collect();
         let mut deck: Deck = Deck::new(119315717514047);
-        let poly: ModPolynomial = Deck::polynomial_for(119315717514047, &lines);
+        let techniques = Day22.parse(&lines).unwrap();
+        let poly: ModPolynomial = Deck::polynomial_for(119315717514047, &techniques);
         let init = 2020;
 
         for i in 0..100 {
@@ -426,7 +473,7 @@ mod tests {
                 deck.get(init),
                 poly.clone().self_composed_deg1(i).apply(init)
             );
-            deck = deck.shuffle(&lines);
+            deck = deck.shuffle(&techniques);
         }
     }
 }
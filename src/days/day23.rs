@@ -1,16 +1,65 @@
+use crate::common::AocError;
 use crate::common::Solution;
+use crate::intcode::network::Flow;
+use crate::intcode::network::Inboxes;
+use crate::intcode::network::Nat;
+use crate::intcode::network::Network;
+use crate::intcode::network::NetworkStop;
+use crate::intcode::network::PacketRouter;
+use crate::intcode::parse_program;
 use crate::intcode::IntcodeComputer;
+use crate::solver::Info;
+use crate::solver::Solver;
 
 const NUM_COMPUTERS: usize = 50;
+const NAT_ADDRESS: i64 = 255;
 
-#[derive(Debug)]
-struct Packet {
-    x: i64,
-    y: i64,
+#[derive(Default)]
+struct Day23Nat {
+    /// Stop the network as soon as the first packet arrives.
+    stop_at_first: bool,
+    first_y: Option<i64>,
+    packet: Option<(i64, i64)>,
+    last_sent_y: Option<i64>,
+    repeated_y: Option<i64>,
+    /// An address without a machine that a packet was sent to.
+    unknown_address: Option<i64>,
 }
 
-fn solve_b(template: &IntcodeComputer) -> (i64, i64) {
-    let mut computers: Vec<IntcodeComputer> = (0..NUM_COMPUTERS)
+impl Nat for Day23Nat {
+    fn receive(&mut self, address: i64, x: i64, y: i64) -> Flow {
+        if address != NAT_ADDRESS {
+            self.unknown_address = Some(address);
+            return Flow::Stop;
+        }
+        if self.first_y.is_none() {
+            self.first_y = Some(y);
+            if self.stop_at_first {
+                return Flow::Stop;
+            }
+        }
+        self.packet = Some((x, y));
+        Flow::Continue
+    }
+
+    fn on_idle(&mut self, inboxes: &mut Inboxes) -> Flow {
+        match self.packet {
+            Some((_, y)) if Some(y) == self.last_sent_y => {
+                self.repeated_y = Some(y);
+                Flow::Stop
+            }
+            Some((x, y)) => {
+                self.last_sent_y = Some(y);
+                inboxes.send(0, &[x, y]);
+                Flow::Continue
+            }
+            None => Flow::Continue,
+        }
+    }
+}
+
+fn run_network(template: &IntcodeComputer, stop_at_first: bool) -> Result<Day23Nat, AocError> {
+    let computers: Vec<IntcodeComputer> = (0..NUM_COMPUTERS)
         .map(|i| {
             let mut computer = template.clone();
             computer.input.push_back(i as i64);
@@ -18,68 +67,67 @@ fn solve_b(template: &IntcodeComputer) -> (i64, i64) {
         })
         .collect();
 
-    let mut nat_buffer: Option<Packet> = None;
-    let mut last_nat_y: Option<i64> = None;
-    let mut computers_stalled: Vec<usize> = vec![0; NUM_COMPUTERS];
+    let mut network = Network::new(
+        computers,
+        PacketRouter {
+            nat: Day23Nat {
+                stop_at_first,
+                ..Day23Nat::default()
+            },
+        },
+    );
+    match network.run() {
+        NetworkStop::Stopped => match network.router.nat.unknown_address {
+            Some(address) => Err(AocError::no_solution(format!(
+                "Packet sent to unknown address: {}",
+                address
+            ))),
+            None => Ok(network.router.nat),
+        },
+        NetworkStop::Error { error, .. } => Err(error.into()),
+        stop => Err(AocError::no_solution(format!(
+            "The network stopped early: {:?}",
+            stop
+        ))),
+    }
+}
 
-    let mut a_solution: Option<i64> = None;
+fn nat_value(value: Option<i64>, what: &str) -> Result<String, AocError> {
+    value
+        .map(|y| y.to_string())
+        .ok_or_else(|| AocError::no_solution(format!("The NAT never {}", what)))
+}
 
-    loop {
-        let network_idle = computers
-            .iter()
-            .enumerate()
-            .all(|(compi, _)| computers_stalled[compi] > 1 && computers[compi].input.is_empty());
-        if network_idle {
-            if let Some(packet) = &nat_buffer {
-                if Some(packet.y) == last_nat_y {
-                    return (a_solution.unwrap(), packet.y);
-                } else {
-                    last_nat_y = Some(packet.y);
-                    computers[0].input.push_back(packet.x);
-                    computers[0].input.push_back(packet.y);
-                }
-            } else {
-                panic!("Network stalled");
-            }
-        }
+pub struct Day23;
 
-        for compi in 0..computers.len() {
-            let computer = &mut computers[compi];
+impl Solver for Day23 {
+    type Input = IntcodeComputer;
 
-            if computer.expects_input() {
-                if computer.input.is_empty() {
-                    computers_stalled[compi] += 1;
-                    computer.input.push_back(-1);
-                } else {
-                    computers_stalled[compi] = 0;
-                }
-            };
+    fn info(&self) -> Info {
+        super::info(23, "Category Six", true)
+    }
 
-            computer.run_mut(None);
+    fn parse(&self, lines: &[String]) -> Result<IntcodeComputer, AocError> {
+        parse_program(lines).map(IntcodeComputer::new)
+    }
 
-            if computer.output.len() >= 3 {
-                computers_stalled[compi] = 0;
-                let addr = computer.output.pop_front().unwrap() as usize;
-                let x = computer.output.pop_front().unwrap();
-                let y = computer.output.pop_front().unwrap();
-                let packet = Packet { x, y };
+    fn part_a(&self, computer: &IntcodeComputer) -> Result<String, AocError> {
+        nat_value(run_network(computer, true)?.first_y, "received a packet")
+    }
 
-                if addr == 255 {
-                    if a_solution.is_none() {
-                        a_solution = Some(packet.y);
-                    }
-                    nat_buffer = Some(packet);
-                } else {
-                    computers[addr].input.push_back(packet.x);
-                    computers[addr].input.push_back(packet.y);
-                }
-            }
-        }
+    fn part_b(&self, computer: &IntcodeComputer) -> Result<String, AocError> {
+        nat_value(
+            run_network(computer, false)?.repeated_y,
+            "repeated a Y value",
+        )
     }
-}
 
-pub fn solve(lines: &[String]) -> Solution {
-    let computer: IntcodeComputer = lines.into();
-    let (a_solution, b_solution) = solve_b(&computer);
-    (a_solution.to_string(), b_solution.to_string())
+    /// Part B continues the same run of the network.
+    fn solve(&self, computer: &IntcodeComputer) -> Result<Solution, AocError> {
+        let nat = run_network(computer, false)?;
+        Ok((
+            nat_value(nat.first_y, "received a packet")?,
+            nat_value(nat.repeated_y, "repeated a Y value")?,
+        ))
+    }
 }
@@ -1,8 +1,37 @@
-use crate::common::Solution;
+use crate::common::AocError;
+use crate::solver::Info;
+use crate::solver::Solver;
 use std::collections::HashSet;
 
-fn parse(lines: &[String]) -> BoolMatrix {
-    [".....".to_string()]
+fn parse(lines: &[String]) -> Result<BoolMatrix, AocError> {
+    let lines = &lines[..lines
+        .iter()
+        .rposition(|l| !l.is_empty())
+        .map_or(0, |i| i + 1)];
+    if lines.len() != 5 {
+        return Err(AocError::invalid_input(format!(
+            "Expected 5 lines, got {}",
+            lines.len()
+        )));
+    }
+    for (y, line) in lines.iter().enumerate() {
+        if let Some((x, c)) = line
+            .chars()
+            .enumerate()
+            .find(|(_, c)| *c != '#' && *c != '.')
+        {
+            return Err(AocError::invalid_input(format!("Unknown tile: {:?}", c)).at(y + 1, x + 1));
+        }
+        if line.chars().count() != 5 {
+            return Err(AocError::invalid_input(format!(
+                "Expected 5 tiles, got {}",
+                line.chars().count()
+            ))
+            .at_line(y + 1));
+        }
+    }
+
+    Ok([".....".to_string()]
         .iter()
         .chain(lines.iter())
         .chain([".....".to_string()].iter())
@@ -13,7 +42,7 @@ fn parse(lines: &[String]) -> BoolMatrix {
                 .map(|c| c == '#')
         })
         .collect::<BoolMatrixBuilder>()
-        .dim()
+        .dim())
 }
 
 fn format_state(state: &State) -> String {
@@ -68,7 +97,7 @@ fn update(state: State, mut next_state: State) -> (State, State) {
 type State = BoolMatrix;
 
 #[derive(Clone)]
-struct BoolMatrix {
+pub struct BoolMatrix {
     value: u64,
 }
 
@@ -275,7 +304,7 @@ impl LevelsState {
     }
 
     fn level_to_index(level: i32) -> usize {
-        level.abs() as usize * 2 - ((level < 0) as usize)
+        level.unsigned_abs() as usize * 2 - ((level < 0) as usize)
     }
 }
 
@@ -346,9 +375,24 @@ fn solve_b(initial_state: State) -> u32 {
         .sum()
 }
 
-pub fn solve(lines: &[String]) -> Solution {
-    let initial_state = parse(lines);
-    let a_solution = solve_a(initial_state.clone());
-    let b_solution = solve_b(initial_state);
-    (a_solution.to_string(), b_solution.to_string())
+pub struct Day24;
+
+impl Solver for Day24 {
+    type Input = State;
+
+    fn info(&self) -> Info {
+        super::info(24, "Planet of Discord", false)
+    }
+
+    fn parse(&self, lines: &[String]) -> Result<State, AocError> {
+        parse(lines)
+    }
+
+    fn part_a(&self, initial_state: &State) -> Result<String, AocError> {
+        Ok(solve_a(initial_state.clone()).to_string())
+    }
+
+    fn part_b(&self, initial_state: &State) -> Result<String, AocError> {
+        Ok(solve_b(initial_state.clone()).to_string())
+    }
 }
@@ -1,7 +1,10 @@
-use crate::common::Solution;
+use crate::common::AocError;
+use crate::intcode::ascii::AsciiIntcode;
+use crate::intcode::parse_program;
 use crate::intcode::IntcodeComputer;
+use crate::solver::Info;
+use crate::solver::Solver;
 use std::collections::VecDeque;
-use std::io::Read;
 
 type Point = (i8, i8);
 type Direction = (i8, i8);
@@ -53,20 +56,28 @@ impl State {
         self.pos[self.pos.len() - 1]
     }
 
-    fn backtrack_dir(&self) -> Direction {
+    fn prev_pos(&self) -> Result<Point, AocError> {
+        self.pos
+            .len()
+            .checked_sub(2)
+            .map(|i| self.pos[i])
+            .ok_or_else(|| AocError::no_solution("The droid has no room to go back to"))
+    }
+
+    fn backtrack_dir(&self) -> Result<Direction, AocError> {
         let pos = self.current_pos();
-        let prev = self.pos[self.pos.len() - 2];
-        (prev.0 - pos.0, prev.1 - pos.1)
+        let prev = self.prev_pos()?;
+        Ok((prev.0 - pos.0, prev.1 - pos.1))
     }
 
-    fn backtrack_move(&self) -> &'static str {
-        dir_to_move(self.backtrack_dir())
+    fn backtrack_move(&self) -> Result<&'static str, AocError> {
+        Ok(dir_to_move(self.backtrack_dir()?))
     }
 
-    fn backtrack(mut self) -> Self {
+    fn backtrack(mut self) -> Result<Self, AocError> {
         self.unexplored_pos.pop();
-        self.next_commands
-            .push_back(self.backtrack_move().to_string());
+        let move_command = self.backtrack_move()?;
+        self.next_commands.push_back(move_command.to_string());
         let prev_pos = self.current_pos();
         self.pos.pop();
 
@@ -77,7 +88,7 @@ impl State {
                 self.path_to_security.push(prev_pos);
             }
         }
-        self
+        Ok(self)
     }
 
     fn take(mut self, item: String) -> Self {
@@ -86,7 +97,7 @@ impl State {
         self
     }
 
-    fn explore(mut self, doors: Vec<String>) -> Self {
+    fn explore(mut self, doors: Vec<String>) -> Result<Self, AocError> {
         if self.unexplored_pos.iter().all(|poss| poss.is_empty()) {
             self.stage = Navigate;
             self.navigate()
@@ -113,14 +124,20 @@ impl State {
                     }
                     self.unexplored_pos.push(next_unexplored);
 
-                    return self;
+                    return Ok(self);
                 }
             }
             self.backtrack()
         }
     }
 
-    fn navigate(mut self) -> Self {
+    fn navigate(mut self) -> Result<Self, AocError> {
+        if self.items.len() >= u32::BITS as usize {
+            return Err(AocError::no_solution(format!(
+                "The droid carries too many items: {}",
+                self.items.len()
+            )));
+        }
         while let Some(step) = self.path_to_security.pop() {
             let pos = self.current_pos();
             if self.pos.len() > 2 && self.pos[self.pos.len() - 2] == step {
@@ -135,10 +152,10 @@ impl State {
         self.stage = Unlock;
         self.last_attempt_code = (1 << self.items.len()) - 1;
         self.unlock_attempt = gray_decode((1 << (self.items.len() / 2 + 1)) - 1);
-        self
+        Ok(self)
     }
 
-    fn unlock(mut self, room: Room) -> Self {
+    fn unlock(mut self, room: Room) -> Result<Self, AocError> {
         if let Some(solution) = room.solution {
             self.solution = Some(solution);
             self.stage = Done;
@@ -153,6 +170,7 @@ impl State {
             self.unlock_attempt = (self.unlock_attempt + 1) % (1 << self.items.len());
 
             let mut attempt_code = gray_code(self.unlock_attempt);
+            let mut skipped: u32 = 0;
             while self
                 .too_heavy
                 .iter()
@@ -162,17 +180,23 @@ impl State {
                     .iter()
                     .any(|light| light | attempt_code == *light)
             {
+                skipped += 1;
+                if skipped == 1 << self.items.len() {
+                    return Err(AocError::no_solution(
+                        "No combination of items is left to try",
+                    ));
+                }
                 self.unlock_attempt = (self.unlock_attempt + 1) % (1 << self.items.len());
                 attempt_code = gray_code(self.unlock_attempt);
             }
 
             let pos = self.current_pos();
-            let prev_pos = self.pos[self.pos.len() - 2];
+            let prev_pos = self.prev_pos()?;
             let move_command = room
                 .doors
                 .into_iter()
                 .find(|door| move_to_point(pos, door) != prev_pos)
-                .unwrap();
+                .ok_or_else(|| AocError::no_solution("The security checkpoint has no way on"))?;
 
             for i in 0..self.items.len() {
                 let mask = 1 << i;
@@ -189,7 +213,7 @@ impl State {
             self.last_attempt_code = attempt_code;
             self.next_commands.push_back(move_command);
         }
-        self
+        Ok(self)
     }
 }
 
@@ -225,7 +249,7 @@ struct Room {
     solution: Option<String>,
 }
 
-fn parse_room(output: String) -> Room {
+fn parse_room(output: String) -> Result<Room, AocError> {
     let mut words: VecDeque<&str> = output.split_whitespace().collect();
 
     let mut name: String = "".to_string();
@@ -255,20 +279,30 @@ fn parse_room(output: String) -> Room {
                 words.pop_front();
                 words.pop_front();
                 doors.clear();
-                while words[0] == "-" {
+                while words.front() == Some(&"-") {
                     words.pop_front();
-                    doors.push(words.pop_front().unwrap().to_string());
+                    match words.pop_front() {
+                        Some(door @ "north") | Some(door @ "east") | Some(door @ "south")
+                        | Some(door @ "west") => doors.push(door.to_string()),
+                        Some(door) => {
+                            return Err(AocError::no_solution(format!("Unknown door: {}", door)))
+                        }
+                        None => return Err(AocError::no_solution("The list of doors ended early")),
+                    }
                 }
             }
 
             "Items" => {
                 words.pop_front();
                 words.pop_front();
-                while words[0] == "-" {
+                while words.front() == Some(&"-") {
                     words.pop_front();
                     let mut item: Vec<&str> = Vec::new();
-                    while words[0] != "-" && words[0] != "Command?" {
-                        item.push(words.pop_front().unwrap());
+                    while let Some(&word) =
+                        words.front().filter(|w| **w != "-" && **w != "Command?")
+                    {
+                        item.push(word);
+                        words.pop_front();
                     }
                     let item = item.join(" ");
                     match item.as_str() {
@@ -286,10 +320,13 @@ fn parse_room(output: String) -> Room {
 
             "\"Analysis" => {
                 words.pop_front();
-                assert_eq!(words.pop_front(), Some("complete!"));
-                assert_eq!(words.pop_front(), Some("You"));
-                assert_eq!(words.pop_front(), Some("may"));
-                assert_eq!(words.pop_front(), Some("proceed.\""));
+                let verdict: Vec<&str> = words.drain(..words.len().min(4)).collect();
+                if verdict != ["complete!", "You", "may", "proceed.\""] {
+                    return Err(AocError::no_solution(format!(
+                        "Unexpected analysis: {}",
+                        verdict.join(" ")
+                    )));
+                }
                 while let Some(word) = words.pop_front() {
                     if word == "typing" {
                         solution = words.pop_front().map(|s| s.to_string());
@@ -299,7 +336,7 @@ fn parse_room(output: String) -> Room {
             }
 
             "\"Alert!" => {
-                words.drain(0..6);
+                words.drain(..words.len().min(6));
                 if words.pop_front() == Some("lighter") {
                     too_heavy = true;
                 } else {
@@ -312,21 +349,21 @@ fn parse_room(output: String) -> Room {
         }
     }
 
-    Room {
+    Ok(Room {
         name,
         doors,
         items,
         too_light,
         too_heavy,
         solution,
-    }
+    })
 }
 
-fn update(mut state: State, output: String) -> State {
+fn update(mut state: State, output: String) -> Result<State, AocError> {
     state.next_commands.clear();
     match state.stage {
         Collect => {
-            let room = parse_room(output);
+            let room = parse_room(output)?;
             if room.name == "Security Checkpoint" {
                 state.security_found = true;
                 state.backtrack()
@@ -340,7 +377,7 @@ fn update(mut state: State, output: String) -> State {
         }
 
         Navigate => state.navigate(),
-        Unlock => state.unlock(parse_room(output)),
+        Unlock => state.unlock(parse_room(output)?),
         _ => unreachable!(),
     }
 }
@@ -356,8 +393,8 @@ fn move_to_point(pos: Point, move_command: &str) -> Point {
     (pos.0 + dir.0, pos.1 + dir.1)
 }
 
-fn initialize(mut state: State, output: String) -> State {
-    let room = parse_room(output);
+fn initialize(mut state: State, output: String) -> Result<State, AocError> {
+    let room = parse_room(output)?;
     for item in room.items {
         state = state.take(item);
     }
@@ -370,13 +407,13 @@ fn initialize(mut state: State, output: String) -> State {
     state.explore(room.doors)
 }
 
-fn solve_a(computer: IntcodeComputer) -> String {
+fn solve_a(computer: IntcodeComputer) -> Result<String, AocError> {
     let mut state = State::new();
 
-    let mut computer = computer.run(None);
-    let first_output: String = computer.output.drain(..).map(|i| i as u8 as char).collect();
+    let mut computer = AsciiIntcode::new(computer);
+    let first_output = computer.read_until_prompt()?;
     // println!("{}", first_output);
-    state = initialize(state, first_output);
+    state = initialize(state, first_output)?;
 
     loop {
         let mut output: String = "".to_string();
@@ -386,53 +423,37 @@ fn solve_a(computer: IntcodeComputer) -> String {
             // println!("items: {:?}", state.items);
             // println!("unexplored: {:?}", state.unexplored_pos);
             // println!("Command: {}", cmd);
-            let input: Vec<i64> = format!("{}\n", cmd)
-                .chars()
-                .map(|c| c as u8 as i64)
-                .collect();
-            computer = computer.run(input);
-            output = computer.output.drain(..).map(|i| i as u8 as char).collect();
+            computer.send_line(&cmd);
+            output = computer.read_until_prompt()?;
             // println!("{}", output);
         }
 
-        state = update(state, output);
+        state = update(state, output)?;
 
-        if state.solution.is_some() {
-            return state.solution.unwrap();
+        if let Some(solution) = state.solution {
+            return Ok(solution);
         }
     }
 }
 
-#[allow(dead_code)]
-fn interact(mut computer: IntcodeComputer) {
-    while computer.is_running() {
-        computer.run_mut(None);
-
-        print!(
-            "{}",
-            computer
-                .output
-                .drain(..)
-                .map(|o| (o as u8 as char).to_string())
-                .collect::<Vec<String>>()
-                .join("")
-        );
-
-        let mut buf = [0; 100];
-        let mut len = 0;
-        while len == 0 || (buf[len - 1] as char) != '\n' {
-            let n = std::io::stdin().read(&mut buf[len..]).unwrap();
-            len += n;
-        }
-        for b in buf[0..len].iter() {
-            computer.input.push_back(*b as i64);
-        }
+pub struct Day25;
+
+impl Solver for Day25 {
+    type Input = IntcodeComputer;
+
+    fn info(&self) -> Info {
+        super::info(25, "Cryostasis", true)
     }
-}
 
-pub fn solve(lines: &[String]) -> Solution {
-    let computer: IntcodeComputer = lines.into();
-    // interact(computer);
-    let a_solution = solve_a(computer);
-    (a_solution, "-".to_string())
+    fn parse(&self, lines: &[String]) -> Result<IntcodeComputer, AocError> {
+        parse_program(lines).map(IntcodeComputer::new)
+    }
+
+    fn part_a(&self, computer: &IntcodeComputer) -> Result<String, AocError> {
+        solve_a(computer.clone())
+    }
+
+    fn part_b(&self, _computer: &IntcodeComputer) -> Result<String, AocError> {
+        Ok("-".to_string())
+    }
 }
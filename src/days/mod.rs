@@ -24,37 +24,46 @@ pub mod day23;
 pub mod day24;
 pub mod day25;
 
-pub fn all_numbers() -> Vec<u8> {
-    (1..=25).filter(|&day| get_solver(day).is_some()).collect()
-}
+use crate::solver::Info;
+use crate::solver::Registry;
+use crate::solver::DEFAULT_VARIANT;
+
+pub const YEAR: u16 = 2019;
 
-pub fn get_solver(day: u8) -> Option<fn(&[String]) -> crate::common::Solution> {
-    match day {
-        1 => Some(day01::solve),
-        2 => Some(day02::solve),
-        3 => Some(day03::solve),
-        4 => Some(day04::solve),
-        5 => Some(day05::solve),
-        6 => Some(day06::solve),
-        7 => Some(day07::solve),
-        8 => Some(day08::solve),
-        9 => Some(day09::solve),
-        10 => Some(day10::solve),
-        11 => Some(day11::solve),
-        12 => Some(day12::solve),
-        13 => Some(day13::solve),
-        14 => Some(day14::solve),
-        15 => Some(day15::solve),
-        16 => Some(day16::solve),
-        17 => Some(day17::solve),
-        18 => Some(day18::solve),
-        19 => Some(day19::solve),
-        20 => Some(day20::solve),
-        21 => Some(day21::solve),
-        22 => Some(day22::solve),
-        23 => Some(day23::solve),
-        24 => Some(day24::solve),
-        25 => Some(day25::solve),
-        _ => None,
+fn info(day: u8, title: &'static str, uses_intcode: bool) -> Info {
+    Info {
+        year: YEAR,
+        day,
+        title,
+        variant: DEFAULT_VARIANT,
+        uses_intcode,
     }
 }
+
+pub fn register(registry: &mut Registry) {
+    registry.register(day01::Day01);
+    registry.register(day02::Day02);
+    registry.register(day03::Day03);
+    registry.register(day04::Day04);
+    registry.register(day05::Day05);
+    registry.register(day06::Day06);
+    registry.register(day07::Day07);
+    registry.register(day08::Day08);
+    registry.register(day09::Day09);
+    registry.register(day10::Day10);
+    registry.register(day11::Day11);
+    registry.register(day12::Day12);
+    registry.register(day13::Day13);
+    registry.register(day14::Day14);
+    registry.register(day15::Day15);
+    registry.register(day16::Day16);
+    registry.register(day17::Day17);
+    registry.register(day18::Day18);
+    registry.register(day19::Day19);
+    registry.register(day20::Day20);
+    registry.register(day21::Day21);
+    registry.register(day22::Day22);
+    registry.register(day23::Day23);
+    registry.register(day24::Day24);
+    registry.register(day25::Day25);
+}
@@ -1,15 +1,81 @@
+pub mod ascii;
+pub mod asm;
+pub mod cfg;
+pub mod debug;
+pub mod disasm;
+mod fast;
+pub mod fuzz;
+pub mod history;
+pub mod io;
+pub mod memory;
+pub mod network;
+pub mod opcodes;
+pub mod play;
+pub mod session;
+pub mod snapshot;
+pub mod threads;
+pub mod trace;
+
+use self::fast::DecodeCache;
+use self::history::History;
+use self::io::IntcodeInput;
+use self::io::IntcodeOutput;
+use self::memory::Memory;
+use self::opcodes::OpcodeRegistry;
+use self::session::Session;
+use self::trace::Profile;
+use self::trace::TraceEntry;
+use crate::common::first_line;
+use crate::common::parse_at;
+use crate::common::split_columns;
+use crate::common::AocError;
 use std::collections::VecDeque;
+use std::time::Duration;
+use std::time::Instant;
 
-type Word = i64;
-type Memory = Vec<Word>;
+pub type Word = i64;
 
 #[derive(Clone)]
-pub struct IntcodeComputer {
+pub struct IntcodeComputer<I = VecDeque<Word>, O = VecDeque<Word>> {
     pub eip: usize,
     pub prog: Memory,
     relbase: Word,
-    pub input: VecDeque<Word>,
-    pub output: VecDeque<Word>,
+    pub input: I,
+    pub output: O,
+    pub pause_on_output: Option<usize>,
+    /// The maximum number of instructions to execute in each call to `resume`.
+    pub step_limit: Option<usize>,
+    /// The number of instructions left for all future runs to share.
+    pub step_budget: Option<usize>,
+    /// The maximum time to spend in each call to `resume`.
+    pub timeout: Option<Duration>,
+    /// Set to `Some` to record every executed instruction.
+    pub trace: Option<Vec<TraceEntry>>,
+    /// Set to `Some` to count executed instructions per address and opcode.
+    pub profile: Option<Profile>,
+    /// Set to `Some` to record every value read from `input` and written to
+    /// `output`.
+    pub session: Option<Session>,
+    /// Set to `Some` to be able to step backwards.
+    pub history: Option<History>,
+    pub engine: Engine,
+    /// Opcodes to accept in addition to the built-in ones.
+    pub opcodes: OpcodeRegistry,
+    /// Set by a custom opcode to stop the run.
+    trapped: bool,
+    cache: DecodeCache,
+}
+
+/// How `resume` executes instructions. Both engines produce exactly the same
+/// results, but `Cached` avoids decoding the same instructions over and over.
+/// Tracing, profiling, recording sessions and keeping a history always use
+/// `Step`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Engine {
+    /// Execute each instruction with [`IntcodeComputer::step`].
+    Step,
+    /// Execute instructions from a cache of decoded instructions.
+    Cached,
 }
 
 const OP_ADD: Word = 1;
@@ -23,64 +89,437 @@ const OP_EQ: Word = 8;
 const OP_RELBASE: Word = 9;
 const OP_HALT: Word = 99;
 
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ParamKind {
+    Read,
+    Write,
+}
+
+pub struct OpcodeInfo {
+    pub opcode: Word,
+    pub mnemonic: &'static str,
+    pub params: &'static [ParamKind],
+}
+
+static OPCODES: [OpcodeInfo; 10] = {
+    use ParamKind::{Read, Write};
+    [
+        OpcodeInfo {
+            opcode: OP_ADD,
+            mnemonic: "ADD",
+            params: &[Read, Read, Write],
+        },
+        OpcodeInfo {
+            opcode: OP_MULTIPLY,
+            mnemonic: "MUL",
+            params: &[Read, Read, Write],
+        },
+        OpcodeInfo {
+            opcode: OP_INPUT,
+            mnemonic: "IN",
+            params: &[Write],
+        },
+        OpcodeInfo {
+            opcode: OP_OUTPUT,
+            mnemonic: "OUT",
+            params: &[Read],
+        },
+        OpcodeInfo {
+            opcode: OP_JUMP_NONZERO,
+            mnemonic: "JNZ",
+            params: &[Read, Read],
+        },
+        OpcodeInfo {
+            opcode: OP_JUMP_ZERO,
+            mnemonic: "JZ",
+            params: &[Read, Read],
+        },
+        OpcodeInfo {
+            opcode: OP_LESS,
+            mnemonic: "LT",
+            params: &[Read, Read, Write],
+        },
+        OpcodeInfo {
+            opcode: OP_EQ,
+            mnemonic: "EQ",
+            params: &[Read, Read, Write],
+        },
+        OpcodeInfo {
+            opcode: OP_RELBASE,
+            mnemonic: "ARB",
+            params: &[Read],
+        },
+        OpcodeInfo {
+            opcode: OP_HALT,
+            mnemonic: "HALT",
+            params: &[],
+        },
+    ]
+};
+
+pub fn opcode_info(opcode: Word) -> Option<&'static OpcodeInfo> {
+    OPCODES.iter().find(|info| info.opcode == opcode)
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum IntcodeError {
+    InvalidOpcode {
+        eip: usize,
+        opcode: Word,
+    },
+    InvalidParameterMode {
+        eip: usize,
+        instruction: Word,
+        mode: Word,
+    },
+    NegativeAddress {
+        eip: usize,
+        address: Word,
+    },
+    WriteToImmediate {
+        eip: usize,
+        instruction: Word,
+    },
+    /// The relative base or a relative address doesn't fit in a `Word`.
+    /// Arithmetic instructions wrap around instead.
+    Overflow {
+        eip: usize,
+        instruction: Word,
+    },
+    /// A write would make memory hold more words than its `limit`.
+    MemoryLimitExceeded {
+        eip: usize,
+        address: usize,
+    },
+    /// The handler of a custom opcode failed.
+    CustomOpcodeFailed {
+        eip: usize,
+        opcode: Word,
+        message: &'static str,
+    },
+}
+
+impl std::fmt::Display for IntcodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::InvalidOpcode { eip, opcode } => {
+                write!(f, "Invalid opcode {} at address {}", opcode, eip)
+            }
+            Self::InvalidParameterMode {
+                eip,
+                instruction,
+                mode,
+            } => write!(
+                f,
+                "Invalid parameter mode {} in instruction {} at address {}",
+                mode, instruction, eip
+            ),
+            Self::NegativeAddress { eip, address } => write!(
+                f,
+                "Negative address {} accessed by instruction at address {}",
+                address, eip
+            ),
+            Self::WriteToImmediate { eip, instruction } => write!(
+                f,
+                "Instruction {} at address {} writes to an immediate mode parameter",
+                instruction, eip
+            ),
+            Self::Overflow { eip, instruction } => write!(
+                f,
+                "Relative base overflow in instruction {} at address {}",
+                instruction, eip
+            ),
+            Self::MemoryLimitExceeded { eip, address } => write!(
+                f,
+                "Memory limit exceeded by write to address {} by instruction at address {}",
+                address, eip
+            ),
+            Self::CustomOpcodeFailed {
+                eip,
+                opcode,
+                message,
+            } => write!(
+                f,
+                "Custom opcode {} at address {} failed: {}",
+                opcode, eip, message
+            ),
+        }
+    }
+}
+
+impl std::error::Error for IntcodeError {}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[must_use]
+pub enum StopReason {
+    Halted,
+    NeedsInput,
+    /// The run has produced the number of outputs requested by `pause_on_output`.
+    OutputReady(usize),
+    /// The run has executed `step_limit` instructions.
+    StepLimitReached,
+    /// The computer has executed all instructions in its `step_budget`.
+    BudgetExhausted,
+    /// The run has taken longer than `timeout`.
+    TimedOut,
+    /// A custom opcode at `eip` stopped the run. Resuming continues after it.
+    Trapped {
+        eip: usize,
+    },
+    Error(IntcodeError),
+}
+
+/// Reading the clock on every instruction would slow execution down a lot, so
+/// timeouts are only checked this often.
+const TIMEOUT_CHECK_INTERVAL: usize = 1024;
+
+/// The limits on a single call to `resume`.
+struct RunLimits {
+    max_steps: Option<usize>,
+    /// Whether `max_steps` comes from the step budget.
+    budget_limited: bool,
+    deadline: Option<Instant>,
+}
+
+impl RunLimits {
+    fn new(
+        step_limit: Option<usize>,
+        step_budget: Option<usize>,
+        timeout: Option<Duration>,
+    ) -> Self {
+        let budget_limited = match (step_limit, step_budget) {
+            (Some(limit), Some(budget)) => budget <= limit,
+            (None, Some(_)) => true,
+            _ => false,
+        };
+        RunLimits {
+            max_steps: if budget_limited {
+                step_budget
+            } else {
+                step_limit
+            },
+            budget_limited,
+            // A timeout too far in the future to represent is no timeout
+            deadline: timeout.and_then(|timeout| Instant::now().checked_add(timeout)),
+        }
+    }
+
+    /// Returns the reason to stop after `steps` instructions, if any.
+    #[inline(always)]
+    fn check(&self, steps: usize) -> Option<StopReason> {
+        if Some(steps) == self.max_steps {
+            return Some(if self.budget_limited {
+                StopReason::BudgetExhausted
+            } else {
+                StopReason::StepLimitReached
+            });
+        }
+        match self.deadline {
+            Some(deadline)
+                if steps.is_multiple_of(TIMEOUT_CHECK_INTERVAL) && Instant::now() >= deadline =>
+            {
+                Some(StopReason::TimedOut)
+            }
+            _ => None,
+        }
+    }
+}
+
 impl IntcodeComputer {
     pub fn new(program: Vec<Word>) -> IntcodeComputer {
+        Self::with_io(program, VecDeque::new(), VecDeque::new())
+    }
+}
+
+impl<I, O> IntcodeComputer<I, O>
+where
+    I: IntcodeInput,
+    O: IntcodeOutput,
+{
+    pub fn with_io(program: Vec<Word>, input: I, output: O) -> Self {
         IntcodeComputer {
             eip: 0,
-            prog: program,
+            prog: Memory::from(program),
             relbase: 0,
-            input: VecDeque::new(),
-            output: VecDeque::new(),
+            input,
+            output,
+            pause_on_output: None,
+            step_limit: None,
+            step_budget: None,
+            timeout: None,
+            trace: None,
+            profile: None,
+            session: None,
+            history: None,
+            engine: Engine::Cached,
+            opcodes: OpcodeRegistry::default(),
+            trapped: false,
+            cache: DecodeCache::default(),
         }
     }
 
-    pub fn step(&mut self) {
-        let instruction = self.prog[self.eip];
-        let opcode = instruction % 100;
+    /// Moves the machine to other input and output, and returns it with the
+    /// old ones. Everything else is kept.
+    pub fn replace_io<J, P>(self, input: J, output: P) -> (IntcodeComputer<J, P>, I, O) {
+        let machine = IntcodeComputer {
+            input,
+            output,
+            eip: self.eip,
+            prog: self.prog,
+            relbase: self.relbase,
+            pause_on_output: self.pause_on_output,
+            step_limit: self.step_limit,
+            step_budget: self.step_budget,
+            timeout: self.timeout,
+            trace: self.trace,
+            profile: self.profile,
+            session: self.session,
+            history: self.history,
+            engine: self.engine,
+            opcodes: self.opcodes,
+            trapped: self.trapped,
+            cache: self.cache,
+        };
+        (machine, self.input, self.output)
+    }
+
+    pub fn step(&mut self) -> Result<(), IntcodeError> {
+        if !self.is_observed() {
+            return self.execute();
+        }
+
+        let eip = self.eip;
+        let instruction = self.current_instruction();
+        let (operands, write_addr) =
+            trace::resolve_operands(&self.prog, &self.opcodes, eip, self.relbase);
+        let undo = self.history.as_ref().map(|_| self.undo_entry());
+        self.execute()?;
+        if instruction % 100 == OP_INPUT && self.eip == eip {
+            return Ok(());
+        }
+
+        if let Some(profile) = &mut self.profile {
+            profile.record(eip, instruction % 100);
+        }
+        let write = write_addr.map(|addr| (addr, self.prog.get(addr)));
+        if let (Some(history), Some(undo)) = (&mut self.history, undo) {
+            history.push(undo.finish(instruction % 100, &self.prog));
+        }
+        if let Some(session) = &mut self.session {
+            session.record(
+                instruction % 100,
+                operands.first().copied(),
+                write.map(|(_, value)| value),
+            );
+        }
+        if let Some(trace) = &mut self.trace {
+            trace.push(TraceEntry {
+                eip,
+                instruction,
+                operands,
+                write,
+            });
+        }
+        Ok(())
+    }
+
+    /// Whether `step` needs to record anything besides executing.
+    fn is_observed(&self) -> bool {
+        self.trace.is_some()
+            || self.profile.is_some()
+            || self.session.is_some()
+            || self.history.is_some()
+    }
+
+    fn execute(&mut self) -> Result<(), IntcodeError> {
         let eip = self.eip;
+        let instruction = self.prog.get(eip);
+        let opcode = instruction % 100;
         let relbase = self.relbase;
 
-        let get_addr = |prog: &mut Memory, offset: usize| -> usize {
+        let get_addr = |prog: &Memory, offset: usize| -> Result<usize, IntcodeError> {
             let parmode_pow = match offset {
                 1 => 100,
                 2 => 1000,
                 3 => 10000,
                 _ => unreachable!(),
             };
+            let param = prog.get(eip + offset);
             let out_addr = match (instruction / parmode_pow) % 10 {
-                0 => (prog[eip + offset]) as usize,
-                1 => eip + offset,
-                2 => (relbase + prog[eip + offset]) as usize,
+                0 => param,
+                1 => (eip + offset) as Word,
+                2 => relbase
+                    .checked_add(param)
+                    .ok_or(IntcodeError::Overflow { eip, instruction })?,
+                mode => {
+                    return Err(IntcodeError::InvalidParameterMode {
+                        eip,
+                        instruction,
+                        mode,
+                    })
+                }
+            };
+            if out_addr < 0 {
+                return Err(IntcodeError::NegativeAddress {
+                    eip,
+                    address: out_addr,
+                });
+            }
+            Ok(out_addr as usize)
+        };
+
+        let get_arg = |prog: &Memory, arg_num: usize| -> Result<Word, IntcodeError> {
+            Ok(prog.get(get_addr(prog, arg_num)?))
+        };
+
+        let get_out_addr = |prog: &Memory, arg_num: usize| -> Result<usize, IntcodeError> {
+            let parmode_pow = match arg_num {
+                1 => 100,
+                3 => 10000,
                 _ => unreachable!(),
             };
-            if out_addr >= prog.len() {
-                prog.resize(out_addr + 1, 0);
+            if (instruction / parmode_pow) % 10 == 1 {
+                Err(IntcodeError::WriteToImmediate { eip, instruction })
+            } else {
+                get_addr(prog, arg_num)
             }
-            out_addr
         };
 
-        let get_arg = |prog: &mut Memory, arg_num: usize| -> Word {
-            let addr = get_addr(prog, arg_num);
-            prog[addr]
+        let write = |prog: &mut Memory, address: usize, value: Word| -> Result<(), IntcodeError> {
+            prog.set(address, value)
+                .map_err(|_| IntcodeError::MemoryLimitExceeded { eip, address })
+        };
+
+        let jump_target = |target: Word| -> Result<usize, IntcodeError> {
+            if target < 0 {
+                Err(IntcodeError::NegativeAddress {
+                    eip,
+                    address: target,
+                })
+            } else {
+                Ok(target as usize)
+            }
         };
 
         self.eip = match opcode {
             OP_ADD => {
-                let io = get_addr(&mut self.prog, 3);
-                self.prog[io] = get_arg(&mut self.prog, 1) + get_arg(&mut self.prog, 2);
+                let io = get_out_addr(&self.prog, 3)?;
+                let value = get_arg(&self.prog, 1)?.wrapping_add(get_arg(&self.prog, 2)?);
+                write(&mut self.prog, io, value)?;
                 self.eip + 4
             }
 
             OP_MULTIPLY => {
-                let io = get_addr(&mut self.prog, 3);
-                self.prog[io] = get_arg(&mut self.prog, 1) * get_arg(&mut self.prog, 2);
+                let io = get_out_addr(&self.prog, 3)?;
+                let value = get_arg(&self.prog, 1)?.wrapping_mul(get_arg(&self.prog, 2)?);
+                write(&mut self.prog, io, value)?;
                 self.eip + 4
             }
 
             OP_INPUT => {
-                let io = get_addr(&mut self.prog, 1);
-                if let Some(i) = self.input.pop_front() {
-                    self.prog[io] = i;
+                let io = get_out_addr(&self.prog, 1)?;
+                if let Some(i) = self.input.read() {
+                    write(&mut self.prog, io, i)?;
                     self.eip + 2
                 } else {
                     self.eip
@@ -88,90 +527,325 @@ impl IntcodeComputer {
             }
 
             OP_OUTPUT => {
-                self.output.push_back(get_arg(&mut self.prog, 1));
+                self.output.write(get_arg(&self.prog, 1)?);
                 self.eip + 2
             }
 
             OP_JUMP_NONZERO => {
-                if get_arg(&mut self.prog, 1) != 0 {
-                    get_arg(&mut self.prog, 2) as usize
+                if get_arg(&self.prog, 1)? != 0 {
+                    jump_target(get_arg(&self.prog, 2)?)?
                 } else {
                     self.eip + 3
                 }
             }
 
             OP_JUMP_ZERO => {
-                if get_arg(&mut self.prog, 1) == 0 {
-                    get_arg(&mut self.prog, 2) as usize
+                if get_arg(&self.prog, 1)? == 0 {
+                    jump_target(get_arg(&self.prog, 2)?)?
                 } else {
                     self.eip + 3
                 }
             }
 
             OP_LESS => {
-                let io = get_addr(&mut self.prog, 3);
-                self.prog[io] = if get_arg(&mut self.prog, 1) < get_arg(&mut self.prog, 2) {
-                    1
-                } else {
-                    0
-                };
+                let io = get_out_addr(&self.prog, 3)?;
+                let value = get_arg(&self.prog, 1)? < get_arg(&self.prog, 2)?;
+                write(&mut self.prog, io, value as Word)?;
                 self.eip + 4
             }
 
             OP_EQ => {
-                let io = get_addr(&mut self.prog, 3);
-                self.prog[io] = if get_arg(&mut self.prog, 1) == get_arg(&mut self.prog, 2) {
-                    1
-                } else {
-                    0
-                };
+                let io = get_out_addr(&self.prog, 3)?;
+                let value = get_arg(&self.prog, 1)? == get_arg(&self.prog, 2)?;
+                write(&mut self.prog, io, value as Word)?;
                 self.eip + 4
             }
 
             OP_RELBASE => {
-                self.relbase += get_arg(&mut self.prog, 1);
+                self.relbase = self
+                    .relbase
+                    .checked_add(get_arg(&self.prog, 1)?)
+                    .ok_or(IntcodeError::Overflow { eip, instruction })?;
                 self.eip + 2
             }
 
             OP_HALT => self.eip,
-            _ => unreachable!(),
+            _ => {
+                return match self.opcodes.get_shared(opcode) {
+                    Some(custom) => self.execute_custom(&custom),
+                    None => Err(IntcodeError::InvalidOpcode { eip, opcode }),
+                }
+            }
+        };
+
+        Ok(())
+    }
+
+    /// Runs the program until it halts, runs out of input or hits a configured limit.
+    pub fn resume(&mut self) -> StopReason {
+        // Left over if the last instruction was run with `step`
+        self.trapped = false;
+        if self.engine == Engine::Cached && !self.is_observed() {
+            self.resume_cached()
+        } else {
+            self.resume_stepwise()
+        }
+    }
+
+    fn resume_stepwise(&mut self) -> StopReason {
+        let limits = self.run_limits();
+        let mut steps = 0;
+        let mut outputs = 0;
+
+        let stop_reason = loop {
+            if !self.is_running() {
+                break StopReason::Halted;
+            }
+            if let Some(stop_reason) = limits.check(steps) {
+                break stop_reason;
+            }
+
+            let eip = self.eip;
+            let opcode = self.current_instruction() % 100;
+            if let Err(err) = self.step() {
+                break StopReason::Error(err);
+            }
+
+            match opcode {
+                OP_INPUT if self.eip == eip => break StopReason::NeedsInput,
+                OP_OUTPUT => {
+                    steps += 1;
+                    outputs += 1;
+                    if Some(outputs) == self.pause_on_output {
+                        break StopReason::OutputReady(outputs);
+                    }
+                }
+                _ => {
+                    steps += 1;
+                    if std::mem::take(&mut self.trapped) {
+                        break StopReason::Trapped { eip };
+                    }
+                }
+            }
         };
+
+        self.spend_budget(steps);
+        stop_reason
+    }
+
+    fn run_limits(&self) -> RunLimits {
+        RunLimits::new(self.step_limit, self.step_budget, self.timeout)
+    }
+
+    fn spend_budget(&mut self, steps: usize) {
+        if let Some(budget) = &mut self.step_budget {
+            *budget -= steps;
+        }
+    }
+
+    fn current_instruction(&self) -> Word {
+        self.prog.get(self.eip)
+    }
+
+    pub fn relbase(&self) -> Word {
+        self.relbase
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.current_instruction() != OP_HALT
     }
 
-    pub fn run<I>(mut self, input: I) -> Self
+    pub fn expects_input(&self) -> bool {
+        self.current_instruction() % 100 == OP_INPUT
+    }
+}
+
+impl<I, O> IntcodeComputer<I, O>
+where
+    I: IntcodeInput + Extend<Word>,
+    O: IntcodeOutput,
+{
+    /// Panics if the program fails; use `try_run` or `run_mut` to handle
+    /// untrusted programs.
+    pub fn run<It>(self, input: It) -> Self
     where
-        I: IntoIterator<Item = Word>,
+        It: IntoIterator<Item = Word>,
     {
-        self.run_mut(input);
-        self
+        self.try_run(input).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn try_run<It>(mut self, input: It) -> Result<Self, IntcodeError>
+    where
+        It: IntoIterator<Item = Word>,
+    {
+        match self.run_mut(input) {
+            StopReason::Error(err) => Err(err),
+            _ => Ok(self),
+        }
     }
 
-    pub fn run_mut<I>(&mut self, input: I) -> &mut Self
+    pub fn run_mut<It>(&mut self, input: It) -> StopReason
     where
-        I: IntoIterator<Item = Word>,
+        It: IntoIterator<Item = Word>,
     {
         self.input.extend(input);
-        while self.is_running() && !(self.input.is_empty() && self.expects_input()) {
-            self.step();
+        self.resume()
+    }
+}
+
+/// Parses a program from the first line of `lines`.
+pub fn parse_program(lines: &[String]) -> Result<Vec<Word>, AocError> {
+    split_columns(first_line(lines)?, ",")
+        .map(|(column, word)| parse_at(word.trim(), 1, column))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_err(program: Vec<Word>) -> IntcodeError {
+        match IntcodeComputer::new(program).run_mut(None) {
+            StopReason::Error(err) => err,
+            reason => panic!("Expected an error, got: {:?}", reason),
         }
-        self
     }
 
-    pub fn is_running(&self) -> bool {
-        self.prog[self.eip] != OP_HALT
+    #[test]
+    fn invalid_opcode_is_an_error() {
+        assert_eq!(
+            run_err(vec![1101, 1, 2, 5, 42, 0]),
+            IntcodeError::InvalidOpcode { eip: 4, opcode: 42 }
+        );
     }
 
-    pub fn expects_input(&self) -> bool {
-        self.prog[self.eip] % 100 == OP_INPUT
+    #[test]
+    fn jump_past_end_of_memory_is_an_error() {
+        assert_eq!(
+            run_err(vec![1105, 1, 100]),
+            IntcodeError::InvalidOpcode {
+                eip: 100,
+                opcode: 0
+            }
+        );
     }
-}
 
-impl From<&[String]> for IntcodeComputer {
-    fn from(lines: &[String]) -> IntcodeComputer {
-        Self::new(parse_program(lines))
+    #[test]
+    fn invalid_parameter_mode_is_an_error() {
+        assert_eq!(
+            run_err(vec![304, 0, 99]),
+            IntcodeError::InvalidParameterMode {
+                eip: 0,
+                instruction: 304,
+                mode: 3
+            }
+        );
     }
-}
 
-pub fn parse_program(lines: &[String]) -> Vec<Word> {
-    lines[0].split(',').map(|s| s.parse().unwrap()).collect()
+    #[test]
+    fn negative_address_is_an_error() {
+        assert_eq!(
+            run_err(vec![109, -5, 204, 0, 99]),
+            IntcodeError::NegativeAddress {
+                eip: 2,
+                address: -5
+            }
+        );
+        assert_eq!(
+            run_err(vec![1105, 1, -3]),
+            IntcodeError::NegativeAddress {
+                eip: 0,
+                address: -3
+            }
+        );
+    }
+
+    #[test]
+    fn write_to_immediate_is_an_error() {
+        assert_eq!(
+            run_err(vec![11101, 1, 1, 0, 99]),
+            IntcodeError::WriteToImmediate {
+                eip: 0,
+                instruction: 11101
+            }
+        );
+    }
+
+    #[test]
+    fn relbase_overflow_is_an_error() {
+        assert_eq!(
+            run_err(vec![109, Word::MAX, 109, 1, 99]),
+            IntcodeError::Overflow {
+                eip: 2,
+                instruction: 109
+            }
+        );
+    }
+
+    #[test]
+    fn arithmetic_wraps_around() {
+        assert_eq!(
+            IntcodeComputer::new(vec![1102, Word::MAX, 2, 7, 4, 7, 99, 0])
+                .run(None)
+                .output,
+            vec![-2]
+        );
+    }
+
+    #[test]
+    fn run_mut_reports_stop_reason() {
+        let mut computer = IntcodeComputer::new(vec![3, 9, 4, 9, 4, 9, 1105, 1, 0, 0]);
+        assert_eq!(computer.run_mut(None), StopReason::NeedsInput);
+
+        computer.pause_on_output = Some(2);
+        assert_eq!(computer.run_mut(Some(7)), StopReason::OutputReady(2));
+        assert_eq!(computer.output, vec![7, 7]);
+
+        computer.step_limit = Some(2);
+        assert_eq!(computer.run_mut(Some(8)), StopReason::StepLimitReached);
+        assert_eq!(computer.eip, 2);
+        computer.step_limit = None;
+        assert_eq!(computer.run_mut(None), StopReason::OutputReady(2));
+        assert_eq!(computer.output, vec![7, 7, 8, 8]);
+
+        assert_eq!(
+            IntcodeComputer::new(vec![99]).run_mut(None),
+            StopReason::Halted
+        );
+    }
+
+    #[test]
+    fn step_budget_is_shared_between_runs() {
+        let mut computer = IntcodeComputer::new(vec![3, 9, 4, 9, 4, 9, 1105, 1, 0, 0]);
+        computer.step_budget = Some(5);
+        computer.pause_on_output = Some(1);
+        assert_eq!(computer.run_mut(vec![7, 8]), StopReason::OutputReady(1));
+        assert_eq!(computer.step_budget, Some(3));
+
+        computer.step_limit = Some(2);
+        assert_eq!(computer.resume(), StopReason::OutputReady(1));
+        assert_eq!(computer.resume(), StopReason::BudgetExhausted);
+        assert_eq!(computer.step_budget, Some(0));
+        assert_eq!(computer.eip, 2);
+        assert_eq!(computer.resume(), StopReason::BudgetExhausted);
+
+        computer.step_budget = None;
+        assert_eq!(computer.resume(), StopReason::OutputReady(1));
+        assert_eq!(computer.output, vec![7, 7, 8]);
+    }
+
+    #[test]
+    fn endless_loop_times_out() {
+        let mut computer = IntcodeComputer::new(vec![1105, 1, 0]);
+        computer.timeout = Some(Duration::from_millis(10));
+        assert_eq!(computer.run_mut(None), StopReason::TimedOut);
+    }
+
+    #[test]
+    fn runs_with_huge_timeout() {
+        let mut computer = IntcodeComputer::new(vec![3, 0, 4, 0, 99]);
+        computer.timeout = Some(Duration::from_secs_f64(1e19));
+        assert_eq!(computer.run_mut(Some(7)), StopReason::Halted);
+        assert_eq!(computer.output, vec![7]);
+    }
 }
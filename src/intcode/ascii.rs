@@ -0,0 +1,127 @@
+use super::IntcodeComputer;
+use super::StopReason;
+use super::Word;
+
+/// Wraps a computer running a program that communicates in ASCII text, like
+/// the ones in days 17, 21 and 25. Such programs often end their output with
+/// a number that doesn't fit in ASCII, for example the puzzle answer; these
+/// trailing values are kept apart from the text.
+#[derive(Clone)]
+pub struct AsciiIntcode {
+    pub computer: IntcodeComputer,
+}
+
+fn is_ascii(value: Word) -> bool {
+    (0..128).contains(&value)
+}
+
+impl AsciiIntcode {
+    pub fn new(computer: IntcodeComputer) -> Self {
+        AsciiIntcode { computer }
+    }
+
+    /// Queues `line` and a newline as input.
+    pub fn send_line(&mut self, line: &str) {
+        self.computer
+            .input
+            .extend(line.bytes().chain(Some(b'\n')).map(Word::from));
+    }
+
+    /// Runs the program until it needs more input or halts, and returns the
+    /// text it printed. If the program stops for any other reason, returns
+    /// that reason and leaves the output to read.
+    pub fn read_until_prompt(&mut self) -> Result<String, StopReason> {
+        loop {
+            match self.computer.resume() {
+                StopReason::OutputReady(_) => {}
+                StopReason::NeedsInput | StopReason::Halted => return Ok(self.read_text()),
+                reason => return Err(reason),
+            }
+        }
+    }
+
+    /// Takes the output printed so far as text, up to any trailing non-ASCII
+    /// values. Those are left to be taken with [`read_values`](Self::read_values).
+    pub fn read_text(&mut self) -> String {
+        let output = &mut self.computer.output;
+        let text_len = output.len()
+            - output
+                .iter()
+                .rev()
+                .take_while(|value| !is_ascii(**value))
+                .count();
+        output
+            .drain(..text_len)
+            .map(|value| {
+                if is_ascii(value) {
+                    value as u8 as char
+                } else {
+                    char::REPLACEMENT_CHARACTER
+                }
+            })
+            .collect()
+    }
+
+    /// Takes the values left in the output after [`read_text`](Self::read_text).
+    pub fn read_values(&mut self) -> Vec<Word> {
+        self.computer.output.drain(..).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::asm::assemble;
+
+    #[test]
+    fn separates_text_from_trailing_values() {
+        let mut computer = AsciiIntcode::new(IntcodeComputer::new(
+            assemble(
+                "
+                OUT #72
+                OUT #1000
+                OUT #105
+                OUT #10
+                IN -> [rb+100]
+                OUT [rb+100]
+                OUT #10
+                OUT #1234
+                OUT #-5
+                HALT
+                ",
+            )
+            .unwrap(),
+        ));
+        assert_eq!(computer.read_until_prompt(), Ok("H\u{fffd}i\n".to_string()));
+        assert!(computer.read_values().is_empty());
+
+        computer.send_line("x");
+        assert_eq!(computer.read_until_prompt(), Ok("x\n".to_string()));
+        assert_eq!(computer.read_values(), vec![1234, -5]);
+        assert!(!computer.computer.is_running());
+    }
+
+    #[test]
+    fn stops_at_step_budget() {
+        let mut computer = AsciiIntcode::new(IntcodeComputer::new(
+            assemble(
+                "
+                loop:
+                OUT #46
+                JNZ #1, #loop
+                ",
+            )
+            .unwrap(),
+        ));
+        computer.computer.step_budget = Some(10);
+        assert_eq!(
+            computer.read_until_prompt(),
+            Err(StopReason::BudgetExhausted)
+        );
+        assert_eq!(computer.read_text(), ".....");
+        assert_eq!(
+            computer.read_until_prompt(),
+            Err(StopReason::BudgetExhausted)
+        );
+    }
+}
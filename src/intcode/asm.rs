@@ -0,0 +1,433 @@
+use super::ParamKind;
+use super::Word;
+use super::OPCODES;
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum AsmErrorKind {
+    UnknownMnemonic(String),
+    OperandCount {
+        mnemonic: &'static str,
+        reads: usize,
+        writes: usize,
+    },
+    InvalidOperand(String),
+    ImmediateWrite(String),
+    UndefinedLabel(String),
+    DuplicateLabel(String),
+    AddressOverflow(String),
+    AddressMismatch {
+        expected: usize,
+        actual: usize,
+    },
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AsmError {
+    pub line: usize,
+    pub kind: AsmErrorKind,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            AsmErrorKind::UnknownMnemonic(mnemonic) => write!(f, "Unknown mnemonic: {}", mnemonic),
+            AsmErrorKind::OperandCount {
+                mnemonic,
+                reads,
+                writes,
+            } => write!(
+                f,
+                "{} takes {} input operand(s) and {} output operand(s)",
+                mnemonic, reads, writes
+            ),
+            AsmErrorKind::InvalidOperand(operand) => write!(f, "Invalid operand: {}", operand),
+            AsmErrorKind::ImmediateWrite(operand) => {
+                write!(f, "Output operand cannot be immediate: {}", operand)
+            }
+            AsmErrorKind::UndefinedLabel(label) => write!(f, "Undefined label: {}", label),
+            AsmErrorKind::DuplicateLabel(label) => write!(f, "Duplicate label: {}", label),
+            AsmErrorKind::AddressOverflow(operand) => {
+                write!(f, "Address out of range: {}", operand)
+            }
+            AsmErrorKind::AddressMismatch { expected, actual } => write!(
+                f,
+                "Address label {} does not match actual address {}",
+                expected, actual
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+#[derive(Debug)]
+enum Value {
+    Number(Word),
+    Label(String, Word),
+}
+
+#[derive(Debug)]
+enum Item {
+    Instruction {
+        opcode: Word,
+        operands: Vec<(Word, Value)>,
+    },
+    Data(Vec<Value>),
+}
+
+impl Item {
+    fn size(&self) -> usize {
+        match self {
+            Self::Instruction { operands, .. } => 1 + operands.len(),
+            Self::Data(values) => values.len(),
+        }
+    }
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .map(|c| c.is_ascii_alphabetic() || c == '_')
+        .unwrap_or(false)
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn split_label(line: &str) -> Option<(&str, &str)> {
+    let colon = line.find(':')?;
+    let label = line[..colon].trim();
+    if is_identifier(label) || (!label.is_empty() && label.chars().all(|c| c.is_ascii_digit())) {
+        Some((label, &line[colon + 1..]))
+    } else {
+        None
+    }
+}
+
+fn parse_value(s: &str) -> Result<Value, AsmErrorKind> {
+    let s: String = s.chars().filter(|c| !c.is_whitespace()).collect();
+    if let Ok(number) = s.parse() {
+        return Ok(Value::Number(number));
+    }
+
+    let (label, offset) = match s.find(['+', '-']) {
+        Some(i) => (
+            &s[..i],
+            s[i..]
+                .trim_start_matches('+')
+                .parse()
+                .map_err(|_| AsmErrorKind::InvalidOperand(s.clone()))?,
+        ),
+        None => (s.as_str(), 0),
+    };
+    if is_identifier(label) {
+        Ok(Value::Label(label.to_string(), offset))
+    } else {
+        Err(AsmErrorKind::InvalidOperand(s.clone()))
+    }
+}
+
+fn parse_operand(s: &str) -> Result<(Word, Value), AsmErrorKind> {
+    let s = s.trim();
+    if let Some(value) = s.strip_prefix('#') {
+        return Ok((1, parse_value(value)?));
+    }
+
+    let inner: String = s
+        .strip_prefix('[')
+        .and_then(|s| s.strip_suffix(']'))
+        .unwrap_or(s)
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    if inner.is_empty() {
+        Err(AsmErrorKind::InvalidOperand(s.to_string()))
+    } else if inner == "rb" {
+        Ok((2, Value::Number(0)))
+    } else if let Some(offset) = inner
+        .strip_prefix("rb")
+        .filter(|offset| offset.starts_with('+') || offset.starts_with('-'))
+    {
+        match parse_value(offset) {
+            Ok(Value::Number(offset)) => Ok((2, Value::Number(offset))),
+            _ => Err(AsmErrorKind::InvalidOperand(s.to_string())),
+        }
+    } else {
+        Ok((0, parse_value(&inner)?))
+    }
+}
+
+fn split_operands(s: &str) -> Vec<&str> {
+    let s = s.trim();
+    if s.is_empty() {
+        Vec::new()
+    } else {
+        s.split(',').collect()
+    }
+}
+
+fn parse_item(line: &str) -> Result<Item, AsmErrorKind> {
+    let (mnemonic, rest) = match line.find(char::is_whitespace) {
+        Some(i) => (&line[..i], &line[i..]),
+        None => (line, ""),
+    };
+
+    if mnemonic.eq_ignore_ascii_case(".data") || mnemonic.eq_ignore_ascii_case("DATA") {
+        return Ok(Item::Data(
+            split_operands(rest)
+                .into_iter()
+                .map(parse_value)
+                .collect::<Result<_, _>>()?,
+        ));
+    }
+
+    let info = OPCODES
+        .iter()
+        .find(|info| info.mnemonic.eq_ignore_ascii_case(mnemonic))
+        .ok_or_else(|| AsmErrorKind::UnknownMnemonic(mnemonic.to_string()))?;
+
+    let mut parts = rest.split("->");
+    let mut reads = split_operands(parts.next().unwrap_or("")).into_iter();
+    let mut writes = parts.map(str::trim);
+
+    let operand_count_error = || AsmErrorKind::OperandCount {
+        mnemonic: info.mnemonic,
+        reads: info
+            .params
+            .iter()
+            .filter(|kind| **kind == ParamKind::Read)
+            .count(),
+        writes: info
+            .params
+            .iter()
+            .filter(|kind| **kind == ParamKind::Write)
+            .count(),
+    };
+
+    let mut operands = Vec::with_capacity(info.params.len());
+    for kind in info.params {
+        let operand = match kind {
+            ParamKind::Read => reads.next(),
+            ParamKind::Write => writes.next(),
+        }
+        .ok_or_else(operand_count_error)?;
+        let (mode, value) = parse_operand(operand)?;
+        if mode == 1 && *kind == ParamKind::Write {
+            return Err(AsmErrorKind::ImmediateWrite(operand.trim().to_string()));
+        }
+        operands.push((mode, value));
+    }
+    if reads.next().is_some() || writes.next().is_some() {
+        return Err(operand_count_error());
+    }
+
+    Ok(Item::Instruction {
+        opcode: info.opcode,
+        operands,
+    })
+}
+
+/// Assembles a program written in the format printed by
+/// [`disassemble`](super::disasm::disassemble), with the addition of named
+/// labels, `.data` directives and `;` comments. Operands are written as
+/// `#value` (immediate), `[addr]` (position) or `[rb+offset]` (relative);
+/// brackets are optional. Labels can be used in place of numbers, optionally
+/// with a constant offset as in `[buffer+2]`.
+pub fn assemble(source: &str) -> Result<Vec<Word>, AsmError> {
+    let mut labels: HashMap<String, usize> = HashMap::new();
+    let mut items: Vec<(usize, Item)> = Vec::new();
+    let mut addr = 0;
+
+    for (i, line) in source.lines().enumerate() {
+        let line_num = i + 1;
+        let error = |kind| AsmError {
+            line: line_num,
+            kind,
+        };
+        let mut rest = line.split(';').next().unwrap_or("").trim();
+
+        while let Some((label, after)) = split_label(rest) {
+            if let Ok(expected) = label.parse::<usize>() {
+                if expected != addr {
+                    return Err(error(AsmErrorKind::AddressMismatch {
+                        expected,
+                        actual: addr,
+                    }));
+                }
+            } else if labels.insert(label.to_string(), addr).is_some() {
+                return Err(error(AsmErrorKind::DuplicateLabel(label.to_string())));
+            }
+            rest = after.trim();
+        }
+
+        if !rest.is_empty() {
+            let item = parse_item(rest).map_err(error)?;
+            addr += item.size();
+            items.push((line_num, item));
+        }
+    }
+
+    let mut program = Vec::with_capacity(addr);
+    for (line_num, item) in items {
+        let error = |kind| AsmError {
+            line: line_num,
+            kind,
+        };
+        let resolve = |value: &Value| match value {
+            Value::Number(number) => Ok(*number),
+            Value::Label(label, offset) => labels
+                .get(label)
+                .ok_or_else(|| error(AsmErrorKind::UndefinedLabel(label.clone())))
+                .and_then(|addr| {
+                    (*addr as Word).checked_add(*offset).ok_or_else(|| {
+                        error(AsmErrorKind::AddressOverflow(format!(
+                            "{}{:+}",
+                            label, offset
+                        )))
+                    })
+                }),
+        };
+
+        match item {
+            Item::Instruction { opcode, operands } => {
+                program.push(
+                    opcode
+                        + operands
+                            .iter()
+                            .rev()
+                            .fold(0, |modes, (mode, _)| modes * 10 + mode)
+                            * 100,
+                );
+                for (_, value) in &operands {
+                    program.push(resolve(value)?);
+                }
+            }
+            Item::Data(values) => {
+                for value in &values {
+                    program.push(resolve(value)?);
+                }
+            }
+        }
+    }
+
+    Ok(program)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::day_input_filename;
+    use crate::common::get_file_lines;
+    use crate::intcode::disasm::disassemble;
+    use crate::intcode::parse_program;
+    use crate::intcode::IntcodeComputer;
+
+    fn disassemble_to_string(prog: &[Word]) -> String {
+        disassemble(prog)
+            .iter()
+            .map(|line| line.to_string())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn assembles_readme_example() {
+        let source = "
+            IN -> [a]
+            IN -> [b]
+            ADD [a], [b] -> [a]
+            OUT [a]
+            HALT
+        a:  .data 0
+        b:  .data 0
+        ";
+        let program = assemble(source).unwrap();
+        assert_eq!(program, vec![3, 11, 3, 12, 1, 11, 12, 11, 4, 11, 99, 0, 0]);
+        assert_eq!(
+            IntcodeComputer::new(program).run(vec![4, 7]).output,
+            vec![11]
+        );
+    }
+
+    #[test]
+    fn assembles_operand_modes_and_labels() {
+        let source = "
+        start:
+            ARB #stack          ; set up the stack
+            ADD [rb+3], #5 -> [104]
+            MUL rb-1, #-2 -> rb
+            JNZ #1, #start+2
+        stack: DATA 7, stack, -1
+        ";
+        assert_eq!(
+            assemble(source).unwrap(),
+            vec![109, 13, 1201, 3, 5, 104, 21202, -1, -2, 0, 1105, 1, 2, 7, 13, -1]
+        );
+    }
+
+    #[test]
+    fn reports_errors_with_line_numbers() {
+        let error = |source| assemble(source).unwrap_err();
+        assert_eq!(
+            error("HALT\nFOO #1"),
+            AsmError {
+                line: 2,
+                kind: AsmErrorKind::UnknownMnemonic("FOO".to_string())
+            }
+        );
+        assert_eq!(
+            error("ADD #1, #2"),
+            AsmError {
+                line: 1,
+                kind: AsmErrorKind::OperandCount {
+                    mnemonic: "ADD",
+                    reads: 2,
+                    writes: 1
+                }
+            }
+        );
+        assert_eq!(
+            error("IN -> #3").kind,
+            AsmErrorKind::ImmediateWrite("#3".to_string())
+        );
+        assert_eq!(
+            error("OUT [nowhere]").kind,
+            AsmErrorKind::UndefinedLabel("nowhere".to_string())
+        );
+        assert_eq!(
+            error("a: HALT\na: HALT").kind,
+            AsmErrorKind::DuplicateLabel("a".to_string())
+        );
+        assert_eq!(
+            error("0000: HALT\n0002: HALT").kind,
+            AsmErrorKind::AddressMismatch {
+                expected: 2,
+                actual: 1
+            }
+        );
+        assert_eq!(
+            error("OUT [a+9223372036854775807]\na: HALT").kind,
+            AsmErrorKind::AddressOverflow("a+9223372036854775807".to_string())
+        );
+        assert_eq!(
+            error("OUT [rb+x]").kind,
+            AsmErrorKind::InvalidOperand("[rb+x]".to_string())
+        );
+    }
+
+    #[test]
+    fn round_trips_disassembly() {
+        for day in &[9, 13, 25] {
+            let program =
+                parse_program(&get_file_lines(&day_input_filename(*day)).unwrap()).unwrap();
+            assert_eq!(
+                assemble(&disassemble_to_string(&program)).unwrap(),
+                program,
+                "Day {} program did not survive a round trip",
+                day
+            );
+        }
+    }
+}
@@ -0,0 +1,483 @@
+use super::disasm::Instruction;
+use super::disasm::Operand;
+use super::ParamKind;
+use super::Word;
+use super::OP_HALT;
+use super::OP_JUMP_NONZERO;
+use super::OP_JUMP_ZERO;
+use super::OP_RELBASE;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EdgeKind {
+    /// Falling through to the next instruction, or a jump not taken.
+    Next,
+    Jump,
+    /// A jump to a subroutine.
+    Call,
+    /// From a call to where the subroutine returns.
+    AfterCall,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Edge {
+    pub to: usize,
+    pub kind: EdgeKind,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BasicBlock {
+    pub start: usize,
+    pub instructions: Vec<(usize, Instruction)>,
+    pub edges: Vec<Edge>,
+}
+
+/// A call following the stack convention of the day programs: the return
+/// address is stored at `[rb+0]`, then an unconditional jump goes to the
+/// subroutine.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Call {
+    /// The address of the jump.
+    pub site: usize,
+    /// `None` for calls through a computed address.
+    pub target: Option<usize>,
+    pub return_addr: usize,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Subroutine {
+    pub entry: usize,
+    /// The size of the stack frame, if the subroutine starts by allocating one
+    /// with `ARB`.
+    pub frame_size: Option<Word>,
+    pub call_sites: Vec<usize>,
+}
+
+/// An instruction that writes to a constant address inside another
+/// instruction.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct CodeWrite {
+    /// The address of the writing instruction.
+    pub addr: usize,
+    /// The address written to.
+    pub target: usize,
+    /// The address of the instruction that is written over.
+    pub instruction: usize,
+}
+
+/// The control flow graph of the code reachable from address 0, found without
+/// running the program.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ControlFlowGraph {
+    pub blocks: BTreeMap<usize, BasicBlock>,
+    pub calls: Vec<Call>,
+    pub subroutines: BTreeMap<usize, Subroutine>,
+    pub code_writes: Vec<CodeWrite>,
+    /// Jumps to `[rb+0]` after a call, which return from a subroutine.
+    pub returns: Vec<usize>,
+    /// Jumps to computed addresses other than returns.
+    pub indirect_jumps: Vec<usize>,
+}
+
+fn is_jump(instruction: &Instruction) -> bool {
+    instruction.opcode == OP_JUMP_NONZERO || instruction.opcode == OP_JUMP_ZERO
+}
+
+fn ends_block(instruction: &Instruction) -> bool {
+    is_jump(instruction) || instruction.opcode == OP_HALT
+}
+
+/// The target of a jump that is always taken.
+fn unconditional_target(instruction: &Instruction) -> Option<&Operand> {
+    match (instruction.opcode, instruction.operands.as_slice()) {
+        (OP_JUMP_NONZERO, [Operand::Immediate(cond), target]) if *cond != 0 => Some(target),
+        (OP_JUMP_ZERO, [Operand::Immediate(0), target]) => Some(target),
+        _ => None,
+    }
+}
+
+/// The call made by the instruction at `addr` and the jump after it, if they
+/// make up a call.
+fn call_at(prog: &[Word], addr: usize, instruction: &Instruction) -> Option<Call> {
+    let return_addr = instruction.pushed_constant()?;
+    if instruction.operands.last() != Some(&Operand::Relative(0)) || return_addr < 0 {
+        return None;
+    }
+    let site = addr + instruction.size();
+    let target = match unconditional_target(&Instruction::decode(prog, site)?)? {
+        Operand::Immediate(target) if *target >= 0 => Some(*target as usize),
+        Operand::Immediate(_) => return None,
+        _ => None,
+    };
+    Some(Call {
+        site,
+        target,
+        return_addr: return_addr as usize,
+    })
+}
+
+/// Decodes the instructions reachable from address 0 by following
+/// fall-through, constant jump targets and returns from calls.
+fn reachable_code(prog: &[Word]) -> (BTreeMap<usize, Instruction>, Vec<Call>) {
+    let mut code = BTreeMap::new();
+    let mut calls = Vec::new();
+    let mut claimed = vec![false; prog.len()];
+    let mut queue = vec![0];
+
+    while let Some(addr) = queue.pop() {
+        if addr >= prog.len() || claimed[addr] {
+            continue;
+        }
+        if let Some(instruction) = Instruction::decode(prog, addr) {
+            let end = addr + instruction.size();
+            if claimed[addr..end].iter().any(|c| *c) {
+                continue;
+            }
+            claimed[addr..end].iter_mut().for_each(|c| *c = true);
+
+            queue.extend(instruction.successors(addr));
+            if let Some(call) = call_at(prog, addr, &instruction) {
+                queue.push(call.return_addr);
+                calls.push(call);
+            }
+            code.insert(addr, instruction);
+        }
+    }
+
+    calls.retain(|call| code.contains_key(&call.site));
+    calls.sort_by_key(|call| call.site);
+    (code, calls)
+}
+
+impl ControlFlowGraph {
+    pub fn build(prog: &[Word]) -> Self {
+        let (code, calls) = reachable_code(prog);
+        let calls_by_site: BTreeMap<usize, Call> =
+            calls.iter().map(|call| (call.site, *call)).collect();
+
+        let mut leaders: BTreeSet<usize> = BTreeSet::new();
+        leaders.insert(0);
+        for (addr, instruction) in &code {
+            if ends_block(instruction) {
+                leaders.extend(instruction.successors(*addr));
+                leaders.insert(addr + instruction.size());
+            }
+        }
+        leaders.extend(calls.iter().map(|call| call.return_addr));
+
+        let mut starts: Vec<usize> = Vec::new();
+        let mut previous: Option<(usize, &Instruction)> = None;
+        for (addr, instruction) in &code {
+            let continues = match previous {
+                Some((prev_addr, prev)) => {
+                    prev_addr + prev.size() == *addr && !ends_block(prev) && !leaders.contains(addr)
+                }
+                None => false,
+            };
+            if !continues {
+                starts.push(*addr);
+            }
+            previous = Some((*addr, instruction));
+        }
+
+        let mut blocks = BTreeMap::new();
+        let mut returns = Vec::new();
+        let mut indirect_jumps = Vec::new();
+        for (i, start) in starts.iter().enumerate() {
+            let end = starts.get(i + 1).copied().unwrap_or(usize::MAX);
+            let instructions: Vec<(usize, Instruction)> = code
+                .range(*start..end)
+                .map(|(addr, instruction)| (*addr, instruction.clone()))
+                .collect();
+            let (last_addr, last) = instructions.last().unwrap();
+            let next = last_addr + last.size();
+
+            let mut edges = Vec::new();
+            if let Some(call) = calls_by_site.get(last_addr) {
+                edges.extend(call.target.map(|to| Edge {
+                    to,
+                    kind: EdgeKind::Call,
+                }));
+                edges.push(Edge {
+                    to: call.return_addr,
+                    kind: EdgeKind::AfterCall,
+                });
+            } else if ends_block(last) {
+                for to in last.successors(*last_addr) {
+                    let kind = if to == next {
+                        EdgeKind::Next
+                    } else {
+                        EdgeKind::Jump
+                    };
+                    if !edges.contains(&Edge { to, kind }) {
+                        edges.push(Edge { to, kind });
+                    }
+                }
+                match last.operands.get(1) {
+                    Some(Operand::Relative(0)) if unconditional_target(last).is_some() => {
+                        returns.push(*last_addr)
+                    }
+                    Some(Operand::Position(_)) | Some(Operand::Relative(_)) => {
+                        indirect_jumps.push(*last_addr)
+                    }
+                    _ => {}
+                }
+            } else if code.contains_key(&next) {
+                edges.push(Edge {
+                    to: next,
+                    kind: EdgeKind::Next,
+                });
+            }
+
+            blocks.insert(
+                *start,
+                BasicBlock {
+                    start: *start,
+                    instructions,
+                    edges,
+                },
+            );
+        }
+
+        // Targets that couldn't be decoded, or that are inside other
+        // instructions, have no block
+        let starts: BTreeSet<usize> = blocks.keys().copied().collect();
+        for block in blocks.values_mut() {
+            block.edges.retain(|edge| starts.contains(&edge.to));
+        }
+
+        let mut subroutines: BTreeMap<usize, Subroutine> = BTreeMap::new();
+        for call in &calls {
+            if let Some(target) = call.target {
+                subroutines
+                    .entry(target)
+                    .or_insert_with(|| Subroutine {
+                        entry: target,
+                        frame_size: match code.get(&target) {
+                            Some(Instruction {
+                                opcode: OP_RELBASE,
+                                operands,
+                            }) => match operands.as_slice() {
+                                [Operand::Immediate(size)] => Some(*size),
+                                _ => None,
+                            },
+                            _ => None,
+                        },
+                        call_sites: Vec::new(),
+                    })
+                    .call_sites
+                    .push(call.site);
+            }
+        }
+
+        let mut code_writes = Vec::new();
+        for (addr, instruction) in &code {
+            for (kind, operand) in instruction.info().params.iter().zip(&instruction.operands) {
+                if let (ParamKind::Write, Operand::Position(target)) = (kind, operand) {
+                    if *target < 0 {
+                        continue;
+                    }
+                    let target = *target as usize;
+                    if let Some((written, written_instruction)) = code.range(..=target).next_back()
+                    {
+                        if target < written + written_instruction.size() {
+                            code_writes.push(CodeWrite {
+                                addr: *addr,
+                                target,
+                                instruction: *written,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        ControlFlowGraph {
+            blocks,
+            calls,
+            subroutines,
+            code_writes,
+            returns,
+            indirect_jumps,
+        }
+    }
+
+    /// The addresses that jumps and calls with constant targets go to.
+    pub fn jump_targets(&self) -> BTreeSet<usize> {
+        self.blocks
+            .values()
+            .flat_map(|block| &block.edges)
+            .filter(|edge| edge.kind == EdgeKind::Jump || edge.kind == EdgeKind::Call)
+            .map(|edge| edge.to)
+            .collect()
+    }
+
+    /// The start of the block containing the instruction at `addr`.
+    fn block_of(&self, addr: usize) -> Option<usize> {
+        self.blocks
+            .range(..=addr)
+            .next_back()
+            .filter(|(_, block)| block.instructions.iter().any(|(a, _)| *a == addr))
+            .map(|(start, _)| *start)
+    }
+
+    /// Formats the graph in the Graphviz DOT language. Subroutine entries are
+    /// drawn with a double border, and writes to code as red dotted edges from
+    /// the writing block to the block that is written over.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        writeln!(dot, "digraph intcode {{").unwrap();
+        writeln!(dot, "    node [shape=box, fontname=\"monospace\"];").unwrap();
+
+        for block in self.blocks.values() {
+            let label: String = block
+                .instructions
+                .iter()
+                .map(|(addr, instruction)| format!("{:04}: {}\\l", addr, instruction))
+                .collect();
+            write!(dot, "    b{} [label=\"{}\"", block.start, label).unwrap();
+            if self.subroutines.contains_key(&block.start) {
+                write!(dot, ", peripheries=2").unwrap();
+            }
+            writeln!(dot, "];").unwrap();
+        }
+
+        for block in self.blocks.values() {
+            for edge in &block.edges {
+                let style = match edge.kind {
+                    EdgeKind::Next => "",
+                    EdgeKind::Jump => " [label=\"jump\"]",
+                    EdgeKind::Call => " [label=\"call\", style=bold]",
+                    EdgeKind::AfterCall => " [style=dashed]",
+                };
+                writeln!(dot, "    b{} -> b{}{};", block.start, edge.to, style).unwrap();
+            }
+        }
+
+        for write in &self.code_writes {
+            if let (Some(from), Some(to)) =
+                (self.block_of(write.addr), self.block_of(write.instruction))
+            {
+                writeln!(
+                    dot,
+                    "    b{} -> b{} [label=\"writes {}\", style=dotted, color=red];",
+                    from, to, write.target
+                )
+                .unwrap();
+            }
+        }
+
+        writeln!(dot, "}}").unwrap();
+        dot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::day_input_filename;
+    use crate::common::get_file_lines;
+    use crate::intcode::asm::assemble;
+    use crate::intcode::parse_program;
+
+    fn block_starts(cfg: &ControlFlowGraph) -> Vec<usize> {
+        cfg.blocks.keys().copied().collect()
+    }
+
+    #[test]
+    fn splits_blocks_at_jumps_and_targets() {
+        let program = assemble(
+            "
+                IN -> [n]
+            loop:
+                OUT [n]
+                ADD [n], #-1 -> [n]
+                JNZ [n], #loop
+                HALT
+            n:  .data 0
+            ",
+        )
+        .unwrap();
+        let cfg = ControlFlowGraph::build(&program);
+        assert_eq!(block_starts(&cfg), vec![0, 2, 11]);
+        assert_eq!(
+            cfg.blocks[&2].edges,
+            vec![
+                Edge {
+                    to: 2,
+                    kind: EdgeKind::Jump
+                },
+                Edge {
+                    to: 11,
+                    kind: EdgeKind::Next
+                },
+            ]
+        );
+        assert_eq!(cfg.jump_targets().into_iter().collect::<Vec<_>>(), vec![2]);
+        assert!(cfg.blocks[&11].edges.is_empty());
+    }
+
+    #[test]
+    fn finds_calls_and_returns() {
+        let program = assemble(
+            "
+                ARB #stack
+                ADD #ret, #0 -> [rb+0]
+                JZ #0, #double
+            ret:
+                OUT [rb+1]
+                HALT
+            double:
+                ARB #2
+                MUL [rb-1], #2 -> [rb-1]
+                ARB #-2
+                JNZ #1, [rb+0]
+            stack: .data 0, 21
+            ",
+        )
+        .unwrap();
+        let cfg = ControlFlowGraph::build(&program);
+        assert_eq!(
+            cfg.calls,
+            vec![Call {
+                site: 6,
+                target: Some(12),
+                return_addr: 9
+            }]
+        );
+        assert_eq!(cfg.subroutines[&12].frame_size, Some(2));
+        assert_eq!(cfg.returns, vec![20]);
+        assert!(cfg.indirect_jumps.is_empty());
+        assert_eq!(block_starts(&cfg), vec![0, 9, 12]);
+    }
+
+    #[test]
+    fn finds_self_modifying_writes_in_day19() {
+        let program = parse_program(&get_file_lines(&day_input_filename(19)).unwrap()).unwrap();
+        let cfg = ControlFlowGraph::build(&program);
+
+        // The subroutine at 225 calls a function pointer by writing it over
+        // the target of its jump at 247
+        assert!(cfg.code_writes.contains(&CodeWrite {
+            addr: 227,
+            target: 249,
+            instruction: 247
+        }));
+        assert_eq!(cfg.subroutines[&225].frame_size, Some(5));
+        assert!(cfg.subroutines[&303].call_sites.contains(&54));
+        // A call through a pointer stored at 108
+        assert!(cfg.calls.contains(&Call {
+            site: 192,
+            target: None,
+            return_addr: 195
+        }));
+        assert!(cfg.blocks.contains_key(&195));
+
+        let dot = cfg.to_dot();
+        assert!(dot.starts_with("digraph intcode {\n"));
+        assert!(dot.contains("b225 -> b225 [label=\"writes 249\", style=dotted, color=red];"));
+        assert!(dot.contains("b0 -> b282 [label=\"call\", style=bold];"));
+    }
+}
@@ -0,0 +1,671 @@
+use super::disasm::Instruction;
+use super::history::History;
+use super::IntcodeComputer;
+use super::IntcodeError;
+use super::Word;
+use super::OPCODES;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::io::Write;
+
+const DEFAULT_LIST_COUNT: usize = 5;
+const PEEK_WORDS_PER_LINE: usize = 8;
+const MAX_PEEK_COUNT: usize = 4096;
+const MAX_LIST_COUNT: usize = 4096;
+/// The number of instructions that can be stepped back over.
+const HISTORY_LIMIT: usize = 1_000_000;
+
+const HELP: &str = "\
+Commands:
+  step|s [N]              Execute N instructions (default 1)
+  continue|c              Run until a breakpoint, watchpoint, halt or missing input
+  back [N]                Undo the last N instructions (default 1)
+  rewind ADDR             Undo instructions until before the last write to ADDR
+  break|b ADDR|MNEMONIC   Break before executing ADDR, or any instruction with MNEMONIC
+  delete|d ADDR|MNEMONIC  Remove a breakpoint
+  watch|w ADDR            Stop when the value at ADDR changes
+  unwatch ADDR            Remove a watchpoint
+  peek|x ADDR [N]         Show N words of memory starting at ADDR (default 1)
+  poke ADDR VALUE...      Write values to memory starting at ADDR
+  list|l [ADDR] [N]       Disassemble N instructions from ADDR (default: next 5)
+  relbase|rb              Show the relative base
+  input|i VALUE...        Queue input values
+  ascii TEXT              Queue TEXT followed by a newline as ASCII input
+  info                    Show registers, breakpoints and watchpoints
+  help|h                  Show this help
+  quit|q                  Exit the debugger
+ADDR can be a number or relative to the relative base, such as rb+3.
+An empty line repeats the last command.";
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Event {
+    Stepped,
+    Breakpoint(usize),
+    OpcodeBreakpoint(Word),
+    Watchpoint {
+        addr: usize,
+        old: Word,
+        new: Word,
+    },
+    Halted,
+    NeedsInput,
+    /// A custom opcode at this address trapped.
+    Trapped(usize),
+    Error(IntcodeError),
+}
+
+/// Wraps an [`IntcodeComputer`] with breakpoints and watchpoints, and
+/// interprets the commands of the `intcode debug` REPL.
+pub struct Debugger {
+    pub computer: IntcodeComputer,
+    pub breakpoints: BTreeSet<usize>,
+    pub opcode_breakpoints: BTreeSet<Word>,
+    watchpoints: BTreeMap<usize, Word>,
+    last_command: Option<String>,
+}
+
+impl Debugger {
+    pub fn new(mut computer: IntcodeComputer) -> Self {
+        computer.history = Some(History::with_limit(HISTORY_LIMIT));
+        Debugger {
+            computer,
+            breakpoints: BTreeSet::new(),
+            opcode_breakpoints: BTreeSet::new(),
+            watchpoints: BTreeMap::new(),
+            last_command: None,
+        }
+    }
+
+    fn peek(&self, addr: usize) -> Word {
+        self.computer.prog.get(addr)
+    }
+
+    pub fn watch(&mut self, addr: usize) {
+        self.watchpoints.insert(addr, self.peek(addr));
+    }
+
+    pub fn unwatch(&mut self, addr: usize) -> bool {
+        self.watchpoints.remove(&addr).is_some()
+    }
+
+    pub fn poke(&mut self, addr: usize, value: Word) {
+        self.computer.poke(addr, value);
+        if let Some(watched) = self.watchpoints.get_mut(&addr) {
+            *watched = value;
+        }
+    }
+
+    /// Makes watchpoints compare against the current memory after stepping
+    /// back, so that stepping forward again stops on the same writes.
+    fn reset_watchpoints(&mut self) {
+        let prog = &self.computer.prog;
+        for (addr, value) in self.watchpoints.iter_mut() {
+            *value = prog.get(*addr);
+        }
+    }
+
+    fn changed_watchpoint(&mut self) -> Option<Event> {
+        let prog = &self.computer.prog;
+        self.watchpoints.iter_mut().find_map(|(addr, old)| {
+            let new = prog.get(*addr);
+            if new != *old {
+                let event = Event::Watchpoint {
+                    addr: *addr,
+                    old: *old,
+                    new,
+                };
+                *old = new;
+                Some(event)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Executes at most `max_steps` instructions, or until something stops
+    /// the program if `max_steps` is `None`. Breakpoints at the instruction
+    /// about to execute are ignored, so that execution can continue past them.
+    pub fn run(&mut self, max_steps: Option<usize>) -> Event {
+        let mut steps = 0;
+        loop {
+            if !self.computer.is_running() {
+                return Event::Halted;
+            }
+            let eip = self.computer.eip;
+            let opcode = self.peek(eip) % 100;
+            if steps > 0 {
+                if self.breakpoints.contains(&eip) {
+                    return Event::Breakpoint(eip);
+                }
+                if self.opcode_breakpoints.contains(&opcode) {
+                    return Event::OpcodeBreakpoint(opcode);
+                }
+            }
+            if Some(steps) == max_steps {
+                return Event::Stepped;
+            }
+            if self.computer.expects_input() && self.computer.input.is_empty() {
+                return Event::NeedsInput;
+            }
+
+            if let Err(err) = self.computer.step() {
+                return Event::Error(err);
+            }
+            steps += 1;
+            if std::mem::take(&mut self.computer.trapped) {
+                return Event::Trapped(eip);
+            }
+
+            if let Some(event) = self.changed_watchpoint() {
+                return event;
+            }
+        }
+    }
+
+    fn parse_addr(&self, s: &str) -> Result<usize, String> {
+        let addr = match s.strip_prefix("rb") {
+            Some("") => Some(self.computer.relbase()),
+            Some(offset) => offset
+                .trim_start_matches('+')
+                .parse::<Word>()
+                .ok()
+                .and_then(|offset| self.computer.relbase().checked_add(offset)),
+            None => s.parse().ok(),
+        };
+        match addr {
+            Some(addr) if addr >= 0 => Ok(addr as usize),
+            _ => Err(format!("Invalid address: {}", s)),
+        }
+    }
+
+    fn parse_breakpoint(&self, s: &str) -> Result<Breakpoint, String> {
+        if let Some(info) = OPCODES
+            .iter()
+            .find(|info| info.mnemonic.eq_ignore_ascii_case(s))
+        {
+            Ok(Breakpoint::Opcode(info.opcode))
+        } else {
+            self.parse_addr(s).map(Breakpoint::Address)
+        }
+    }
+
+    fn format_instruction(&self, addr: usize) -> (String, usize) {
+        match Instruction::decode(self.computer.prog.as_slice(), addr) {
+            Some(instruction) => (format!("{:04}: {}", addr, instruction), instruction.size()),
+            None => (format!("{:04}: DATA {}", addr, self.peek(addr)), 1),
+        }
+    }
+
+    fn print_registers<W: Write>(&self, out: &mut W) -> std::io::Result<()> {
+        writeln!(
+            out,
+            "eip = {}, relbase = {}",
+            self.computer.eip,
+            self.computer.relbase()
+        )
+    }
+
+    fn print_event<W: Write>(&mut self, event: Event, out: &mut W) -> std::io::Result<()> {
+        let output: Vec<Word> = self.computer.output.drain(..).collect();
+        if !output.is_empty() {
+            // Show lines of ASCII output, like the room descriptions in day 25, as text
+            let is_text = output.contains(&10)
+                && output.iter().any(|c| *c != 10)
+                && output.iter().all(|c| *c == 10 || (32..127).contains(c));
+            if is_text {
+                let text: String = output.iter().map(|c| *c as u8 as char).collect();
+                write!(out, "{}", text)?;
+                if !text.ends_with('\n') {
+                    writeln!(out)?;
+                }
+            } else {
+                writeln!(
+                    out,
+                    "Output: {}",
+                    output
+                        .iter()
+                        .map(|v| v.to_string())
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                )?;
+            }
+        }
+
+        match event {
+            Event::Stepped => {}
+            Event::Breakpoint(addr) => writeln!(out, "Breakpoint at {}", addr)?,
+            Event::OpcodeBreakpoint(opcode) => writeln!(
+                out,
+                "Breakpoint on {}",
+                super::opcode_info(opcode).unwrap().mnemonic
+            )?,
+            Event::Watchpoint { addr, old, new } => {
+                writeln!(out, "Watchpoint [{}]: {} -> {}", addr, old, new)?
+            }
+            Event::Halted => writeln!(out, "Program halted")?,
+            Event::NeedsInput => writeln!(out, "Program needs input")?,
+            Event::Trapped(addr) => writeln!(out, "Trap at {}", addr)?,
+            Event::Error(err) => writeln!(out, "Error: {}", err)?,
+        }
+
+        if event != Event::Halted {
+            writeln!(out, "=> {}", self.format_instruction(self.computer.eip).0)?;
+        }
+        Ok(())
+    }
+
+    /// Executes one line of debugger commands and writes the result to `out`.
+    /// Returns `false` if the debugger should exit.
+    pub fn execute<W: Write>(&mut self, line: &str, out: &mut W) -> std::io::Result<bool> {
+        let line = if line.trim().is_empty() {
+            match self.last_command.clone() {
+                Some(last) => last,
+                None => return Ok(true),
+            }
+        } else {
+            line.trim().to_string()
+        };
+        self.last_command = Some(line.clone());
+
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or("");
+        let args: Vec<&str> = words.collect();
+
+        if let Err(message) = self.execute_command(command, &line, &args, out)? {
+            writeln!(out, "{}", message)?;
+        }
+        Ok(!matches!(command, "quit" | "q"))
+    }
+
+    fn execute_command<W: Write>(
+        &mut self,
+        command: &str,
+        line: &str,
+        args: &[&str],
+        out: &mut W,
+    ) -> std::io::Result<Result<(), String>> {
+        let parse_count = |s: Option<&&str>, default: usize| match s {
+            Some(s) => s.parse().map_err(|_| format!("Invalid count: {}", s)),
+            None => Ok(default),
+        };
+
+        match (command, args) {
+            ("step", _) | ("s", _) => match parse_count(args.first(), 1) {
+                Ok(n) => {
+                    let event = self.run(Some(n));
+                    self.print_event(event, out)?;
+                }
+                Err(message) => return Ok(Err(message)),
+            },
+
+            ("continue", []) | ("c", []) => {
+                let event = self.run(None);
+                self.print_event(event, out)?;
+            }
+
+            ("back", _) if args.len() <= 1 => match parse_count(args.first(), 1) {
+                Ok(n) => {
+                    let undone = self.computer.step_back(n);
+                    self.reset_watchpoints();
+                    writeln!(out, "Stepped back {} instruction(s)", undone)?;
+                    self.print_event(Event::Stepped, out)?;
+                }
+                Err(message) => return Ok(Err(message)),
+            },
+
+            ("rewind", [addr]) => match self.parse_addr(addr) {
+                Ok(addr) => match self.computer.back_to_write(addr) {
+                    Some(undone) => {
+                        self.reset_watchpoints();
+                        writeln!(out, "Stepped back {} instruction(s)", undone)?;
+                        self.print_event(Event::Stepped, out)?;
+                    }
+                    None => return Ok(Err(format!("No write to [{}] in the history", addr))),
+                },
+                Err(message) => return Ok(Err(message)),
+            },
+
+            ("break", [target]) | ("b", [target]) => match self.parse_breakpoint(target) {
+                Ok(Breakpoint::Address(addr)) => {
+                    self.breakpoints.insert(addr);
+                    writeln!(out, "Breakpoint at {}", addr)?;
+                }
+                Ok(Breakpoint::Opcode(opcode)) => {
+                    self.opcode_breakpoints.insert(opcode);
+                    writeln!(
+                        out,
+                        "Breakpoint on {}",
+                        super::opcode_info(opcode).unwrap().mnemonic
+                    )?;
+                }
+                Err(message) => return Ok(Err(message)),
+            },
+
+            ("delete", [target]) | ("d", [target]) => {
+                let removed = match self.parse_breakpoint(target) {
+                    Ok(Breakpoint::Address(addr)) => self.breakpoints.remove(&addr),
+                    Ok(Breakpoint::Opcode(opcode)) => self.opcode_breakpoints.remove(&opcode),
+                    Err(message) => return Ok(Err(message)),
+                };
+                if !removed {
+                    return Ok(Err(format!("No breakpoint at {}", target)));
+                }
+            }
+
+            ("watch", [addr]) | ("w", [addr]) => match self.parse_addr(addr) {
+                Ok(addr) => {
+                    self.watch(addr);
+                    writeln!(out, "Watchpoint [{}] = {}", addr, self.peek(addr))?;
+                }
+                Err(message) => return Ok(Err(message)),
+            },
+
+            ("unwatch", [addr]) => match self.parse_addr(addr) {
+                Ok(addr) if self.unwatch(addr) => {}
+                Ok(addr) => return Ok(Err(format!("No watchpoint at {}", addr))),
+                Err(message) => return Ok(Err(message)),
+            },
+
+            ("peek", [addr, ..]) | ("x", [addr, ..]) if args.len() <= 2 => {
+                let addr = match self.parse_addr(addr) {
+                    Ok(addr) => addr,
+                    Err(message) => return Ok(Err(message)),
+                };
+                let count = match parse_count(args.get(1), 1) {
+                    Ok(count) if count > MAX_PEEK_COUNT => {
+                        return Ok(Err(format!("Can peek at most {} words", MAX_PEEK_COUNT)))
+                    }
+                    Ok(count) => count,
+                    Err(message) => return Ok(Err(message)),
+                };
+                let stop = match addr.checked_add(count) {
+                    Some(stop) => stop,
+                    None => return Ok(Err(format!("Invalid address: {}", args[0]))),
+                };
+                for start in (addr..stop).step_by(PEEK_WORDS_PER_LINE) {
+                    let end = (start + PEEK_WORDS_PER_LINE).min(stop);
+                    writeln!(
+                        out,
+                        "{:04}: {}",
+                        start,
+                        (start..end)
+                            .map(|a| self.peek(a).to_string())
+                            .collect::<Vec<String>>()
+                            .join(" ")
+                    )?;
+                }
+            }
+
+            ("poke", [addr, values @ ..]) if !values.is_empty() => {
+                let addr = match self.parse_addr(addr) {
+                    Ok(addr) => addr,
+                    Err(message) => return Ok(Err(message)),
+                };
+                if addr.checked_add(values.len() - 1).is_none() {
+                    return Ok(Err(format!("Invalid address: {}", args[0])));
+                }
+                let values = match values
+                    .iter()
+                    .map(|value| {
+                        value
+                            .parse()
+                            .map_err(|_| format!("Invalid value: {}", value))
+                    })
+                    .collect::<Result<Vec<Word>, String>>()
+                {
+                    Ok(values) => values,
+                    Err(message) => return Ok(Err(message)),
+                };
+                for (i, value) in values.into_iter().enumerate() {
+                    self.poke(addr + i, value);
+                }
+            }
+
+            ("list", _) | ("l", _) if args.len() <= 2 => {
+                let mut addr = match args.first() {
+                    Some(addr) => match self.parse_addr(addr) {
+                        Ok(addr) => addr,
+                        Err(message) => return Ok(Err(message)),
+                    },
+                    None => self.computer.eip,
+                };
+                let count = match parse_count(args.get(1), DEFAULT_LIST_COUNT) {
+                    Ok(count) if count > MAX_LIST_COUNT => {
+                        return Ok(Err(format!(
+                            "Can list at most {} instructions",
+                            MAX_LIST_COUNT
+                        )))
+                    }
+                    Ok(count) => count,
+                    Err(message) => return Ok(Err(message)),
+                };
+                for _ in 0..count {
+                    let (text, size) = self.format_instruction(addr);
+                    let marker = if addr == self.computer.eip {
+                        "=>"
+                    } else {
+                        "  "
+                    };
+                    writeln!(out, "{} {}", marker, text)?;
+                    addr = match addr.checked_add(size) {
+                        Some(next) => next,
+                        None => break,
+                    };
+                }
+            }
+
+            ("relbase", []) | ("rb", []) => writeln!(out, "{}", self.computer.relbase())?,
+
+            ("input", _) | ("i", _) if !args.is_empty() => {
+                let values: Result<Vec<Word>, _> = args
+                    .iter()
+                    .flat_map(|arg| arg.split(','))
+                    .filter(|s| !s.is_empty())
+                    .map(|s| s.parse().map_err(|_| format!("Invalid value: {}", s)))
+                    .collect();
+                match values {
+                    Ok(values) => self.computer.input.extend(values),
+                    Err(message) => return Ok(Err(message)),
+                }
+            }
+
+            ("ascii", _) => {
+                let text = line[command.len()..].trim_start();
+                self.computer
+                    .input
+                    .extend(text.bytes().chain(Some(b'\n')).map(Word::from));
+            }
+
+            ("info", []) => {
+                self.print_registers(out)?;
+                writeln!(
+                    out,
+                    "Breakpoints: {}",
+                    self.breakpoints
+                        .iter()
+                        .map(|addr| addr.to_string())
+                        .chain(
+                            self.opcode_breakpoints
+                                .iter()
+                                .map(|op| super::opcode_info(*op).unwrap().mnemonic.to_string())
+                        )
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                )?;
+                writeln!(
+                    out,
+                    "Watchpoints: {}",
+                    self.watchpoints
+                        .iter()
+                        .map(|(addr, value)| format!("[{}] = {}", addr, value))
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                )?;
+                writeln!(out, "Queued input: {} value(s)", self.computer.input.len())?;
+            }
+
+            ("help", []) | ("h", []) => writeln!(out, "{}", HELP)?,
+            ("quit", []) | ("q", []) => {}
+
+            _ => {
+                return Ok(Err(format!(
+                    "Invalid command: {} (type \"help\" for a list of commands)",
+                    line
+                )))
+            }
+        }
+        Ok(Ok(()))
+    }
+}
+
+enum Breakpoint {
+    Address(usize),
+    Opcode(Word),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::opcodes::Effect;
+
+    fn execute(debugger: &mut Debugger, line: &str) -> String {
+        let mut out = Vec::new();
+        debugger.execute(line, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn add_program() -> Debugger {
+        Debugger::new(IntcodeComputer::new(vec![
+            3, 11, 3, 12, 1, 11, 12, 11, 4, 11, 99, 0, 0,
+        ]))
+    }
+
+    #[test]
+    fn steps_and_stops_on_breakpoints() {
+        let mut debugger = add_program();
+        assert_eq!(
+            execute(&mut debugger, "step"),
+            "Program needs input\n=> 0000: IN -> [11]\n"
+        );
+        execute(&mut debugger, "input 4,7");
+        assert_eq!(execute(&mut debugger, "s"), "=> 0002: IN -> [12]\n");
+        assert_eq!(execute(&mut debugger, "break 8"), "Breakpoint at 8\n");
+        assert_eq!(
+            execute(&mut debugger, "continue"),
+            "Breakpoint at 8\n=> 0008: OUT [11]\n"
+        );
+        assert_eq!(execute(&mut debugger, "b out"), "Breakpoint on OUT\n");
+        assert_eq!(execute(&mut debugger, "c"), "Output: 11\nProgram halted\n");
+    }
+
+    #[test]
+    fn stops_on_opcode_breakpoints_and_watchpoints() {
+        let mut debugger = add_program();
+        debugger.computer.input.extend(vec![4, 7]);
+        execute(&mut debugger, "break ADD");
+        assert_eq!(
+            execute(&mut debugger, "c"),
+            "Breakpoint on ADD\n=> 0004: ADD [11], [12] -> [11]\n"
+        );
+        assert_eq!(execute(&mut debugger, "watch 11"), "Watchpoint [11] = 4\n");
+        assert_eq!(
+            execute(&mut debugger, "c"),
+            "Watchpoint [11]: 4 -> 11\n=> 0008: OUT [11]\n"
+        );
+    }
+
+    #[test]
+    fn stops_on_traps() {
+        let mut computer = IntcodeComputer::new(vec![50, 104, 1, 99]);
+        computer
+            .opcodes
+            .register(50, "BREAK", &[], |_| Ok(Effect::trap()))
+            .unwrap();
+        let mut debugger = Debugger::new(computer);
+        assert_eq!(execute(&mut debugger, "c"), "Trap at 0\n=> 0001: OUT #1\n");
+        assert_eq!(execute(&mut debugger, "c"), "Output: 1\nProgram halted\n");
+    }
+
+    #[test]
+    fn steps_back_and_rewinds_to_writes() {
+        let mut debugger = add_program();
+        execute(&mut debugger, "input 4,7");
+        assert_eq!(execute(&mut debugger, "c"), "Output: 11\nProgram halted\n");
+        assert_eq!(
+            execute(&mut debugger, "back"),
+            "Stepped back 1 instruction(s)\n=> 0008: OUT [11]\n"
+        );
+        assert_eq!(
+            execute(&mut debugger, "rewind 11"),
+            "Stepped back 1 instruction(s)\n=> 0004: ADD [11], [12] -> [11]\n"
+        );
+        assert_eq!(execute(&mut debugger, "x 11 2"), "0011: 4 7\n");
+        assert_eq!(
+            execute(&mut debugger, "rewind 12"),
+            "Stepped back 1 instruction(s)\n=> 0002: IN -> [12]\n"
+        );
+        assert_eq!(
+            execute(&mut debugger, "back 5"),
+            "Stepped back 1 instruction(s)\n=> 0000: IN -> [11]\n"
+        );
+        assert_eq!(
+            execute(&mut debugger, "rewind 11"),
+            "No write to [11] in the history\n"
+        );
+        assert_eq!(execute(&mut debugger, "c"), "Output: 11\nProgram halted\n");
+    }
+
+    #[test]
+    fn lists_instructions() {
+        let mut debugger = add_program();
+        assert_eq!(
+            execute(&mut debugger, "list 0 2"),
+            "=> 0000: IN -> [11]\n   0002: IN -> [12]\n"
+        );
+        assert_eq!(
+            execute(&mut debugger, "list 0 1000000000000"),
+            "Can list at most 4096 instructions\n"
+        );
+    }
+
+    #[test]
+    fn peeks_and_pokes_memory() {
+        let mut debugger = add_program();
+        execute(&mut debugger, "poke 11 5 6");
+        assert_eq!(execute(&mut debugger, "peek 10 3"), "0010: 99 5 6\n");
+        execute(&mut debugger, "poke 0 109 -4");
+        assert_eq!(execute(&mut debugger, "s"), "=> 0002: IN -> [12]\n");
+        assert_eq!(execute(&mut debugger, "rb"), "-4\n");
+        assert_eq!(execute(&mut debugger, "x rb+15"), "0011: 5\n");
+        assert_eq!(execute(&mut debugger, ""), "0011: 5\n");
+        assert_eq!(execute(&mut debugger, "x rb"), "Invalid address: rb\n");
+        assert_eq!(
+            execute(&mut debugger, "peek 0 18446744073709551615"),
+            "Can peek at most 4096 words\n"
+        );
+        assert_eq!(
+            execute(&mut debugger, "peek 18446744073709551615 2"),
+            "Invalid address: 18446744073709551615\n"
+        );
+        assert_eq!(
+            execute(&mut debugger, "poke 18446744073709551615 1 2"),
+            "Invalid address: 18446744073709551615\n"
+        );
+        assert_eq!(execute(&mut debugger, "poke 11 7 x"), "Invalid value: x\n");
+        assert_eq!(execute(&mut debugger, "x 11"), "0011: 5\n");
+    }
+
+    #[test]
+    fn steps_back_over_pokes() {
+        let mut debugger = add_program();
+        execute(&mut debugger, "input 4,7");
+        execute(&mut debugger, "s 2");
+        execute(&mut debugger, "poke 11 100");
+        assert_eq!(
+            execute(&mut debugger, "back"),
+            "Stepped back 1 instruction(s)\n=> 0002: IN -> [12]\n"
+        );
+        assert_eq!(execute(&mut debugger, "x 11 2"), "0011: 4 0\n");
+        assert_eq!(execute(&mut debugger, "c"), "Output: 11\nProgram halted\n");
+    }
+}
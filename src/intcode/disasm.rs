@@ -0,0 +1,342 @@
+use super::opcode_info;
+use super::OpcodeInfo;
+use super::ParamKind;
+use super::Word;
+use super::OP_ADD;
+use super::OP_HALT;
+use super::OP_JUMP_NONZERO;
+use super::OP_JUMP_ZERO;
+use super::OP_MULTIPLY;
+use std::collections::BTreeMap;
+use std::fmt;
+
+const DATA_WORDS_PER_LINE: usize = 8;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Operand {
+    Position(Word),
+    Immediate(Word),
+    Relative(Word),
+}
+
+impl Operand {
+    fn mode(&self) -> Word {
+        match self {
+            Self::Position(_) => 0,
+            Self::Immediate(_) => 1,
+            Self::Relative(_) => 2,
+        }
+    }
+
+    fn value(&self) -> Word {
+        match self {
+            Self::Position(v) | Self::Immediate(v) | Self::Relative(v) => *v,
+        }
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Position(addr) => write!(f, "[{}]", addr),
+            Self::Immediate(value) => write!(f, "#{}", value),
+            Self::Relative(offset) => write!(f, "[rb{:+}]", offset),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Instruction {
+    pub opcode: Word,
+    pub operands: Vec<Operand>,
+}
+
+impl Instruction {
+    /// Returns `None` unless `prog[addr..]` starts with a complete instruction
+    /// that [`encode`](Self::encode) would reproduce exactly.
+    pub fn decode(prog: &[Word], addr: usize) -> Option<Instruction> {
+        let word = *prog.get(addr)?;
+        if word < 0 {
+            return None;
+        }
+        let info = opcode_info(word % 100)?;
+
+        let mut modes = word / 100;
+        let mut operands = Vec::with_capacity(info.params.len());
+        for (i, kind) in info.params.iter().enumerate() {
+            let value = *prog.get(addr + 1 + i)?;
+            operands.push(match (modes % 10, kind) {
+                (0, _) => Operand::Position(value),
+                (1, ParamKind::Read) => Operand::Immediate(value),
+                (2, _) => Operand::Relative(value),
+                _ => return None,
+            });
+            modes /= 10;
+        }
+
+        if modes == 0 {
+            Some(Instruction {
+                opcode: info.opcode,
+                operands,
+            })
+        } else {
+            None
+        }
+    }
+
+    pub fn encode(&self) -> Vec<Word> {
+        let modes = self
+            .operands
+            .iter()
+            .rev()
+            .fold(0, |modes, operand| modes * 10 + operand.mode());
+        std::iter::once(self.opcode + modes * 100)
+            .chain(self.operands.iter().map(Operand::value))
+            .collect()
+    }
+
+    pub fn info(&self) -> &'static OpcodeInfo {
+        opcode_info(self.opcode).unwrap()
+    }
+
+    pub fn size(&self) -> usize {
+        1 + self.operands.len()
+    }
+
+    /// Addresses that control can statically be seen to flow to after this
+    /// instruction at `addr`. Jumps to computed addresses are not included.
+    pub fn successors(&self, addr: usize) -> Vec<usize> {
+        let next = addr + self.size();
+        match (self.opcode, self.operands.as_slice()) {
+            (OP_HALT, _) => vec![],
+            (OP_JUMP_NONZERO, [cond, target]) | (OP_JUMP_ZERO, [cond, target]) => {
+                let taken = match cond {
+                    Operand::Immediate(v) => Some((*v != 0) == (self.opcode == OP_JUMP_NONZERO)),
+                    _ => None,
+                };
+                let mut successors = Vec::new();
+                if taken != Some(false) {
+                    if let Operand::Immediate(target) = target {
+                        if *target >= 0 {
+                            successors.push(*target as usize);
+                        }
+                    }
+                }
+                if taken != Some(true) {
+                    successors.push(next);
+                }
+                successors
+            }
+            _ => vec![next],
+        }
+    }
+
+    /// The constant stored by an `ADD` or `MUL` of two immediates into a
+    /// relative address. Programs use this to push return addresses onto the
+    /// stack before calling a subroutine.
+    pub fn pushed_constant(&self) -> Option<Word> {
+        match (self.opcode, self.operands.as_slice()) {
+            (OP_ADD, [Operand::Immediate(a), Operand::Immediate(b), Operand::Relative(_)]) => {
+                a.checked_add(*b)
+            }
+            (OP_MULTIPLY, [Operand::Immediate(a), Operand::Immediate(b), Operand::Relative(_)]) => {
+                a.checked_mul(*b)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let info = self.info();
+        write!(f, "{}", info.mnemonic)?;
+
+        let reads: Vec<String> = info
+            .params
+            .iter()
+            .zip(self.operands.iter())
+            .filter(|(kind, _)| **kind == ParamKind::Read)
+            .map(|(_, operand)| operand.to_string())
+            .collect();
+        if !reads.is_empty() {
+            write!(f, " {}", reads.join(", "))?;
+        }
+
+        for (_, operand) in info
+            .params
+            .iter()
+            .zip(self.operands.iter())
+            .filter(|(kind, _)| **kind == ParamKind::Write)
+        {
+            write!(f, " -> {}", operand)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Line {
+    Code {
+        addr: usize,
+        instruction: Instruction,
+    },
+    Data {
+        addr: usize,
+        values: Vec<Word>,
+    },
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Code { addr, instruction } => write!(f, "{:04}: {}", addr, instruction),
+            Self::Data { addr, values } => write!(
+                f,
+                "{:04}: DATA {}",
+                addr,
+                values
+                    .iter()
+                    .map(|v| v.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+/// Decodes the instructions reachable from address 0 by following
+/// fall-through, constant jump targets and pushed return addresses.
+pub fn reachable_instructions(prog: &[Word]) -> BTreeMap<usize, Instruction> {
+    let mut code = BTreeMap::new();
+    let mut claimed = vec![false; prog.len()];
+    let mut queue = vec![0];
+
+    while let Some(addr) = queue.pop() {
+        if addr >= prog.len() || claimed[addr] {
+            continue;
+        }
+        if let Some(instruction) = Instruction::decode(prog, addr) {
+            let end = addr + instruction.size();
+            if claimed[addr..end].iter().any(|c| *c) {
+                continue;
+            }
+            claimed[addr..end].iter_mut().for_each(|c| *c = true);
+
+            queue.extend(instruction.successors(addr));
+            if let Some(target) = instruction.pushed_constant() {
+                if target >= 0 {
+                    queue.push(target as usize);
+                }
+            }
+            code.insert(addr, instruction);
+        }
+    }
+
+    code
+}
+
+pub fn disassemble(prog: &[Word]) -> Vec<Line> {
+    let mut code = reachable_instructions(prog);
+    let mut lines = Vec::new();
+    let mut addr = 0;
+
+    while addr < prog.len() {
+        if let Some(instruction) = code.remove(&addr) {
+            let size = instruction.size();
+            lines.push(Line::Code { addr, instruction });
+            addr += size;
+        } else {
+            let mut end = addr + 1;
+            while end < prog.len() && end - addr < DATA_WORDS_PER_LINE && !code.contains_key(&end) {
+                end += 1;
+            }
+            lines.push(Line::Data {
+                addr,
+                values: prog[addr..end].to_vec(),
+            });
+            addr = end;
+        }
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn disassemble_to_strings(prog: &[Word]) -> Vec<String> {
+        disassemble(prog).iter().map(Line::to_string).collect()
+    }
+
+    #[test]
+    fn formats_operands() {
+        let prog = vec![1201, 3, 5, 104, 99];
+        assert_eq!(
+            disassemble_to_strings(&prog),
+            vec!["0000: ADD [rb+3], #5 -> [104]", "0004: HALT"]
+        );
+    }
+
+    #[test]
+    fn follows_control_flow() {
+        let prog = vec![3, 11, 1005, 11, 8, 104, -1, 99, 4, 11, 99, 0];
+        assert_eq!(
+            disassemble_to_strings(&prog),
+            vec![
+                "0000: IN -> [11]",
+                "0002: JNZ [11], #8",
+                "0005: OUT #-1",
+                "0007: HALT",
+                "0008: OUT [11]",
+                "0010: HALT",
+                "0011: DATA 0",
+            ]
+        );
+    }
+
+    #[test]
+    fn does_not_decode_unreachable_data() {
+        let prog = vec![1105, 1, 7, 1, 2, 3, 4, 99, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1];
+        assert_eq!(
+            disassemble_to_strings(&prog),
+            vec![
+                "0000: JNZ #1, #7",
+                "0003: DATA 1, 2, 3, 4",
+                "0007: HALT",
+                "0008: DATA 1, 1, 1, 1, 1, 1, 1, 1",
+                "0016: DATA 1, 1",
+            ]
+        );
+    }
+
+    #[test]
+    fn follows_pushed_return_addresses() {
+        let prog = vec![109, 10, 21101, 9, 0, 0, 1105, 1, 10, 99, 2105, 1, 0];
+        assert_eq!(
+            disassemble_to_strings(&prog),
+            vec![
+                "0000: ARB #10",
+                "0002: ADD #9, #0 -> [rb+0]",
+                "0006: JNZ #1, #10",
+                "0009: HALT",
+                "0010: JNZ #1, [rb+0]",
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_non_canonical_instructions() {
+        assert_eq!(Instruction::decode(&[10004, 0], 0), None);
+        assert_eq!(Instruction::decode(&[103, 0], 0), None);
+        assert_eq!(Instruction::decode(&[1, 0, 0], 0), None);
+        assert_eq!(Instruction::decode(&[-1], 0), None);
+    }
+
+    #[test]
+    fn encode_reverses_decode() {
+        let prog = vec![21202, -3, 7, 1];
+        assert_eq!(Instruction::decode(&prog, 0).unwrap().encode(), prog);
+    }
+}
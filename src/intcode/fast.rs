@@ -0,0 +1,535 @@
+use super::io::IntcodeInput;
+use super::io::IntcodeOutput;
+use super::memory::Memory;
+use super::opcode_info;
+use super::IntcodeComputer;
+use super::IntcodeError;
+use super::ParamKind;
+use super::StopReason;
+use super::Word;
+use super::OP_ADD;
+use super::OP_EQ;
+use super::OP_HALT;
+use super::OP_INPUT;
+use super::OP_JUMP_NONZERO;
+use super::OP_JUMP_ZERO;
+use super::OP_LESS;
+use super::OP_MULTIPLY;
+use super::OP_OUTPUT;
+use super::OP_RELBASE;
+
+/// Short runs are not worth the cost of filling the cache, so a run with an
+/// empty cache starts out executing instructions with `step`.
+#[cfg(not(test))]
+const WARMUP_STEPS: usize = 500;
+/// Unit tests compare the engines on short programs, so use the cache at once.
+#[cfg(test)]
+const WARMUP_STEPS: usize = 0;
+
+/// The longest instruction is 4 words, so a write can affect a cached
+/// instruction starting at most this many words before it.
+const MAX_PARAMS: usize = 3;
+
+#[derive(Clone, Copy, Debug)]
+enum Operand {
+    Immediate(Word),
+    /// A position mode address that was within dense memory when the
+    /// instruction was decoded, so it needs no further checks.
+    Address(usize),
+    /// A position mode address that may be negative or outside dense memory.
+    Position(Word),
+    Relative(Word),
+}
+
+impl Operand {
+    fn param(&self) -> Word {
+        match self {
+            Self::Immediate(param) | Self::Position(param) | Self::Relative(param) => *param,
+            Self::Address(addr) => *addr as Word,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Decoded {
+    /// Entries can be used without checks only in the generation they were
+    /// last validated in. Generation 0 means invalidated.
+    generation: u32,
+    size: usize,
+    instruction: Word,
+    opcode: Word,
+    operands: [Operand; MAX_PARAMS],
+}
+
+impl Default for Decoded {
+    fn default() -> Self {
+        Decoded {
+            generation: 0,
+            size: 1,
+            instruction: 0,
+            opcode: 0,
+            operands: [Operand::Immediate(0); MAX_PARAMS],
+        }
+    }
+}
+
+impl Decoded {
+    /// Whether this is still the instruction at `addr` in `prog`.
+    fn matches(&self, prog: &[Word], addr: usize) -> bool {
+        prog.get(addr) == Some(&self.instruction)
+            && self.operands[..self.size - 1]
+                .iter()
+                .enumerate()
+                .all(|(i, operand)| {
+                    prog.get(addr + 1 + i) == Some(&operand.param())
+                        && match operand {
+                            Operand::Address(addr) => *addr < prog.len(),
+                            _ => true,
+                        }
+                })
+    }
+}
+
+/// Decoded instructions indexed by address. Entries are invalidated whenever
+/// the running program writes over them. Since `prog` may also have been
+/// modified between runs, entries from earlier runs are checked against memory
+/// before they are used again.
+#[derive(Debug)]
+pub(super) struct DecodeCache {
+    /// The number of steps to execute with `step` before filling an empty
+    /// cache.
+    pub(super) warmup_steps: usize,
+    generation: u32,
+    entries: Vec<Decoded>,
+    /// The last generation in which each address was part of a cached
+    /// instruction, so that most writes can skip invalidation.
+    covered: Vec<u32>,
+}
+
+impl Default for DecodeCache {
+    fn default() -> Self {
+        DecodeCache {
+            warmup_steps: WARMUP_STEPS,
+            generation: 0,
+            entries: Vec::new(),
+            covered: Vec::new(),
+        }
+    }
+}
+
+/// Copying the cache costs more than filling it again, so clones start empty.
+impl Clone for DecodeCache {
+    fn clone(&self) -> Self {
+        DecodeCache {
+            warmup_steps: self.warmup_steps,
+            ..Self::default()
+        }
+    }
+}
+
+impl DecodeCache {
+    /// Requires all entries to be checked against memory before next use.
+    fn expire(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+        if self.generation == 0 {
+            self.entries.clear();
+            self.covered.clear();
+            self.generation = 1;
+        }
+    }
+
+    fn reserve(&mut self, size: usize) {
+        if self.entries.len() < size {
+            self.entries.resize(size, Decoded::default());
+            self.covered.resize(size, 0);
+        }
+    }
+
+    fn insert(&mut self, addr: usize, decoded: Decoded) {
+        self.reserve(addr + decoded.size);
+        self.entries[addr] = Decoded {
+            generation: self.generation,
+            ..decoded
+        };
+        for covered in &mut self.covered[addr..addr + decoded.size] {
+            *covered = self.generation;
+        }
+    }
+
+    #[inline(always)]
+    fn lookup(&mut self, prog: &[Word], addr: usize) -> Option<Decoded> {
+        if let Some(decoded) = self.entries.get(addr) {
+            if decoded.generation == self.generation {
+                return Some(*decoded);
+            }
+            if decoded.generation != 0 && decoded.matches(prog, addr) {
+                let decoded = *decoded;
+                self.insert(addr, decoded);
+                return Some(decoded);
+            }
+        }
+        let decoded = decode(prog, addr)?;
+        self.insert(addr, decoded);
+        Some(decoded)
+    }
+
+    fn invalidate(&mut self, addr: usize) {
+        if self.covered.get(addr) == Some(&self.generation) {
+            for decoded in &mut self.entries[addr.saturating_sub(MAX_PARAMS)..=addr] {
+                decoded.generation = 0;
+            }
+        }
+    }
+}
+
+/// Returns `None` for instructions that can't be executed without an error, or
+/// that extend past the end of dense memory. These are left to the reference
+/// implementation so that errors are exactly the same.
+fn decode(prog: &[Word], eip: usize) -> Option<Decoded> {
+    let instruction = *prog.get(eip)?;
+    let opcode = instruction % 100;
+    if opcode == OP_HALT && instruction != OP_HALT {
+        return None;
+    }
+    let info = opcode_info(opcode)?;
+
+    let mut decoded = Decoded {
+        size: 1 + info.params.len(),
+        instruction,
+        opcode,
+        ..Decoded::default()
+    };
+    let mut modes = instruction / 100;
+    for (i, kind) in info.params.iter().enumerate() {
+        let param = *prog.get(eip + 1 + i)?;
+        decoded.operands[i] = match (modes % 10, kind) {
+            (0, _) if param >= 0 && (param as usize) < prog.len() => {
+                Operand::Address(param as usize)
+            }
+            (0, _) => Operand::Position(param),
+            (1, ParamKind::Read) => Operand::Immediate(param),
+            (2, _) => Operand::Relative(param),
+            _ => return None,
+        };
+        modes /= 10;
+    }
+    Some(decoded)
+}
+
+/// Resolves a non-immediate operand to an address.
+#[inline(always)]
+fn resolve_addr(
+    prog: &Memory,
+    relbase: Word,
+    eip: usize,
+    operand: Operand,
+) -> Result<usize, IntcodeError> {
+    let addr = match operand {
+        Operand::Address(addr) => return Ok(addr),
+        Operand::Position(addr) => addr,
+        Operand::Relative(offset) => {
+            relbase
+                .checked_add(offset)
+                .ok_or_else(|| IntcodeError::Overflow {
+                    eip,
+                    instruction: prog.get(eip),
+                })?
+        }
+        Operand::Immediate(_) => unreachable!(),
+    };
+    if addr < 0 {
+        return Err(IntcodeError::NegativeAddress { eip, address: addr });
+    }
+    Ok(addr as usize)
+}
+
+#[inline(always)]
+fn read_arg(
+    prog: &Memory,
+    relbase: Word,
+    eip: usize,
+    operand: Operand,
+) -> Result<Word, IntcodeError> {
+    match operand {
+        Operand::Immediate(value) => Ok(value),
+        Operand::Address(addr) => Ok(prog.as_slice()[addr]),
+        _ => Ok(prog.get(resolve_addr(prog, relbase, eip, operand)?)),
+    }
+}
+
+#[inline(always)]
+fn write_arg(
+    prog: &mut Memory,
+    eip: usize,
+    address: usize,
+    value: Word,
+) -> Result<(), IntcodeError> {
+    match prog.as_mut_slice().get_mut(address) {
+        Some(slot) => *slot = value,
+        None => prog
+            .set(address, value)
+            .map_err(|_| IntcodeError::MemoryLimitExceeded { eip, address })?,
+    }
+    Ok(())
+}
+
+impl<I, O> IntcodeComputer<I, O>
+where
+    I: IntcodeInput,
+    O: IntcodeOutput,
+{
+    /// Equivalent to [`execute`](Self::execute), but for a pre-decoded
+    /// instruction.
+    #[inline(always)]
+    fn execute_decoded(
+        &mut self,
+        decoded: &Decoded,
+        cache: &mut DecodeCache,
+    ) -> Result<(), IntcodeError> {
+        let eip = self.eip;
+        let relbase = self.relbase;
+        let prog = &mut self.prog;
+        match decoded.opcode {
+            OP_ADD | OP_MULTIPLY | OP_LESS | OP_EQ => {
+                let out = resolve_addr(prog, relbase, eip, decoded.operands[2])?;
+                let a = read_arg(prog, relbase, eip, decoded.operands[0])?;
+                let b = read_arg(prog, relbase, eip, decoded.operands[1])?;
+                let value = match decoded.opcode {
+                    OP_ADD => a.wrapping_add(b),
+                    OP_MULTIPLY => a.wrapping_mul(b),
+                    OP_LESS => (a < b) as Word,
+                    _ => (a == b) as Word,
+                };
+                write_arg(prog, eip, out, value)?;
+                cache.invalidate(out);
+                self.eip += 4;
+            }
+
+            OP_INPUT => {
+                let out = resolve_addr(prog, relbase, eip, decoded.operands[0])?;
+                if let Some(i) = self.input.read() {
+                    write_arg(prog, eip, out, i)?;
+                    cache.invalidate(out);
+                    self.eip += 2;
+                }
+            }
+
+            OP_OUTPUT => {
+                self.output
+                    .write(read_arg(prog, relbase, eip, decoded.operands[0])?);
+                self.eip += 2;
+            }
+
+            OP_JUMP_NONZERO | OP_JUMP_ZERO => {
+                let cond = read_arg(prog, relbase, eip, decoded.operands[0])?;
+                if (cond != 0) == (decoded.opcode == OP_JUMP_NONZERO) {
+                    let target = read_arg(prog, relbase, eip, decoded.operands[1])?;
+                    if target < 0 {
+                        return Err(IntcodeError::NegativeAddress {
+                            eip,
+                            address: target,
+                        });
+                    }
+                    self.eip = target as usize;
+                } else {
+                    self.eip += 3;
+                }
+            }
+
+            OP_RELBASE => {
+                self.relbase = relbase
+                    .checked_add(read_arg(prog, relbase, eip, decoded.operands[0])?)
+                    .ok_or_else(|| IntcodeError::Overflow {
+                        eip,
+                        instruction: prog.get(eip),
+                    })?;
+                self.eip += 2;
+            }
+
+            _ => unreachable!(),
+        }
+        Ok(())
+    }
+
+    /// Equivalent to the loop in [`resume`](Self::resume), but executes
+    /// instructions from the decode cache.
+    pub(super) fn resume_cached(&mut self) -> StopReason {
+        let mut cache = std::mem::take(&mut self.cache);
+        cache.expire();
+        let warmup_steps = cache.warmup_steps;
+        let mut warm = warmup_steps == 0 || !cache.entries.is_empty();
+
+        let limits = self.run_limits();
+        let mut steps = 0;
+        let mut outputs = 0;
+        let stop_reason = loop {
+            let eip = self.eip;
+            let decoded = if warm {
+                cache.lookup(self.prog.as_slice(), eip)
+            } else {
+                None
+            };
+
+            let halted = match &decoded {
+                Some(decoded) => decoded.opcode == OP_HALT,
+                None => !self.is_running(),
+            };
+            if halted {
+                break StopReason::Halted;
+            }
+            if let Some(stop_reason) = limits.check(steps) {
+                break stop_reason;
+            }
+
+            let (opcode, result) = match &decoded {
+                Some(decoded) => (decoded.opcode, self.execute_decoded(decoded, &mut cache)),
+                None => {
+                    let opcode = self.current_instruction() % 100;
+                    let result = self.execute();
+                    // We don't know what the reference implementation wrote
+                    cache.expire();
+                    (opcode, result)
+                }
+            };
+            if let Err(err) = result {
+                break StopReason::Error(err);
+            }
+
+            match opcode {
+                OP_INPUT if self.eip == eip => break StopReason::NeedsInput,
+                OP_OUTPUT => {
+                    steps += 1;
+                    outputs += 1;
+                    if Some(outputs) == self.pause_on_output {
+                        break StopReason::OutputReady(outputs);
+                    }
+                }
+                _ => {
+                    steps += 1;
+                    if std::mem::take(&mut self.trapped) {
+                        break StopReason::Trapped { eip };
+                    }
+                }
+            }
+            if steps == warmup_steps {
+                warm = true;
+            }
+        };
+
+        self.cache = cache;
+        self.spend_budget(steps);
+        stop_reason
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::day_input_filename;
+    use crate::common::get_file_lines;
+    use crate::intcode::parse_program;
+    use crate::intcode::Engine;
+    use crate::intcode::IntcodeComputer;
+    use crate::intcode::StopReason;
+    use crate::intcode::Word;
+
+    fn assert_engines_agree(program: &[Word], input: &[Word]) {
+        let run = |engine, pause_on_output, step_limit| {
+            let mut computer = IntcodeComputer::new(program.to_vec());
+            computer.engine = engine;
+            computer.pause_on_output = pause_on_output;
+            computer.step_limit = step_limit;
+            let mut stop_reasons = vec![computer.run_mut(input.iter().copied())];
+            for _ in 0..3 {
+                stop_reasons.push(computer.resume());
+            }
+            (
+                stop_reasons,
+                computer.eip,
+                computer.relbase,
+                computer.prog,
+                computer.output,
+            )
+        };
+
+        for (pause_on_output, step_limit) in &[(None, None), (Some(1), None), (None, Some(1000))] {
+            assert_eq!(
+                run(Engine::Cached, *pause_on_output, *step_limit),
+                run(Engine::Step, *pause_on_output, *step_limit)
+            );
+        }
+    }
+
+    #[test]
+    fn executes_self_modifying_code() {
+        let program = vec![104, 0, 1001, 1, 1, 1, 1007, 1, 3, 14, 1005, 14, 0, 99, 0];
+        assert_eq!(
+            IntcodeComputer::new(program.clone()).run(None).output,
+            vec![0, 1, 2]
+        );
+        assert_engines_agree(&program, &[]);
+    }
+
+    #[test]
+    fn revalidates_cache_after_external_writes() {
+        let mut computer = IntcodeComputer::new(vec![3, 20, 104, 5, 1105, 1, 0]);
+        assert_eq!(computer.run_mut(Some(1)), StopReason::NeedsInput);
+        computer.prog[3] = 6;
+        assert_eq!(computer.run_mut(Some(1)), StopReason::NeedsInput);
+        computer.prog[2] = 4;
+        computer.prog[3] = 20;
+        assert_eq!(computer.run_mut(Some(7)), StopReason::NeedsInput);
+        assert_eq!(computer.output, vec![5, 6, 7]);
+    }
+
+    #[test]
+    fn agrees_with_step_on_errors() {
+        assert_engines_agree(&[1101, 1, 2, 5, 42, 0], &[]);
+        assert_engines_agree(&[304, 0, 99], &[]);
+        assert_engines_agree(&[109, -5, 204, 0, 99], &[]);
+        assert_engines_agree(&[1105, 1, -3], &[]);
+        assert_engines_agree(&[11101, 1, 1, 0, 99], &[]);
+        assert_engines_agree(&[109, Word::MAX, 109, 1, 99], &[]);
+        assert_engines_agree(&[1, 1, 1, 20, 1101, 1], &[]);
+        assert_engines_agree(&[3], &[7]);
+    }
+
+    #[test]
+    fn agrees_with_step_on_step_budget() {
+        let program = parse_program(&get_file_lines(&day_input_filename(9)).unwrap()).unwrap();
+        let run = |engine| {
+            let mut computer = IntcodeComputer::new(program.clone());
+            computer.engine = engine;
+            computer.step_budget = Some(5000);
+            computer.step_limit = Some(3000);
+            let stop_reasons = vec![
+                computer.run_mut(Some(2)),
+                computer.resume(),
+                computer.resume(),
+            ];
+            (
+                stop_reasons,
+                computer.eip,
+                computer.step_budget,
+                computer.prog,
+            )
+        };
+        assert_eq!(run(Engine::Cached), run(Engine::Step));
+    }
+
+    #[test]
+    fn agrees_with_step_on_day_programs() {
+        for (day, input) in &[
+            (5, vec![1]),
+            (5, vec![5]),
+            (9, vec![1]),
+            (9, vec![2]),
+            (19, vec![20, 30]),
+            (21, "NOT A J\nWALK\n".bytes().map(Word::from).collect()),
+            (25, "north\n".bytes().map(Word::from).collect()),
+        ] {
+            let program =
+                parse_program(&get_file_lines(&day_input_filename(*day)).unwrap()).unwrap();
+            assert_engines_agree(&program, input);
+        }
+    }
+}
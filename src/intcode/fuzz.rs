@@ -0,0 +1,436 @@
+use super::memory::Memory;
+use super::opcode_info;
+use super::Engine;
+use super::IntcodeComputer;
+use super::OpcodeInfo;
+use super::ParamKind;
+use super::StopReason;
+use super::Word;
+use super::OPCODES;
+use super::OP_HALT;
+use super::OP_JUMP_NONZERO;
+use super::OP_JUMP_ZERO;
+use std::fmt;
+use std::num::ParseIntError;
+use std::str::FromStr;
+
+const MAX_INSTRUCTIONS: usize = 24;
+const DATA_WORDS: usize = 8;
+const MAX_INPUTS: usize = 4;
+
+/// Every run gets this many steps, so programs that loop forever still stop.
+const STEP_BUDGET: usize = 2_000;
+
+/// Programs can compute addresses that double on every step, so they need a
+/// memory limit too.
+const MEMORY_LIMIT: usize = 1 << 16;
+
+/// Engines compared against [`Engine::Step`].
+const ALTERNATIVE_ENGINES: [Engine; 1] = [Engine::Cached];
+
+/// The settings every program is run with, since each of them makes the
+/// engines stop and resume in different places.
+const SETTINGS: [RunSettings; 4] = [
+    RunSettings {
+        pause_on_output: None,
+        step_limit: None,
+    },
+    RunSettings {
+        pause_on_output: Some(1),
+        step_limit: None,
+    },
+    RunSettings {
+        pause_on_output: None,
+        step_limit: Some(7),
+    },
+    RunSettings {
+        pause_on_output: Some(2),
+        step_limit: Some(13),
+    },
+];
+
+/// A small xorshift generator, so that a seed always gives the same programs.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // One round of splitmix64, so that similar seeds give unrelated
+        // sequences and 0 is a valid seed.
+        let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        Rng((z ^ (z >> 31)) | 1)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// A number in `0..n`.
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// A number in `low..high`.
+    pub fn range(&mut self, low: Word, high: Word) -> Word {
+        low + self.below((high - low) as usize) as Word
+    }
+
+    /// `true` one time in `n`.
+    pub fn one_in(&mut self, n: usize) -> bool {
+        self.below(n) == 0
+    }
+}
+
+/// A program and the input to run it with.
+///
+/// The text form is the program on the first line and the comma separated
+/// input on the second, like the standard input of `intcode`. Lines starting
+/// with `#` are comments.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FuzzCase {
+    pub program: Vec<Word>,
+    pub input: Vec<Word>,
+}
+
+impl FuzzCase {
+    /// A random program made of valid instructions, followed by a few words
+    /// of data. Operands mostly point into the program, so programs read and
+    /// overwrite their own code as well as the data, and jumps mostly go to
+    /// the start of an instruction.
+    pub fn generate(rng: &mut Rng) -> Self {
+        let count = 1 + rng.below(MAX_INSTRUCTIONS);
+        let infos: Vec<&OpcodeInfo> = (0..count)
+            .map(|i| {
+                if i + 1 == count {
+                    opcode_info(OP_HALT).unwrap()
+                } else {
+                    &OPCODES[rng.below(OPCODES.len())]
+                }
+            })
+            .collect();
+        let addresses: Vec<usize> = infos
+            .iter()
+            .scan(0, |addr, info| {
+                let start = *addr;
+                *addr += 1 + info.params.len();
+                Some(start)
+            })
+            .collect();
+        let code_len = addresses.last().unwrap() + 1;
+        let len = code_len + DATA_WORDS;
+
+        let address = |rng: &mut Rng| -> Word {
+            if rng.one_in(16) {
+                rng.range(3000, 5000)
+            } else if rng.one_in(4) {
+                rng.below(len) as Word
+            } else {
+                (code_len + rng.below(DATA_WORDS)) as Word
+            }
+        };
+
+        let mut program = Vec::with_capacity(len);
+        for info in infos {
+            let mut word = info.opcode;
+            let mut operands = Vec::with_capacity(info.params.len());
+            for (i, kind) in info.params.iter().enumerate() {
+                let is_jump_target =
+                    i == 1 && (info.opcode == OP_JUMP_NONZERO || info.opcode == OP_JUMP_ZERO);
+                let (mode, value) = match (kind, rng.below(4)) {
+                    (ParamKind::Read, _) if is_jump_target && !rng.one_in(4) => {
+                        (1, addresses[rng.below(addresses.len())] as Word)
+                    }
+                    (ParamKind::Read, 0) => (1, rng.range(-3, 10)),
+                    (_, 1) => (2, rng.range(-8, len as Word)),
+                    _ => (0, address(rng)),
+                };
+                word += mode * [100, 1000, 10000][i];
+                operands.push(value);
+            }
+            program.push(word);
+            program.extend(operands);
+        }
+        program.extend((0..DATA_WORDS).map(|_| rng.range(-3, 10)));
+
+        let input = (0..rng.below(MAX_INPUTS + 1))
+            .map(|_| rng.range(-3, 10))
+            .collect();
+        FuzzCase { program, input }
+    }
+}
+
+impl fmt::Display for FuzzCase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let join = |words: &[Word]| {
+            words
+                .iter()
+                .map(|word| word.to_string())
+                .collect::<Vec<String>>()
+                .join(",")
+        };
+        writeln!(f, "{}", join(&self.program))?;
+        writeln!(f, "{}", join(&self.input))
+    }
+}
+
+impl FromStr for FuzzCase {
+    type Err = ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines().filter(|line| !line.starts_with('#'));
+        let parse = |line: Option<&str>| -> Result<Vec<Word>, ParseIntError> {
+            match line.map(str::trim) {
+                Some("") | None => Ok(Vec::new()),
+                Some(line) => line.split(',').map(|word| word.trim().parse()).collect(),
+            }
+        };
+        let program = parse(Some(lines.next().unwrap_or("")))?;
+        let input = parse(lines.next())?;
+        if program.is_empty() {
+            // Parse the empty string to get a ParseIntError to return
+            return Err("".parse::<Word>().unwrap_err());
+        }
+        Ok(FuzzCase { program, input })
+    }
+}
+
+/// How often a run pauses. See [`IntcodeComputer::pause_on_output`] and
+/// [`IntcodeComputer::step_limit`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RunSettings {
+    pub pause_on_output: Option<usize>,
+    pub step_limit: Option<usize>,
+}
+
+/// The state of a machine after a run, with the reason it stopped each time
+/// it was resumed.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Outcome {
+    pub stop_reasons: Vec<StopReason>,
+    pub steps: usize,
+    pub eip: usize,
+    pub relbase: Word,
+    pub memory: Memory,
+    pub output: Vec<Word>,
+}
+
+/// Runs `case` and resumes it after every pause, until it halts, fails, needs
+/// more input or runs out of steps or memory.
+pub fn run(case: &FuzzCase, engine: Engine, settings: RunSettings) -> Outcome {
+    let mut computer = IntcodeComputer::new(case.program.clone());
+    computer.engine = engine;
+    // Most programs stop long before the cache would normally be used
+    computer.cache.warmup_steps = 0;
+    computer.pause_on_output = settings.pause_on_output;
+    computer.step_limit = settings.step_limit;
+    computer.step_budget = Some(STEP_BUDGET);
+    computer.prog.limit = Some(MEMORY_LIMIT);
+
+    let mut stop_reasons = vec![computer.run_mut(case.input.iter().copied())];
+    while let Some(StopReason::OutputReady(_)) | Some(StopReason::StepLimitReached) =
+        stop_reasons.last()
+    {
+        stop_reasons.push(computer.resume());
+    }
+
+    Outcome {
+        stop_reasons,
+        steps: STEP_BUDGET - computer.step_budget.unwrap(),
+        eip: computer.eip,
+        relbase: computer.relbase,
+        memory: computer.prog,
+        output: computer.output.into_iter().collect(),
+    }
+}
+
+/// An engine that ended up in a different state than [`Engine::Step`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Divergence {
+    pub engine: Engine,
+    pub settings: RunSettings,
+    pub expected: Box<Outcome>,
+    pub actual: Box<Outcome>,
+}
+
+fn first_memory_difference(a: &Memory, b: &Memory) -> Option<usize> {
+    (0..a.dense_len().max(b.dense_len()))
+        .chain(a.sparse_entries().map(|(addr, _)| addr))
+        .chain(b.sparse_entries().map(|(addr, _)| addr))
+        .filter(|addr| a.get(*addr) != b.get(*addr))
+        .min()
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:?} engine disagrees with Step with {:?}",
+            self.engine, self.settings
+        )?;
+        let (expected, actual) = (&self.expected, &self.actual);
+        if expected.stop_reasons != actual.stop_reasons {
+            write!(
+                f,
+                "\n  stop reasons: expected {:?}, got {:?}",
+                expected.stop_reasons, actual.stop_reasons
+            )?;
+        }
+        if expected.steps != actual.steps {
+            write!(
+                f,
+                "\n  steps: expected {}, got {}",
+                expected.steps, actual.steps
+            )?;
+        }
+        if expected.eip != actual.eip {
+            write!(f, "\n  eip: expected {}, got {}", expected.eip, actual.eip)?;
+        }
+        if expected.relbase != actual.relbase {
+            write!(
+                f,
+                "\n  relbase: expected {}, got {}",
+                expected.relbase, actual.relbase
+            )?;
+        }
+        if expected.output != actual.output {
+            write!(
+                f,
+                "\n  output: expected {:?}, got {:?}",
+                expected.output, actual.output
+            )?;
+        }
+        match first_memory_difference(&expected.memory, &actual.memory) {
+            Some(addr) => write!(
+                f,
+                "\n  memory at {}: expected {}, got {}",
+                addr,
+                expected.memory.get(addr),
+                actual.memory.get(addr)
+            )?,
+            None if expected.memory != actual.memory => write!(f, "\n  memory layout differs")?,
+            None => {}
+        }
+        Ok(())
+    }
+}
+
+/// Runs `case` on every engine with every setting, and returns the first
+/// disagreement with [`Engine::Step`].
+pub fn compare(case: &FuzzCase) -> Result<(), Divergence> {
+    for settings in &SETTINGS {
+        let expected = run(case, Engine::Step, *settings);
+        for engine in &ALTERNATIVE_ENGINES {
+            let actual = run(case, *engine, *settings);
+            if actual != expected {
+                return Err(Divergence {
+                    engine: *engine,
+                    settings: *settings,
+                    expected: Box::new(expected),
+                    actual: Box::new(actual),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Shrinks `case` by removing and zeroing words of the program and input for
+/// as long as `fails` still returns `true`.
+pub fn minimize<F>(case: &FuzzCase, mut fails: F) -> FuzzCase
+where
+    F: FnMut(&FuzzCase) -> bool,
+{
+    let mut case = case.clone();
+    let candidates = |case: &FuzzCase| {
+        let mut candidates = Vec::new();
+        for i in (0..case.program.len()).rev() {
+            let mut removed = case.clone();
+            removed.program.remove(i);
+            candidates.push(removed);
+            if case.program[i] != 0 {
+                let mut zeroed = case.clone();
+                zeroed.program[i] = 0;
+                candidates.push(zeroed);
+            }
+        }
+        for i in (0..case.input.len()).rev() {
+            let mut removed = case.clone();
+            removed.input.remove(i);
+            candidates.push(removed);
+        }
+        candidates
+    };
+
+    while let Some(smaller) = candidates(&case)
+        .into_iter()
+        .find(|candidate| !candidate.program.is_empty() && fails(candidate))
+    {
+        case = smaller;
+    }
+    case
+}
+
+/// Checks `count` random programs generated from `seed`, and returns the
+/// first one that the engines disagree on, minimized.
+pub fn fuzz(seed: u64, count: usize) -> Option<(FuzzCase, Divergence)> {
+    let mut rng = Rng::new(seed);
+    (0..count)
+        .map(|_| FuzzCase::generate(&mut rng))
+        .find(|case| compare(case).is_err())
+        .map(|case| {
+            let case = minimize(&case, |candidate| compare(candidate).is_err());
+            let divergence = compare(&case).unwrap_err();
+            (case, divergence)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::disasm::Instruction;
+
+    #[test]
+    fn generates_valid_instructions() {
+        let mut rng = Rng::new(7);
+        for _ in 0..100 {
+            let case = FuzzCase::generate(&mut rng);
+            let mut addr = 0;
+            while addr < case.program.len() - DATA_WORDS {
+                let instruction = Instruction::decode(&case.program, addr)
+                    .unwrap_or_else(|| panic!("Invalid instruction at {} in {}", addr, case));
+                addr += instruction.size();
+            }
+            assert_eq!(case.program[addr - 1], OP_HALT);
+            assert_eq!(case.to_string().parse(), Ok(case));
+        }
+    }
+
+    #[test]
+    fn engines_agree_on_random_programs() {
+        if let Some((case, divergence)) = fuzz(2019, 200) {
+            panic!("{}\n{}", divergence, case);
+        }
+    }
+
+    #[test]
+    fn minimizes_while_still_failing() {
+        let case = FuzzCase {
+            program: vec![1, 2, 3, 104, 7, 99, 4, 5],
+            input: vec![1, 2],
+        };
+        let minimized = minimize(&case, |candidate| {
+            candidate.program.contains(&104) && candidate.program.contains(&7)
+        });
+        assert_eq!(
+            minimized,
+            FuzzCase {
+                program: vec![104, 7],
+                input: vec![],
+            }
+        );
+    }
+}
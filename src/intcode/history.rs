@@ -0,0 +1,271 @@
+use super::io::IntcodeInput;
+use super::io::IntcodeOutput;
+use super::memory::Layout;
+use super::memory::Memory;
+use super::opcode_info;
+use super::IntcodeComputer;
+use super::ParamKind;
+use super::Word;
+use super::OP_INPUT;
+use super::OP_OUTPUT;
+use std::collections::VecDeque;
+
+/// What it takes to undo one executed instruction.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(super) struct UndoEntry {
+    eip: usize,
+    relbase: Word,
+    /// The addresses the instruction wrote to, with their previous values and
+    /// the layout of memory if the write changed it.
+    writes: Vec<(usize, Word, Option<Layout>)>,
+    /// The value read from `input`, if any.
+    input: Option<Word>,
+    wrote_output: bool,
+}
+
+impl UndoEntry {
+    /// Fills in the I/O of the instruction after it has executed.
+    pub(super) fn finish(mut self, opcode: Word, prog: &Memory) -> Self {
+        match opcode {
+            OP_INPUT => self.input = self.writes.first().map(|(addr, _, _)| prog.get(*addr)),
+            OP_OUTPUT => self.wrote_output = true,
+            _ => {}
+        }
+        self
+    }
+}
+
+/// An undo log of executed instructions, recorded when an [`IntcodeComputer`]
+/// has a `history`. Used by [`step_back`](IntcodeComputer::step_back) and
+/// [`back_to_write`](IntcodeComputer::back_to_write).
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct History {
+    entries: VecDeque<UndoEntry>,
+    /// The maximum number of instructions to remember. The oldest ones are
+    /// forgotten first.
+    pub limit: Option<usize>,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_limit(limit: usize) -> Self {
+        History {
+            entries: VecDeque::new(),
+            limit: Some(limit),
+        }
+    }
+
+    /// The number of instructions that can be undone.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub(super) fn push(&mut self, entry: UndoEntry) {
+        if self.limit == Some(self.entries.len()) {
+            self.entries.pop_front();
+        }
+        if self.limit != Some(0) {
+            self.entries.push_back(entry);
+        }
+    }
+}
+
+impl<I, O> IntcodeComputer<I, O>
+where
+    I: IntcodeInput,
+    O: IntcodeOutput,
+{
+    /// Saves what the instruction at `eip` is about to overwrite.
+    pub(super) fn undo_entry(&self) -> UndoEntry {
+        let eip = self.eip;
+        let instruction = self.prog.get(eip);
+        let opcode = instruction % 100;
+        let params = opcode_info(opcode)
+            .map(|info| info.params.to_vec())
+            .or_else(|| self.opcodes.get(opcode).map(|custom| custom.params.clone()))
+            .unwrap_or_default();
+
+        let mut modes = instruction / 100;
+        let mut writes = Vec::new();
+        for (i, kind) in params.iter().enumerate() {
+            let param = self.prog.get(eip + 1 + i);
+            let addr = match modes % 10 {
+                2 => self.relbase.saturating_add(param),
+                _ => param,
+            };
+            if *kind == ParamKind::Write && addr >= 0 {
+                let addr = addr as usize;
+                writes.push((
+                    addr,
+                    self.prog.get(addr),
+                    self.prog.layout_before_write(addr),
+                ));
+            }
+            modes /= 10;
+        }
+
+        UndoEntry {
+            eip,
+            relbase: self.relbase,
+            writes,
+            input: None,
+            wrote_output: false,
+        }
+    }
+
+    /// Writes `value` to `addr` from outside the program. With a `history`,
+    /// stepping back over the last executed instruction also undoes the write.
+    pub fn poke(&mut self, addr: usize, value: Word) {
+        if let Some(entry) = self.history.as_mut().and_then(|h| h.entries.back_mut()) {
+            entry.writes.push((
+                addr,
+                self.prog.get(addr),
+                self.prog.layout_before_write(addr),
+            ));
+        }
+        self.prog[addr] = value;
+    }
+}
+
+impl IntcodeComputer {
+    /// Undoes up to `n` instructions, including their input and output, and
+    /// returns the number undone. Does nothing without a `history`. Traces,
+    /// profiles and sessions are not rewound.
+    pub fn step_back(&mut self, n: usize) -> usize {
+        let history = match &mut self.history {
+            Some(history) => history,
+            None => return 0,
+        };
+        let mut undone = 0;
+        while undone < n {
+            let entry = match history.entries.pop_back() {
+                Some(entry) => entry,
+                None => break,
+            };
+            for (addr, old, layout) in entry.writes.into_iter().rev() {
+                self.prog[addr] = old;
+                if let Some(layout) = layout {
+                    self.prog.restore_layout(layout);
+                }
+            }
+            if let Some(value) = entry.input {
+                self.input.push_front(value);
+            }
+            if entry.wrote_output {
+                self.output.pop_back();
+            }
+            self.eip = entry.eip;
+            self.relbase = entry.relbase;
+            undone += 1;
+        }
+        undone
+    }
+
+    /// Rewinds to just before the last instruction that wrote to `addr`, and
+    /// returns the number of instructions undone. Returns `None` and stays put
+    /// if the history has no such write.
+    pub fn back_to_write(&mut self, addr: usize) -> Option<usize> {
+        let history = self.history.as_ref()?;
+        let index = history
+            .entries
+            .iter()
+            .rposition(|entry| entry.writes.iter().any(|(a, _, _)| *a == addr))?;
+        Some(self.step_back(history.entries.len() - index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::day_input_filename;
+    use crate::common::get_file_lines;
+    use crate::intcode::parse_program;
+    use crate::intcode::StopReason;
+
+    #[test]
+    fn steps_back_to_earlier_states() {
+        let mut computer = IntcodeComputer::new(vec![3, 11, 3, 12, 1, 11, 12, 11, 4, 11, 99, 0, 0]);
+        computer.history = Some(History::new());
+        let initial = computer.clone();
+        computer.input.extend(vec![4, 7]);
+        assert_eq!(computer.resume(), StopReason::Halted);
+        assert_eq!(computer.output, vec![11]);
+
+        assert_eq!(computer.step_back(1), 1);
+        assert_eq!(computer.eip, 8);
+        assert_eq!(computer.output, vec![]);
+
+        assert_eq!(computer.back_to_write(11), Some(1));
+        assert_eq!(computer.eip, 4);
+        assert_eq!(computer.prog.get(11), 4);
+
+        assert_eq!(computer.back_to_write(11), Some(2));
+        assert_eq!(computer.back_to_write(11), None);
+        assert_eq!(computer.step_back(10), 0);
+        assert_eq!(computer.prog, initial.prog);
+        assert_eq!(computer.input, vec![4, 7]);
+    }
+
+    #[test]
+    fn shrinks_memory_when_stepping_back() {
+        // Grow dense memory over the sparse value at 1000, then write far away
+        let program = vec![1101, 1, 2, 1020, 1101, 3, 4, 1_000_000, 99];
+        let sparse = vec![(1000, 5), (3000, 6)].into_iter().collect();
+        let mut computer = IntcodeComputer::new(vec![]);
+        computer.prog = Memory::from_parts(program, sparse, None).unwrap();
+        computer.history = Some(History::new());
+        let initial = computer.clone();
+        assert_eq!(computer.resume(), StopReason::Halted);
+        assert_ne!(computer.prog, initial.prog);
+
+        assert_eq!(computer.step_back(2), 2);
+        assert_eq!(computer.prog, initial.prog);
+        assert_eq!(computer.snapshot(), initial.snapshot());
+    }
+
+    #[test]
+    fn steps_back_over_pokes() {
+        let mut computer = IntcodeComputer::new(vec![3, 11, 3, 12, 1, 11, 12, 11, 4, 11, 99, 0, 0]);
+        computer.history = Some(History::new());
+        computer.input.extend(vec![4, 7]);
+        computer.step().unwrap();
+        let before = computer.clone();
+        computer.step().unwrap();
+        computer.poke(11, 5);
+        computer.poke(11, 6);
+
+        assert_eq!(computer.step_back(1), 1);
+        assert_eq!(computer.prog, before.prog);
+        assert_eq!(computer.resume(), StopReason::Halted);
+        assert_eq!(computer.output, vec![11]);
+    }
+
+    #[test]
+    fn rewinds_and_reruns_day9() {
+        let program = IntcodeComputer::new(
+            parse_program(&get_file_lines(&day_input_filename(9)).unwrap()).unwrap(),
+        );
+        let mut expected = program.clone();
+        assert_eq!(expected.run_mut(Some(2)), StopReason::Halted);
+
+        let mut computer = program.clone();
+        computer.history = Some(History::with_limit(1000));
+        assert_eq!(computer.run_mut(Some(2)), StopReason::Halted);
+        assert_eq!(computer.output, expected.output);
+        assert_eq!(computer.history.as_ref().unwrap().len(), 1000);
+
+        assert_eq!(computer.step_back(2000), 1000);
+        assert!(computer.output.is_empty());
+        assert_eq!(computer.resume(), StopReason::Halted);
+        assert_eq!(computer.output, expected.output);
+        assert_eq!(computer.prog, expected.prog);
+        assert_eq!(computer.relbase(), expected.relbase());
+    }
+}
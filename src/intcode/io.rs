@@ -0,0 +1,229 @@
+use super::Word;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::io::BufRead;
+use std::io::StdinLock;
+use std::rc::Rc;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::Sender;
+
+pub trait IntcodeInput {
+    /// Returns `None` if no input is available, which stops the computer with
+    /// `StopReason::NeedsInput`.
+    fn read(&mut self) -> Option<Word>;
+}
+
+pub trait IntcodeOutput {
+    fn write(&mut self, value: Word);
+}
+
+impl IntcodeInput for VecDeque<Word> {
+    fn read(&mut self) -> Option<Word> {
+        self.pop_front()
+    }
+}
+
+impl IntcodeOutput for VecDeque<Word> {
+    fn write(&mut self, value: Word) {
+        self.push_back(value);
+    }
+}
+
+/// A queue that can be shared between computers, for example as the output of
+/// one computer and the input of another.
+pub type SharedQueue = Rc<RefCell<VecDeque<Word>>>;
+
+impl IntcodeInput for SharedQueue {
+    fn read(&mut self) -> Option<Word> {
+        self.borrow_mut().pop_front()
+    }
+}
+
+impl IntcodeOutput for SharedQueue {
+    fn write(&mut self, value: Word) {
+        self.borrow_mut().push_back(value);
+    }
+}
+
+impl<F> IntcodeInput for F
+where
+    F: FnMut() -> Option<Word>,
+{
+    fn read(&mut self) -> Option<Word> {
+        self()
+    }
+}
+
+impl<F> IntcodeOutput for F
+where
+    F: FnMut(Word),
+{
+    fn write(&mut self, value: Word) {
+        self(value);
+    }
+}
+
+/// Blocks until a value is received; a disconnected channel counts as no input.
+impl IntcodeInput for Receiver<Word> {
+    fn read(&mut self) -> Option<Word> {
+        self.recv().ok()
+    }
+}
+
+/// Values sent after the receiver has hung up are discarded.
+impl IntcodeOutput for Sender<Word> {
+    fn write(&mut self, value: Word) {
+        self.send(value).ok();
+    }
+}
+
+/// Reads input one line at a time and feeds it to the computer as ASCII codes.
+/// Bytes that aren't ASCII are passed on as they are. After a read error, no
+/// more input is read; check [`error`](Self::error) to tell it from the end of
+/// the input.
+pub struct AsciiReader<R> {
+    reader: R,
+    buffer: VecDeque<Word>,
+    error: Option<std::io::Error>,
+}
+
+impl<R> AsciiReader<R>
+where
+    R: BufRead,
+{
+    pub fn new(reader: R) -> Self {
+        AsciiReader {
+            reader,
+            buffer: VecDeque::new(),
+            error: None,
+        }
+    }
+
+    /// The error that stopped the input, if any.
+    pub fn error(&self) -> Option<&std::io::Error> {
+        self.error.as_ref()
+    }
+}
+
+impl AsciiReader<StdinLock<'static>> {
+    pub fn stdin() -> Self {
+        Self::new(std::io::stdin().lock())
+    }
+}
+
+impl<R> IntcodeInput for AsciiReader<R>
+where
+    R: BufRead,
+{
+    fn read(&mut self) -> Option<Word> {
+        if self.buffer.is_empty() && self.error.is_none() {
+            let mut line = Vec::new();
+            match self.reader.read_until(b'\n', &mut line) {
+                Ok(_) => self.buffer.extend(line.into_iter().map(Word::from)),
+                Err(err) => self.error = Some(err),
+            }
+        }
+        self.buffer.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::IntcodeComputer;
+    use crate::intcode::StopReason;
+
+    const DOUBLER: [Word; 11] = [3, 9, 102, 2, 9, 9, 4, 9, 99, 0, 0];
+
+    #[test]
+    fn reads_from_closure() {
+        let mut output = Vec::new();
+        let mut computer =
+            IntcodeComputer::with_io(DOUBLER.to_vec(), || Some(21), |o| output.push(o));
+        assert_eq!(computer.resume(), StopReason::Halted);
+        drop(computer);
+        assert_eq!(output, vec![42]);
+    }
+
+    #[test]
+    fn connects_computers_through_shared_queue() {
+        let input = SharedQueue::default();
+        let between = SharedQueue::default();
+        let output = SharedQueue::default();
+        let mut first = IntcodeComputer::with_io(DOUBLER.to_vec(), input.clone(), between.clone());
+        let mut second = IntcodeComputer::with_io(DOUBLER.to_vec(), between, output.clone());
+
+        assert_eq!(second.resume(), StopReason::NeedsInput);
+        input.borrow_mut().push_back(5);
+        assert_eq!(first.resume(), StopReason::Halted);
+        assert_eq!(second.resume(), StopReason::Halted);
+        assert_eq!(
+            output.borrow().iter().copied().collect::<Vec<Word>>(),
+            vec![20]
+        );
+    }
+
+    #[test]
+    fn reads_from_channel() {
+        let (in_tx, in_rx) = std::sync::mpsc::channel();
+        let (out_tx, out_rx) = std::sync::mpsc::channel();
+        let mut computer = IntcodeComputer::with_io(DOUBLER.to_vec(), in_rx, out_tx);
+
+        in_tx.send(4).unwrap();
+        assert_eq!(computer.resume(), StopReason::Halted);
+        assert_eq!(out_rx.recv(), Ok(8));
+    }
+
+    #[test]
+    fn reads_ascii_lines() {
+        let program = vec![3, 100, 3, 101, 3, 102, 3, 103, 99];
+        let mut computer = IntcodeComputer::with_io(
+            program,
+            AsciiReader::new("hi\nx".as_bytes()),
+            VecDeque::new(),
+        );
+        assert_eq!(computer.resume(), StopReason::Halted);
+        assert_eq!(computer.prog.as_slice()[100..104], [104, 105, 10, 120]);
+
+        let mut computer = IntcodeComputer::with_io(
+            vec![3, 100, 99],
+            AsciiReader::new("".as_bytes()),
+            VecDeque::new(),
+        );
+        assert_eq!(computer.resume(), StopReason::NeedsInput);
+        assert!(computer.input.error().is_none());
+    }
+
+    #[test]
+    fn reads_non_ascii_bytes() {
+        let mut computer = IntcodeComputer::with_io(
+            vec![3, 100, 3, 101, 99],
+            AsciiReader::new(&[0xff, b'\n'][..]),
+            VecDeque::new(),
+        );
+        assert_eq!(computer.resume(), StopReason::Halted);
+        assert_eq!(computer.prog.as_slice()[100..102], [255, 10]);
+    }
+
+    #[test]
+    fn keeps_read_errors() {
+        struct Broken;
+
+        impl std::io::Read for Broken {
+            fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("broken"))
+            }
+        }
+
+        let mut computer = IntcodeComputer::with_io(
+            vec![3, 100, 99],
+            AsciiReader::new(std::io::BufReader::new(Broken)),
+            VecDeque::new(),
+        );
+        assert_eq!(computer.resume(), StopReason::NeedsInput);
+        assert_eq!(
+            computer.input.error().map(|err| err.to_string()),
+            Some("broken".to_string())
+        );
+    }
+}
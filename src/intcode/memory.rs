@@ -0,0 +1,278 @@
+use super::Word;
+use std::collections::BTreeMap;
+use std::ops::Index;
+use std::ops::IndexMut;
+
+/// Writes up to this many words past the end of dense memory, or twice its
+/// length, grow it instead of going to the sparse map.
+const DENSE_SLACK: usize = 1024;
+
+/// Intcode memory: a dense `Vec` holding the program image and anything
+/// written near it, and a sparse map for far away addresses. Unwritten
+/// addresses read as 0.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Memory {
+    dense: Vec<Word>,
+    sparse: BTreeMap<usize, Word>,
+    /// The maximum number of words that the program may cause to be stored.
+    /// Writes from outside the program, through `IndexMut`, are not limited.
+    pub limit: Option<usize>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MemoryLimitExceeded;
+
+/// The parts of memory that writing to a new address changes besides the
+/// value at that address: the length of the dense part, and the sparse entries
+/// in `sparse_range`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(super) struct Layout {
+    dense_len: usize,
+    sparse_range: std::ops::Range<usize>,
+    sparse: Vec<(usize, Word)>,
+}
+
+impl Memory {
+    #[inline]
+    pub fn get(&self, addr: usize) -> Word {
+        match self.dense.get(addr) {
+            Some(value) => *value,
+            None => self.sparse.get(&addr).copied().unwrap_or(0),
+        }
+    }
+
+    #[inline]
+    pub fn set(&mut self, addr: usize, value: Word) -> Result<(), MemoryLimitExceeded> {
+        if let Some(slot) = self.dense.get_mut(addr) {
+            *slot = value;
+        } else if let Some(slot) = self.sparse.get_mut(&addr) {
+            *slot = value;
+        } else if self.is_near_dense(addr) {
+            // Sparse values below `addr` move into the dense part
+            if !self.allows(addr + 1 + self.sparse.range(addr + 1..).count()) {
+                return Err(MemoryLimitExceeded);
+            }
+            self.grow_dense(addr + 1);
+            self.dense[addr] = value;
+        } else {
+            if !self.allows(self.dense.len() + self.sparse.len() + 1) {
+                return Err(MemoryLimitExceeded);
+            }
+            self.sparse.insert(addr, value);
+        }
+        Ok(())
+    }
+
+    fn allows(&self, words: usize) -> bool {
+        self.limit.map(|limit| words <= limit).unwrap_or(true)
+    }
+
+    fn is_near_dense(&self, addr: usize) -> bool {
+        addr < (self.dense.len() * 2).max(self.dense.len() + DENSE_SLACK)
+    }
+
+    fn grow_dense(&mut self, len: usize) {
+        let far = self.sparse.split_off(&len);
+        self.dense.resize(len, 0);
+        for (addr, value) in std::mem::replace(&mut self.sparse, far) {
+            self.dense[addr] = value;
+        }
+    }
+
+    /// Returns what a write to `addr` would change besides its value, or `None`
+    /// if the address is already stored.
+    pub(super) fn layout_before_write(&self, addr: usize) -> Option<Layout> {
+        if addr < self.dense.len() || self.sparse.contains_key(&addr) {
+            None
+        } else if self.is_near_dense(addr) {
+            let sparse_range = self.dense.len()..addr + 1;
+            Some(Layout {
+                dense_len: self.dense.len(),
+                sparse: self
+                    .sparse
+                    .range(sparse_range.clone())
+                    .map(|(addr, value)| (*addr, *value))
+                    .collect(),
+                sparse_range,
+            })
+        } else {
+            Some(Layout {
+                dense_len: self.dense.len(),
+                sparse_range: addr..addr + 1,
+                sparse: Vec::new(),
+            })
+        }
+    }
+
+    /// Undoes the changes a write made to the layout of memory.
+    pub(super) fn restore_layout(&mut self, layout: Layout) {
+        self.dense.truncate(layout.dense_len);
+        let after = self.sparse.split_off(&layout.sparse_range.end);
+        self.sparse.split_off(&layout.sparse_range.start);
+        self.sparse.extend(after);
+        self.sparse.extend(layout.sparse);
+    }
+
+    /// Returns `None` unless all sparse addresses are past the dense part.
+    pub(super) fn from_parts(
+        dense: Vec<Word>,
+        sparse: BTreeMap<usize, Word>,
+        limit: Option<usize>,
+    ) -> Option<Memory> {
+        match sparse.keys().next() {
+            Some(addr) if *addr < dense.len() => None,
+            _ => Some(Memory {
+                dense,
+                sparse,
+                limit,
+            }),
+        }
+    }
+
+    /// The dense part of memory, which starts with the program image.
+    pub fn as_slice(&self) -> &[Word] {
+        &self.dense
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [Word] {
+        &mut self.dense
+    }
+
+    /// The length of the dense part of memory.
+    pub fn dense_len(&self) -> usize {
+        self.dense.len()
+    }
+
+    /// Whether no words are stored, neither dense nor sparse.
+    pub fn is_empty(&self) -> bool {
+        self.dense.is_empty() && self.sparse.is_empty()
+    }
+
+    /// The values stored outside the dense part of memory, in address order.
+    pub fn sparse_entries(&self) -> impl Iterator<Item = (usize, Word)> + '_ {
+        self.sparse.iter().map(|(addr, value)| (*addr, *value))
+    }
+}
+
+impl From<Vec<Word>> for Memory {
+    fn from(dense: Vec<Word>) -> Self {
+        Memory {
+            dense,
+            sparse: BTreeMap::new(),
+            limit: None,
+        }
+    }
+}
+
+impl Index<usize> for Memory {
+    type Output = Word;
+
+    fn index(&self, addr: usize) -> &Word {
+        match self.dense.get(addr) {
+            Some(value) => value,
+            None => self.sparse.get(&addr).unwrap_or(&0),
+        }
+    }
+}
+
+impl IndexMut<usize> for Memory {
+    fn index_mut(&mut self, addr: usize) -> &mut Word {
+        if addr >= self.dense.len() && self.is_near_dense(addr) {
+            self.grow_dense(addr + 1);
+        }
+        match self.dense.get_mut(addr) {
+            Some(slot) => slot,
+            None => self.sparse.entry(addr).or_insert(0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::IntcodeComputer;
+    use crate::intcode::IntcodeError;
+    use crate::intcode::StopReason;
+
+    #[test]
+    fn stores_far_addresses_sparsely() {
+        let mut memory = Memory::from(vec![1, 2, 3]);
+        memory.set(1_000_000_000_000, 7).unwrap();
+        memory.set(10, 5).unwrap();
+        assert_eq!(memory.dense_len(), 11);
+        assert_eq!(memory.get(1_000_000_000_000), 7);
+        assert_eq!(memory[10], 5);
+        assert_eq!(memory.get(999), 0);
+        assert_eq!(
+            memory.sparse_entries().collect::<Vec<_>>(),
+            vec![(1_000_000_000_000, 7)]
+        );
+    }
+
+    #[test]
+    fn moves_sparse_values_into_dense_memory_when_it_grows() {
+        let mut memory = Memory::from(vec![0; 10]);
+        memory.set(5000, 1).unwrap();
+        memory.set(2000, 2).unwrap();
+        assert_eq!(memory.dense_len(), 10);
+        memory.set(1000, 3).unwrap();
+        memory.set(2020, 4).unwrap();
+        assert_eq!(memory.dense_len(), 2021);
+        assert_eq!(memory.sparse_entries().collect::<Vec<_>>(), vec![(5000, 1)]);
+        assert_eq!(
+            (memory[1000], memory[2000], memory[2020], memory[5000]),
+            (3, 2, 4, 1)
+        );
+    }
+
+    #[test]
+    fn fills_memory_up_to_limit_when_it_grows() {
+        let mut memory = Memory::from(vec![0; 10]);
+        memory.limit = Some(2022);
+        memory.set(5000, 1).unwrap();
+        memory.set(2000, 2).unwrap();
+        memory.set(1000, 3).unwrap();
+        memory.set(2020, 4).unwrap();
+        assert_eq!(memory.dense_len() + memory.sparse_entries().count(), 2022);
+        assert_eq!(memory.set(3000, 5), Err(MemoryLimitExceeded));
+    }
+
+    #[test]
+    fn runs_program_writing_to_huge_address() {
+        let mut computer = IntcodeComputer::new(vec![
+            1101,
+            3,
+            4,
+            1_000_000_000_000,
+            4,
+            1_000_000_000_000,
+            99,
+        ]);
+        assert_eq!(computer.run_mut(None), StopReason::Halted);
+        assert_eq!(computer.output, vec![7]);
+    }
+
+    #[test]
+    fn exceeding_memory_limit_is_an_error() {
+        let mut computer = IntcodeComputer::new(vec![109, 1, 21101, 1, 2, 100, 1105, 1, 0]);
+        computer.prog.limit = Some(200);
+        assert_eq!(
+            computer.run_mut(None),
+            StopReason::Error(IntcodeError::MemoryLimitExceeded {
+                eip: 2,
+                address: 200
+            })
+        );
+        assert_eq!(computer.prog.dense_len(), 200);
+
+        let mut computer = IntcodeComputer::new(vec![1101, 3, 4, 1_000_000, 99]);
+        computer.prog.limit = Some(5);
+        assert_eq!(
+            computer.run_mut(None),
+            StopReason::Error(IntcodeError::MemoryLimitExceeded {
+                eip: 0,
+                address: 1_000_000
+            })
+        );
+    }
+}
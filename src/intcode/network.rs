@@ -0,0 +1,378 @@
+use super::IntcodeComputer;
+use super::IntcodeError;
+use super::StopReason;
+use super::Word;
+
+/// A machine counts as idle once it has waited for input this many turns in a
+/// row without sending anything.
+const IDLE_TURNS: usize = 2;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Flow {
+    Continue,
+    Stop,
+}
+
+/// Decides what happens to the messages sent by the machines in a [`Network`].
+pub trait Router {
+    /// The number of output values that make up one message.
+    fn message_len(&self) -> usize {
+        1
+    }
+
+    /// Handles a message sent by machine `from`.
+    fn route(&mut self, from: usize, message: &[Word], inboxes: &mut Inboxes) -> Flow;
+
+    /// The value a machine reads when its input is empty at the time it
+    /// reads, or `None` to make it wait until someone sends it something.
+    fn empty_input(&self, _machine: usize) -> Option<Word> {
+        None
+    }
+
+    /// Called when all machines are idle. If this sends nothing and doesn't
+    /// stop the network, the network is deadlocked.
+    fn on_idle(&mut self, _inboxes: &mut Inboxes) -> Flow {
+        Flow::Continue
+    }
+}
+
+/// The input queues of the machines in a network.
+pub struct Inboxes<'a>(&'a mut [IntcodeComputer]);
+
+impl Inboxes<'_> {
+    /// Returns `false` if there is no machine `to`.
+    pub fn send(&mut self, to: usize, values: &[Word]) -> bool {
+        match self.0.get_mut(to) {
+            Some(machine) => {
+                machine.input.extend(values);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn machine_count(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Ignores halted machines, which will never read their input.
+    fn all_empty(&self) -> bool {
+        self.0
+            .iter()
+            .all(|machine| !machine.is_running() || machine.input.is_empty())
+    }
+}
+
+/// Machines take turns in order, so a network always runs the same way.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Scheduling {
+    /// Each turn lasts until the machine sends a message, waits for input or
+    /// halts.
+    UntilBlocked,
+    /// Each turn also ends after at most this many instructions, so a machine
+    /// that computes for a long time doesn't hold up the others.
+    TimeSlice(usize),
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum NetworkStop {
+    /// The router returned [`Flow::Stop`].
+    Stopped,
+    AllHalted,
+    /// All machines are idle and the router didn't wake any of them.
+    Deadlock,
+    Error {
+        machine: usize,
+        error: IntcodeError,
+    },
+    /// A machine used up its step budget or ran past its timeout.
+    LimitReached {
+        machine: usize,
+        reason: StopReason,
+    },
+}
+
+/// A set of computers that send messages to each other through a [`Router`].
+/// The network takes over the `pause_on_output` and `step_limit` settings of
+/// the machines.
+pub struct Network<R> {
+    pub machines: Vec<IntcodeComputer>,
+    pub router: R,
+    pub scheduling: Scheduling,
+    idle_turns: Vec<usize>,
+    /// Whether each machine stopped waiting for input on its last turn.
+    waiting: Vec<bool>,
+}
+
+impl<R> Network<R>
+where
+    R: Router,
+{
+    pub fn new(machines: Vec<IntcodeComputer>, router: R) -> Self {
+        Network {
+            idle_turns: vec![0; machines.len()],
+            waiting: vec![false; machines.len()],
+            machines,
+            router,
+            scheduling: Scheduling::UntilBlocked,
+        }
+    }
+
+    /// Gives machine `i` one turn and routes the messages it sent.
+    fn turn(&mut self, i: usize) -> Result<Flow, NetworkStop> {
+        let message_len = self.router.message_len();
+        let machine = &mut self.machines[i];
+
+        // Only a machine already blocked on input reads it right away. Any
+        // other machine might get a message before it reads.
+        let started_empty = machine.input.is_empty();
+        if started_empty && self.waiting[i] {
+            machine.input.extend(self.router.empty_input(i));
+        }
+        machine.pause_on_output = Some(message_len);
+        machine.step_limit = match self.scheduling {
+            Scheduling::UntilBlocked => None,
+            Scheduling::TimeSlice(steps) => Some(steps),
+        };
+        let stop_reason = machine.resume();
+        self.waiting[i] = stop_reason == StopReason::NeedsInput;
+        match stop_reason {
+            StopReason::Error(error) => return Err(NetworkStop::Error { machine: i, error }),
+            StopReason::BudgetExhausted | StopReason::TimedOut => {
+                return Err(NetworkStop::LimitReached {
+                    machine: i,
+                    reason: stop_reason,
+                })
+            }
+            _ => {}
+        }
+
+        let mut sent = false;
+        while self.machines[i].output.len() >= message_len {
+            let message: Vec<Word> = self.machines[i].output.drain(..message_len).collect();
+            sent = true;
+            if self
+                .router
+                .route(i, &message, &mut Inboxes(&mut self.machines))
+                == Flow::Stop
+            {
+                return Ok(Flow::Stop);
+            }
+        }
+
+        if started_empty && !sent && stop_reason == StopReason::NeedsInput {
+            self.idle_turns[i] += 1;
+        } else {
+            self.idle_turns[i] = 0;
+        }
+        Ok(Flow::Continue)
+    }
+
+    fn is_idle(&self) -> bool {
+        self.machines
+            .iter()
+            .zip(&self.idle_turns)
+            .all(|(machine, idle_turns)| {
+                !machine.is_running() || (*idle_turns >= IDLE_TURNS && machine.input.is_empty())
+            })
+    }
+
+    pub fn run(&mut self) -> NetworkStop {
+        loop {
+            for i in 0..self.machines.len() {
+                if self.machines[i].is_running() {
+                    match self.turn(i) {
+                        Ok(Flow::Continue) => {}
+                        Ok(Flow::Stop) => return NetworkStop::Stopped,
+                        Err(stop) => return stop,
+                    }
+                }
+            }
+
+            if self.machines.iter().all(|machine| !machine.is_running()) {
+                return NetworkStop::AllHalted;
+            }
+            if self.is_idle() {
+                let mut inboxes = Inboxes(&mut self.machines);
+                if self.router.on_idle(&mut inboxes) == Flow::Stop {
+                    return NetworkStop::Stopped;
+                }
+                if inboxes.all_empty() {
+                    return NetworkStop::Deadlock;
+                }
+            }
+        }
+    }
+}
+
+/// Passes every value from each machine to the next one, like the amplifiers
+/// of day 7. With `feedback`, values from the last machine go back to the
+/// first one.
+#[derive(Clone, Debug, Default)]
+pub struct Pipeline {
+    pub feedback: bool,
+    /// The last value sent by the last machine.
+    pub last_output: Option<Word>,
+}
+
+impl Router for Pipeline {
+    fn route(&mut self, from: usize, message: &[Word], inboxes: &mut Inboxes) -> Flow {
+        if from + 1 == inboxes.machine_count() {
+            self.last_output = message.last().copied();
+            if self.feedback {
+                inboxes.send(0, message);
+            }
+        } else {
+            inboxes.send(from + 1, message);
+        }
+        Flow::Continue
+    }
+}
+
+/// Receives the packets sent by a [`PacketRouter`] to addresses without a
+/// machine, and can wake up the network when it goes idle.
+pub trait Nat {
+    fn receive(&mut self, address: Word, x: Word, y: Word) -> Flow;
+
+    fn on_idle(&mut self, inboxes: &mut Inboxes) -> Flow;
+}
+
+/// Routes packets of the form `address, x, y` like the network of day 23.
+/// Machines read -1 when they have no packets.
+pub struct PacketRouter<N> {
+    pub nat: N,
+}
+
+impl<N> Router for PacketRouter<N>
+where
+    N: Nat,
+{
+    fn message_len(&self) -> usize {
+        3
+    }
+
+    fn route(&mut self, _from: usize, message: &[Word], inboxes: &mut Inboxes) -> Flow {
+        let (address, x, y) = (message[0], message[1], message[2]);
+        if address >= 0 && inboxes.send(address as usize, &[x, y]) {
+            Flow::Continue
+        } else {
+            self.nat.receive(address, x, y)
+        }
+    }
+
+    fn empty_input(&self, _machine: usize) -> Option<Word> {
+        Some(-1)
+    }
+
+    fn on_idle(&mut self, inboxes: &mut Inboxes) -> Flow {
+        self.nat.on_idle(inboxes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn amplifiers(program: &[Word], phases: &[Word], scheduling: Scheduling) -> Network<Pipeline> {
+        let mut machines: Vec<IntcodeComputer> = phases
+            .iter()
+            .map(|phase| {
+                let mut machine = IntcodeComputer::new(program.to_vec());
+                machine.input.push_back(*phase);
+                machine
+            })
+            .collect();
+        machines[0].input.push_back(0);
+        let mut network = Network::new(
+            machines,
+            Pipeline {
+                feedback: true,
+                last_output: None,
+            },
+        );
+        network.scheduling = scheduling;
+        network
+    }
+
+    #[test]
+    fn runs_feedback_loop_with_either_scheduling() {
+        let program = vec![
+            3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1,
+            28, 1005, 28, 6, 99, 0, 0, 5,
+        ];
+        for scheduling in &[Scheduling::UntilBlocked, Scheduling::TimeSlice(3)] {
+            let mut network = amplifiers(&program, &[9, 8, 7, 6, 5], *scheduling);
+            assert_eq!(network.run(), NetworkStop::AllHalted);
+            assert_eq!(network.router.last_output, Some(139629729));
+        }
+    }
+
+    #[test]
+    fn detects_deadlock() {
+        let mut network = amplifiers(&[3, 0, 3, 0, 4, 0, 99], &[1, 2], Scheduling::UntilBlocked);
+        network.machines[0].input.clear();
+        assert_eq!(network.run(), NetworkStop::Deadlock);
+    }
+
+    #[test]
+    fn detects_deadlock_with_input_left_for_halted_machines() {
+        let mut network = Network::new(
+            vec![
+                IntcodeComputer::new(vec![104, 1, 3, 0, 99]),
+                IntcodeComputer::new(vec![99]),
+            ],
+            Pipeline::default(),
+        );
+        assert_eq!(network.run(), NetworkStop::Deadlock);
+        assert_eq!(network.machines[1].input, vec![1]);
+    }
+
+    /// Stops the network at the first packet.
+    #[derive(Default)]
+    struct FirstPacket(Option<(Word, Word, Word)>);
+
+    impl Nat for FirstPacket {
+        fn receive(&mut self, address: Word, x: Word, y: Word) -> Flow {
+            self.0 = Some((address, x, y));
+            Flow::Stop
+        }
+
+        fn on_idle(&mut self, _inboxes: &mut Inboxes) -> Flow {
+            Flow::Stop
+        }
+    }
+
+    #[test]
+    fn reads_packets_sent_during_a_time_slice() {
+        let mut network = Network::new(
+            vec![
+                // Send 7, 8 to machine 1
+                IntcodeComputer::new(vec![104, 1, 104, 7, 104, 8, 99]),
+                // Compute for a while, then send the first value read to 255
+                IntcodeComputer::new(vec![
+                    1101, 0, 0, 30, 1101, 0, 0, 30, 1101, 0, 0, 30, 3, 30, 104, 255, 4, 30, 104, 0,
+                    99,
+                ]),
+            ],
+            PacketRouter {
+                nat: FirstPacket::default(),
+            },
+        );
+        network.scheduling = Scheduling::TimeSlice(2);
+        assert_eq!(network.run(), NetworkStop::Stopped);
+        assert_eq!(network.router.nat.0, Some((255, 7, 0)));
+    }
+
+    #[test]
+    fn reports_errors_with_machine_index() {
+        let mut network = amplifiers(&[4, 0, 42], &[1, 2], Scheduling::UntilBlocked);
+        network.machines[0].input.clear();
+        assert_eq!(
+            network.run(),
+            NetworkStop::Error {
+                machine: 0,
+                error: IntcodeError::InvalidOpcode { eip: 2, opcode: 42 }
+            }
+        );
+    }
+}
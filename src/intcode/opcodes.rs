@@ -0,0 +1,352 @@
+use super::io::IntcodeInput;
+use super::io::IntcodeOutput;
+use super::opcode_info;
+use super::IntcodeComputer;
+use super::IntcodeError;
+use super::ParamKind;
+use super::Word;
+use super::OP_HALT;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// Parameter modes are digits of the instruction, so a `Word` only has room
+/// for this many.
+const MAX_PARAMS: usize = 16;
+
+/// What a custom instruction does, given the values of its read parameters.
+/// Returns a message on failure, which stops the machine with
+/// [`IntcodeError::CustomOpcodeFailed`].
+pub type Handler = dyn Fn(&[Word]) -> Result<Effect, &'static str> + Send + Sync;
+
+/// The result of a custom instruction.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Effect {
+    /// One value for each write parameter, in order. Any other number of
+    /// values stops the machine with [`IntcodeError::CustomOpcodeFailed`].
+    pub writes: Vec<Word>,
+    /// Where to continue instead of the next instruction.
+    pub jump: Option<Word>,
+    /// Stop the run with [`StopReason::Trapped`](super::StopReason::Trapped)
+    /// after this instruction.
+    pub trap: bool,
+}
+
+impl Effect {
+    pub fn write(values: Vec<Word>) -> Self {
+        Effect {
+            writes: values,
+            ..Self::default()
+        }
+    }
+
+    pub fn jump(target: Word) -> Self {
+        Effect {
+            jump: Some(target),
+            ..Self::default()
+        }
+    }
+
+    pub fn trap() -> Self {
+        Effect {
+            trap: true,
+            ..Self::default()
+        }
+    }
+}
+
+pub struct CustomOpcode {
+    pub opcode: Word,
+    pub mnemonic: String,
+    pub params: Vec<ParamKind>,
+    handler: Box<Handler>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum RegistryError {
+    /// Opcodes are the last two digits of an instruction.
+    OutOfRange(Word),
+    Builtin(Word),
+    AlreadyRegistered(Word),
+    TooManyParams(usize),
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::OutOfRange(opcode) => write!(f, "Opcode {} is not between 0 and 98", opcode),
+            Self::Builtin(opcode) => write!(f, "Opcode {} is a built-in opcode", opcode),
+            Self::AlreadyRegistered(opcode) => {
+                write!(f, "Opcode {} is already registered", opcode)
+            }
+            Self::TooManyParams(count) => write!(
+                f,
+                "{} parameters is more than the maximum of {}",
+                count, MAX_PARAMS
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RegistryError {}
+
+/// Opcodes added to the built-in instruction set. A computer with an empty
+/// registry runs standard Intcode.
+#[derive(Clone, Default)]
+pub struct OpcodeRegistry {
+    opcodes: BTreeMap<Word, Arc<CustomOpcode>>,
+}
+
+impl OpcodeRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register<F>(
+        &mut self,
+        opcode: Word,
+        mnemonic: &str,
+        params: &[ParamKind],
+        handler: F,
+    ) -> Result<(), RegistryError>
+    where
+        F: Fn(&[Word]) -> Result<Effect, &'static str> + Send + Sync + 'static,
+    {
+        if !(0..OP_HALT).contains(&opcode) {
+            return Err(RegistryError::OutOfRange(opcode));
+        }
+        if opcode_info(opcode).is_some() {
+            return Err(RegistryError::Builtin(opcode));
+        }
+        if self.opcodes.contains_key(&opcode) {
+            return Err(RegistryError::AlreadyRegistered(opcode));
+        }
+        if params.len() > MAX_PARAMS {
+            return Err(RegistryError::TooManyParams(params.len()));
+        }
+        self.opcodes.insert(
+            opcode,
+            Arc::new(CustomOpcode {
+                opcode,
+                mnemonic: mnemonic.to_string(),
+                params: params.to_vec(),
+                handler: Box::new(handler),
+            }),
+        );
+        Ok(())
+    }
+
+    pub fn get(&self, opcode: Word) -> Option<&CustomOpcode> {
+        self.opcodes.get(&opcode).map(|custom| custom.as_ref())
+    }
+
+    /// Like [`get`](Self::get), but doesn't borrow the registry.
+    pub(super) fn get_shared(&self, opcode: Word) -> Option<Arc<CustomOpcode>> {
+        self.opcodes.get(&opcode).cloned()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.opcodes.is_empty()
+    }
+}
+
+impl<I, O> IntcodeComputer<I, O>
+where
+    I: IntcodeInput,
+    O: IntcodeOutput,
+{
+    /// Equivalent to [`execute`](Self::execute) for an opcode from the
+    /// registry.
+    pub(super) fn execute_custom(&mut self, custom: &CustomOpcode) -> Result<(), IntcodeError> {
+        let eip = self.eip;
+        let instruction = self.prog.get(eip);
+
+        let mut modes = instruction / 100;
+        let mut reads = Vec::with_capacity(custom.params.len());
+        let mut write_addrs = Vec::new();
+        for (i, kind) in custom.params.iter().enumerate() {
+            let param = self.prog.get(eip + 1 + i);
+            let mode = modes % 10;
+            modes /= 10;
+            let addr = match (mode, kind) {
+                (0, _) => param,
+                (1, ParamKind::Read) => (eip + 1 + i) as Word,
+                (1, ParamKind::Write) => {
+                    return Err(IntcodeError::WriteToImmediate { eip, instruction })
+                }
+                (2, _) => self
+                    .relbase
+                    .checked_add(param)
+                    .ok_or(IntcodeError::Overflow { eip, instruction })?,
+                _ => {
+                    return Err(IntcodeError::InvalidParameterMode {
+                        eip,
+                        instruction,
+                        mode,
+                    })
+                }
+            };
+            if addr < 0 {
+                return Err(IntcodeError::NegativeAddress { eip, address: addr });
+            }
+            match kind {
+                ParamKind::Read => reads.push(self.prog.get(addr as usize)),
+                ParamKind::Write => write_addrs.push(addr as usize),
+            }
+        }
+
+        let effect =
+            (custom.handler)(&reads).map_err(|message| IntcodeError::CustomOpcodeFailed {
+                eip,
+                opcode: custom.opcode,
+                message,
+            })?;
+        if effect.writes.len() != write_addrs.len() {
+            return Err(IntcodeError::CustomOpcodeFailed {
+                eip,
+                opcode: custom.opcode,
+                message: "wrong number of values to write",
+            });
+        }
+
+        for (address, value) in write_addrs.into_iter().zip(effect.writes) {
+            self.prog
+                .set(address, value)
+                .map_err(|_| IntcodeError::MemoryLimitExceeded { eip, address })?;
+        }
+        self.eip = match effect.jump {
+            Some(target) if target < 0 => {
+                return Err(IntcodeError::NegativeAddress {
+                    eip,
+                    address: target,
+                })
+            }
+            Some(target) => target as usize,
+            None => eip + 1 + custom.params.len(),
+        };
+        self.trapped = effect.trap;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::Engine;
+    use crate::intcode::StopReason;
+    use ParamKind::{Read, Write};
+
+    fn dialect() -> OpcodeRegistry {
+        let mut registry = OpcodeRegistry::new();
+        registry
+            .register(10, "MOD", &[Read, Read, Write], |args| match args {
+                [_, 0] => Err("division by zero"),
+                [a, b] => Ok(Effect::write(vec![a.rem_euclid(*b)])),
+                _ => unreachable!(),
+            })
+            .unwrap();
+        registry
+            .register(11, "BREAK", &[], |_| Ok(Effect::trap()))
+            .unwrap();
+        registry
+            .register(12, "JMP", &[Read], |args| Ok(Effect::jump(args[0])))
+            .unwrap();
+        registry
+    }
+
+    fn run(program: Vec<Word>, input: Vec<Word>, engine: Engine) -> IntcodeComputer {
+        let mut computer = IntcodeComputer::new(program);
+        computer.opcodes = dialect();
+        computer.engine = engine;
+        computer.input.extend(input);
+        computer
+    }
+
+    #[test]
+    fn runs_custom_opcodes() {
+        // Read a and b, output a mod b, break, then jump over a HALT and
+        // output a mod b again
+        let program = vec![
+            3, 17, 3, 18, 10, 17, 18, 19, 4, 19, 11, 1112, 14, 99, 4, 19, 99, 0, 0, 0,
+        ];
+        for engine in &[Engine::Step, Engine::Cached] {
+            let mut computer = run(program.clone(), vec![-7, 3], *engine);
+            assert_eq!(computer.resume(), StopReason::Trapped { eip: 10 });
+            assert_eq!(computer.output, vec![2]);
+            assert_eq!(computer.resume(), StopReason::Halted);
+            assert_eq!(computer.output, vec![2, 2]);
+        }
+    }
+
+    #[test]
+    fn traces_custom_opcodes() {
+        let mut computer = run(vec![1110, 7, 3, 5, 99, 0], vec![], Engine::Step);
+        computer.trace = Some(Vec::new());
+        assert_eq!(computer.resume(), StopReason::Halted);
+        assert_eq!(
+            computer.trace.as_ref().unwrap()[0].to_json(&computer.opcodes),
+            r#"{"eip":0,"instruction":1110,"opcode":10,"mnemonic":"MOD","operands":[7,3],"write":{"addr":5,"value":1}}"#
+        );
+    }
+
+    #[test]
+    fn reports_errors_from_custom_opcodes() {
+        let mut computer = run(vec![1110, 1, 0, 0, 99], vec![], Engine::Cached);
+        assert_eq!(
+            computer.resume(),
+            StopReason::Error(IntcodeError::CustomOpcodeFailed {
+                eip: 0,
+                opcode: 10,
+                message: "division by zero"
+            })
+        );
+
+        let mut computer = run(vec![11110, 1, 2, 0, 99], vec![], Engine::Cached);
+        assert_eq!(
+            computer.resume(),
+            StopReason::Error(IntcodeError::WriteToImmediate {
+                eip: 0,
+                instruction: 11110
+            })
+        );
+
+        let mut computer = run(vec![13, 99], vec![], Engine::Cached);
+        assert_eq!(
+            computer.resume(),
+            StopReason::Error(IntcodeError::InvalidOpcode { eip: 0, opcode: 13 })
+        );
+
+        for engine in &[Engine::Step, Engine::Cached] {
+            let mut computer = run(vec![104, 1, 14, 0, 99], vec![], *engine);
+            computer
+                .opcodes
+                .register(14, "SKIP", &[Write], |_| Ok(Effect::default()))
+                .unwrap();
+            assert_eq!(
+                computer.resume(),
+                StopReason::Error(IntcodeError::CustomOpcodeFailed {
+                    eip: 2,
+                    opcode: 14,
+                    message: "wrong number of values to write"
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_registrations() {
+        let mut registry = dialect();
+        let mut register = |opcode, params: &[ParamKind]| {
+            registry.register(opcode, "FOO", params, |_| Ok(Effect::default()))
+        };
+        assert_eq!(register(1, &[]), Err(RegistryError::Builtin(1)));
+        assert_eq!(register(99, &[]), Err(RegistryError::OutOfRange(99)));
+        assert_eq!(register(-1, &[]), Err(RegistryError::OutOfRange(-1)));
+        assert_eq!(register(10, &[]), Err(RegistryError::AlreadyRegistered(10)));
+        assert_eq!(
+            register(20, &[Read; 17]),
+            Err(RegistryError::TooManyParams(17))
+        );
+        assert_eq!(register(20, &[Read; 16]), Ok(()));
+    }
+}
@@ -0,0 +1,136 @@
+use super::ascii::AsciiIntcode;
+use super::IntcodeComputer;
+use super::StopReason;
+use std::io::Write;
+
+/// Prefix of the lines in a transcript that were typed by the player.
+const COMMAND_PREFIX: &str = "> ";
+
+/// Runs an ASCII program for a human player, optionally writing a transcript
+/// of everything the program printed and every command it was sent.
+pub struct Player<T> {
+    pub machine: AsciiIntcode,
+    transcript: Option<T>,
+    at_line_start: bool,
+}
+
+/// The commands recorded in a transcript, in order. Lines that don't start
+/// with `> ` are program output and are skipped, so a script to replay can
+/// also be written by hand as a list of `> command` lines.
+pub fn transcript_commands(transcript: &str) -> Vec<String> {
+    transcript
+        .lines()
+        .filter_map(|line| line.strip_prefix(COMMAND_PREFIX))
+        .map(str::to_string)
+        .collect()
+}
+
+impl<T> Player<T>
+where
+    T: Write,
+{
+    pub fn new(computer: IntcodeComputer, transcript: Option<T>) -> Self {
+        Player {
+            machine: AsciiIntcode::new(computer),
+            transcript,
+            at_line_start: true,
+        }
+    }
+
+    /// Runs the program until it stops, and prints its output to `out`.
+    /// Non-ASCII output values are printed as numbers on lines of their own.
+    pub fn run<W: Write>(&mut self, out: &mut W) -> std::io::Result<StopReason> {
+        let stop_reason = loop {
+            match self.machine.computer.resume() {
+                StopReason::OutputReady(_) => {}
+                stop_reason => break stop_reason,
+            }
+        };
+
+        let mut text = self.machine.read_text();
+        for value in self.machine.read_values() {
+            if !text.is_empty() && !text.ends_with('\n') {
+                text.push('\n');
+            }
+            text.push_str(&format!("{}\n", value));
+        }
+        if !text.is_empty() {
+            self.at_line_start = text.ends_with('\n');
+        }
+
+        write!(out, "{}", text)?;
+        out.flush()?;
+        if let Some(transcript) = &mut self.transcript {
+            write!(transcript, "{}", text)?;
+            transcript.flush()?;
+        }
+        Ok(stop_reason)
+    }
+
+    pub fn send_command(&mut self, command: &str) -> std::io::Result<()> {
+        self.machine.send_line(command);
+        if let Some(transcript) = &mut self.transcript {
+            if !self.at_line_start {
+                writeln!(transcript)?;
+            }
+            writeln!(transcript, "{}{}", COMMAND_PREFIX, command)?;
+            transcript.flush()?;
+        }
+        self.at_line_start = true;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::day_input_filename;
+    use crate::common::get_file_lines;
+    use crate::intcode::parse_program;
+
+    #[test]
+    fn replays_transcript() {
+        let computer = IntcodeComputer::new(
+            parse_program(&get_file_lines(&day_input_filename(25)).unwrap()).unwrap(),
+        );
+
+        let mut player = Player::new(computer.clone(), Some(Vec::new()));
+        let mut screen = Vec::new();
+        assert_eq!(player.run(&mut screen).unwrap(), StopReason::NeedsInput);
+        for command in &["north", "south", "inv"] {
+            player.send_command(command).unwrap();
+            assert_eq!(player.run(&mut screen).unwrap(), StopReason::NeedsInput);
+        }
+        let transcript = String::from_utf8(player.transcript.unwrap()).unwrap();
+        let screen = String::from_utf8(screen).unwrap();
+        assert!(transcript.contains("Command?\n> north\n"));
+        assert_eq!(
+            transcript
+                .lines()
+                .filter(|line| !line.starts_with("> "))
+                .collect::<Vec<&str>>(),
+            screen.lines().collect::<Vec<&str>>()
+        );
+        assert_eq!(
+            transcript_commands(&transcript),
+            vec!["north", "south", "inv"]
+        );
+
+        let mut replay = Player::new(computer, Some(Vec::new()));
+        assert_eq!(
+            replay.run(&mut std::io::sink()).unwrap(),
+            StopReason::NeedsInput
+        );
+        for command in transcript_commands(&transcript) {
+            replay.send_command(&command).unwrap();
+            assert_eq!(
+                replay.run(&mut std::io::sink()).unwrap(),
+                StopReason::NeedsInput
+            );
+        }
+        assert_eq!(
+            String::from_utf8(replay.transcript.unwrap()).unwrap(),
+            transcript
+        );
+    }
+}
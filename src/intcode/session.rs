@@ -0,0 +1,330 @@
+use super::IntcodeComputer;
+use super::StopReason;
+use super::Word;
+use super::OP_INPUT;
+use super::OP_OUTPUT;
+use std::fmt;
+use std::str::FromStr;
+
+const HEADER: &str = "intcode-session";
+
+/// The version of the session format written by this version of the crate.
+pub const VERSION: u32 = 1;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum IoKind {
+    Input,
+    Output,
+}
+
+/// A value read or written by the program, after it had executed `step`
+/// instructions.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct IoEvent {
+    pub step: u64,
+    pub kind: IoKind,
+    pub value: Word,
+}
+
+impl fmt::Display for IoEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match self.kind {
+            IoKind::Input => "in",
+            IoKind::Output => "out",
+        };
+        write!(f, "{} {} {}", kind, self.step, self.value)
+    }
+}
+
+/// The input and output of a run, recorded when an [`IntcodeComputer`] has a
+/// `session`.
+///
+/// Sessions are saved as text, one event per line, followed by the number of
+/// instructions executed in total:
+///
+/// ```text
+/// intcode-session 1
+/// in 0 4
+/// in 1 7
+/// out 3 11
+/// steps 4
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Session {
+    pub events: Vec<IoEvent>,
+    pub steps: u64,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn inputs(&self) -> impl Iterator<Item = Word> + '_ {
+        self.events
+            .iter()
+            .filter(|event| event.kind == IoKind::Input)
+            .map(|event| event.value)
+    }
+
+    pub fn outputs(&self) -> impl Iterator<Item = Word> + '_ {
+        self.events
+            .iter()
+            .filter(|event| event.kind == IoKind::Output)
+            .map(|event| event.value)
+    }
+
+    /// Records an executed instruction. `read` is the value of its first
+    /// operand and `written` the value it wrote, if any.
+    pub(super) fn record(&mut self, opcode: Word, read: Option<Word>, written: Option<Word>) {
+        let event = match (opcode, read, written) {
+            (OP_INPUT, _, Some(value)) => Some((IoKind::Input, value)),
+            (OP_OUTPUT, Some(value), _) => Some((IoKind::Output, value)),
+            _ => None,
+        };
+        if let Some((kind, value)) = event {
+            self.events.push(IoEvent {
+                step: self.steps,
+                kind,
+                value,
+            });
+        }
+        self.steps += 1;
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SessionError {
+    NotASession,
+    UnsupportedVersion(String),
+    InvalidLine { line: usize, text: String },
+    MissingSteps,
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NotASession => write!(f, "Not an Intcode session"),
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "Unsupported session version {} (expected {})",
+                version, VERSION
+            ),
+            Self::InvalidLine { line, text } => write!(f, "line {}: Invalid line: {}", line, text),
+            Self::MissingSteps => write!(f, "Missing step count"),
+        }
+    }
+}
+
+impl std::error::Error for SessionError {}
+
+impl fmt::Display for Session {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} {}", HEADER, VERSION)?;
+        for event in &self.events {
+            writeln!(f, "{}", event)?;
+        }
+        writeln!(f, "steps {}", self.steps)
+    }
+}
+
+impl FromStr for Session {
+    type Err = SessionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty());
+
+        let (_, header) = lines.next().ok_or(SessionError::NotASession)?;
+        match header.trim().split_once(' ') {
+            Some((HEADER, version)) if version == VERSION.to_string() => {}
+            Some((HEADER, version)) => {
+                return Err(SessionError::UnsupportedVersion(version.to_string()))
+            }
+            _ => return Err(SessionError::NotASession),
+        }
+
+        let mut session = Session::new();
+        let mut steps = None;
+        for (i, line) in lines {
+            let invalid = || SessionError::InvalidLine {
+                line: i + 1,
+                text: line.to_string(),
+            };
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words.as_slice() {
+                ["steps", count] if steps.is_none() => {
+                    steps = Some(count.parse().map_err(|_| invalid())?);
+                }
+                [kind @ "in", step, value] | [kind @ "out", step, value] if steps.is_none() => {
+                    session.events.push(IoEvent {
+                        step: step.parse().map_err(|_| invalid())?,
+                        kind: if *kind == "in" {
+                            IoKind::Input
+                        } else {
+                            IoKind::Output
+                        },
+                        value: value.parse().map_err(|_| invalid())?,
+                    });
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        session.steps = steps.ok_or(SessionError::MissingSteps)?;
+        Ok(session)
+    }
+}
+
+/// The first difference between a recorded session and a replay.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Divergence {
+    /// Event number `index` differs, or only one of the runs has it.
+    Event {
+        index: usize,
+        expected: Option<IoEvent>,
+        actual: Option<IoEvent>,
+    },
+    /// The events match, but the replay executed a different number of
+    /// instructions.
+    Steps { expected: u64, actual: u64 },
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let describe = |event: &Option<IoEvent>| match event {
+            Some(event) => event.to_string(),
+            None => "nothing".to_string(),
+        };
+        match self {
+            Self::Event {
+                index,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Event {} differs: expected {}, got {}",
+                index,
+                describe(expected),
+                describe(actual)
+            ),
+            Self::Steps { expected, actual } => {
+                write!(f, "Run ended after {} steps, expected {}", actual, expected)
+            }
+        }
+    }
+}
+
+impl IntcodeComputer {
+    /// Runs the computer with the inputs of a recorded session, and returns
+    /// the new recording if it matches. The replay never executes more
+    /// instructions than the recording, so a program that has been changed to
+    /// loop forever is still caught.
+    pub fn replay(mut self, recorded: &Session) -> Result<Session, Divergence> {
+        self.input.clear();
+        self.input.extend(recorded.inputs());
+        self.session = Some(Session::new());
+        self.pause_on_output = None;
+        self.step_limit = None;
+        self.step_budget = Some(recorded.steps as usize);
+        while let StopReason::Trapped { .. } = self.resume() {}
+
+        let replayed = self.session.take().unwrap();
+        let index = recorded
+            .events
+            .iter()
+            .zip(&replayed.events)
+            .position(|(expected, actual)| expected != actual)
+            .unwrap_or_else(|| recorded.events.len().min(replayed.events.len()));
+        let (expected, actual) = (
+            recorded.events.get(index).copied(),
+            replayed.events.get(index).copied(),
+        );
+        if expected.is_some() || actual.is_some() {
+            Err(Divergence::Event {
+                index,
+                expected,
+                actual,
+            })
+        } else if recorded.steps != replayed.steps {
+            Err(Divergence::Steps {
+                expected: recorded.steps,
+                actual: replayed.steps,
+            })
+        } else {
+            Ok(replayed)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::day_input_filename;
+    use crate::common::get_file_lines;
+    use crate::intcode::ascii::AsciiIntcode;
+    use crate::intcode::parse_program;
+
+    #[test]
+    fn records_io_with_step_counts() {
+        let mut computer = IntcodeComputer::new(vec![3, 11, 3, 12, 1, 11, 12, 11, 4, 11, 99, 0, 0]);
+        computer.session = Some(Session::new());
+        assert_eq!(computer.run_mut(Some(4)), StopReason::NeedsInput);
+        assert_eq!(computer.run_mut(Some(7)), StopReason::Halted);
+
+        let session = computer.session.unwrap();
+        assert_eq!(
+            session.to_string(),
+            "intcode-session 1\nin 0 4\nin 1 7\nout 3 11\nsteps 4\n"
+        );
+        assert_eq!(session.to_string().parse(), Ok(session));
+        assert_eq!(
+            "intcode-session 1\nout 3 11\nsteps 4\nin 4 1".parse::<Session>(),
+            Err(SessionError::InvalidLine {
+                line: 4,
+                text: "in 4 1".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn replays_day25_session() {
+        let program = IntcodeComputer::new(
+            parse_program(&get_file_lines(&day_input_filename(25)).unwrap()).unwrap(),
+        );
+        let mut machine = AsciiIntcode::new(program.clone());
+        machine.computer.session = Some(Session::new());
+        for command in &["north", "south", "inv"] {
+            machine.read_until_prompt().unwrap();
+            machine.send_line(command);
+        }
+        machine.read_until_prompt().unwrap();
+        let recorded = machine.computer.session.unwrap();
+
+        assert_eq!(program.clone().replay(&recorded), Ok(recorded.clone()));
+
+        let mut changed = recorded.clone();
+        let first_input = changed
+            .events
+            .iter()
+            .position(|event| event.kind == IoKind::Input)
+            .unwrap();
+        changed.events[first_input].value = 's' as Word;
+        let divergence = program.clone().replay(&changed).unwrap_err();
+        match divergence {
+            Divergence::Event { index, .. } => assert!(index > first_input),
+            _ => panic!("Unexpected divergence: {:?}", divergence),
+        }
+
+        let mut longer = recorded.clone();
+        longer.steps += 1;
+        assert_eq!(
+            program.replay(&longer),
+            Err(Divergence::Steps {
+                expected: recorded.steps + 1,
+                actual: recorded.steps
+            })
+        );
+    }
+}
@@ -0,0 +1,301 @@
+use super::memory::Memory;
+use super::IntcodeComputer;
+use super::Word;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+const HEADER: &str = "intcode-snapshot";
+
+/// The version of the snapshot format written by this version of the crate.
+/// Bump it whenever the format changes in a way older readers can't handle.
+pub const VERSION: u32 = 1;
+
+/// The complete state of an [`IntcodeComputer`], apart from configuration
+/// like tracing that doesn't affect how the program runs.
+///
+/// Snapshots are saved as text, one field per line:
+///
+/// ```text
+/// intcode-snapshot 1
+/// eip 2
+/// relbase 0
+/// limit 1000000
+/// memory 3,5,99,0,0,7
+/// sparse 1000000000000:42
+/// input 1,2
+/// output
+/// ```
+///
+/// `limit`, `sparse`, `input` and `output` may be omitted.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Snapshot {
+    pub eip: usize,
+    pub relbase: Word,
+    pub memory: Memory,
+    pub input: Vec<Word>,
+    pub output: Vec<Word>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SnapshotError {
+    NotASnapshot,
+    UnsupportedVersion(String),
+    InvalidLine {
+        line: usize,
+        text: String,
+    },
+    DuplicateField(String),
+    MissingField(&'static str),
+    /// A sparse memory address is within the dense part of memory.
+    OverlappingMemory,
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NotASnapshot => write!(f, "Not an Intcode snapshot"),
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "Unsupported snapshot version {} (expected {})",
+                version, VERSION
+            ),
+            Self::InvalidLine { line, text } => write!(f, "line {}: Invalid line: {}", line, text),
+            Self::DuplicateField(field) => write!(f, "Duplicate field: {}", field),
+            Self::MissingField(field) => write!(f, "Missing field: {}", field),
+            Self::OverlappingMemory => write!(f, "Sparse memory overlaps dense memory"),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+fn join<T: ToString>(values: impl Iterator<Item = T>) -> String {
+    values
+        .map(|v| v.to_string())
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+fn parse_list<T: FromStr>(value: &str) -> Option<Vec<T>> {
+    if value.is_empty() {
+        Some(Vec::new())
+    } else {
+        value.split(',').map(|s| s.trim().parse().ok()).collect()
+    }
+}
+
+fn parse_sparse(value: &str) -> Option<BTreeMap<usize, Word>> {
+    parse_list::<String>(value)?
+        .iter()
+        .map(|entry| {
+            let (addr, value) = entry.split_once(':')?;
+            Some((addr.parse().ok()?, value.parse().ok()?))
+        })
+        .collect()
+}
+
+fn parse_field<T>(
+    fields: &mut HashMap<&str, (usize, &str)>,
+    key: &str,
+    parse: impl Fn(&str) -> Option<T>,
+) -> Result<Option<T>, SnapshotError> {
+    match fields.remove(key) {
+        Some((line, value)) => parse(value)
+            .map(Some)
+            .ok_or_else(|| SnapshotError::InvalidLine {
+                line,
+                text: value.to_string(),
+            }),
+        None => Ok(None),
+    }
+}
+
+fn write_field(f: &mut fmt::Formatter, key: &str, value: String) -> fmt::Result {
+    if value.is_empty() {
+        writeln!(f, "{}", key)
+    } else {
+        writeln!(f, "{} {}", key, value)
+    }
+}
+
+impl fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} {}", HEADER, VERSION)?;
+        write_field(f, "eip", self.eip.to_string())?;
+        write_field(f, "relbase", self.relbase.to_string())?;
+        if let Some(limit) = self.memory.limit {
+            write_field(f, "limit", limit.to_string())?;
+        }
+        write_field(f, "memory", join(self.memory.as_slice().iter()))?;
+        write_field(
+            f,
+            "sparse",
+            join(
+                self.memory
+                    .sparse_entries()
+                    .map(|(addr, value)| format!("{}:{}", addr, value)),
+            ),
+        )?;
+        write_field(f, "input", join(self.input.iter()))?;
+        write_field(f, "output", join(self.output.iter()))
+    }
+}
+
+impl FromStr for Snapshot {
+    type Err = SnapshotError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty());
+
+        let (_, header) = lines.next().ok_or(SnapshotError::NotASnapshot)?;
+        match header.trim().split_once(' ') {
+            Some((HEADER, version)) if version == VERSION.to_string() => {}
+            Some((HEADER, version)) => {
+                return Err(SnapshotError::UnsupportedVersion(version.to_string()))
+            }
+            _ => return Err(SnapshotError::NotASnapshot),
+        }
+
+        let mut fields: HashMap<&str, (usize, &str)> = HashMap::new();
+        for (i, line) in lines {
+            let line = line.trim();
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            if fields.insert(key, (i + 1, value.trim())).is_some() {
+                return Err(SnapshotError::DuplicateField(key.to_string()));
+            }
+        }
+
+        let eip = parse_field(&mut fields, "eip", |v| v.parse().ok())?
+            .ok_or(SnapshotError::MissingField("eip"))?;
+        let relbase = parse_field(&mut fields, "relbase", |v| v.parse().ok())?
+            .ok_or(SnapshotError::MissingField("relbase"))?;
+        let limit = parse_field(&mut fields, "limit", |v| v.parse().ok())?;
+        let memory = parse_field(&mut fields, "memory", parse_list)?
+            .ok_or(SnapshotError::MissingField("memory"))?;
+        let sparse = parse_field(&mut fields, "sparse", parse_sparse)?;
+        let input = parse_field(&mut fields, "input", parse_list)?;
+        let output = parse_field(&mut fields, "output", parse_list)?;
+        if let Some((key, (line, value))) = fields.into_iter().min_by_key(|(_, (line, _))| *line) {
+            return Err(SnapshotError::InvalidLine {
+                line,
+                text: format!("{} {}", key, value).trim().to_string(),
+            });
+        }
+
+        Ok(Snapshot {
+            eip,
+            relbase,
+            memory: Memory::from_parts(memory, sparse.unwrap_or_default(), limit)
+                .ok_or(SnapshotError::OverlappingMemory)?,
+            input: input.unwrap_or_default(),
+            output: output.unwrap_or_default(),
+        })
+    }
+}
+
+impl IntcodeComputer {
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            eip: self.eip,
+            relbase: self.relbase,
+            memory: self.prog.clone(),
+            input: self.input.iter().copied().collect(),
+            output: self.output.iter().copied().collect(),
+        }
+    }
+
+    pub fn restore(snapshot: Snapshot) -> IntcodeComputer {
+        let mut computer = Self::with_io(Vec::new(), snapshot.input.into(), snapshot.output.into());
+        computer.eip = snapshot.eip;
+        computer.relbase = snapshot.relbase;
+        computer.prog = snapshot.memory;
+        computer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::day_input_filename;
+    use crate::common::get_file_lines;
+    use crate::intcode::parse_program;
+    use crate::intcode::StopReason;
+
+    #[test]
+    fn formats_snapshot() {
+        let mut computer = IntcodeComputer::new(vec![109, 3, 203, 0, 4, 1_000_000, 99]);
+        computer.prog.limit = Some(100);
+        computer.prog[1_000_000] = 42;
+        computer.pause_on_output = Some(1);
+        assert_eq!(computer.run_mut(vec![7, 8]), StopReason::OutputReady(1));
+        assert_eq!(
+            computer.snapshot().to_string(),
+            "intcode-snapshot 1
+eip 6
+relbase 3
+limit 100
+memory 109,3,203,7,4,1000000,99
+sparse 1000000:42
+input 8
+output 42
+"
+        );
+    }
+
+    #[test]
+    fn resumes_from_restored_snapshot() {
+        let program = parse_program(&get_file_lines(&day_input_filename(25)).unwrap()).unwrap();
+        let command = |s: &str| s.bytes().map(Word::from).collect::<Vec<Word>>();
+
+        let mut expected = IntcodeComputer::new(program.clone());
+        let _ = expected.run_mut(command("north\n"));
+        let _ = expected.run_mut(command("south\n"));
+
+        let mut computer = IntcodeComputer::new(program);
+        let _ = computer.run_mut(command("north\n"));
+        let snapshot: Snapshot = computer.snapshot().to_string().parse().unwrap();
+        assert_eq!(snapshot, computer.snapshot());
+
+        let mut restored = IntcodeComputer::restore(snapshot);
+        let _ = restored.run_mut(command("south\n"));
+        assert_eq!(restored.output, expected.output);
+        assert_eq!(restored.snapshot(), expected.snapshot());
+    }
+
+    #[test]
+    fn rejects_invalid_snapshots() {
+        let parse = |s: &str| s.parse::<Snapshot>().unwrap_err();
+        assert_eq!(parse("1,2,3"), SnapshotError::NotASnapshot);
+        assert_eq!(
+            parse("intcode-snapshot 2\neip 0"),
+            SnapshotError::UnsupportedVersion("2".to_string())
+        );
+        assert_eq!(
+            parse("intcode-snapshot 1\neip 0\nmemory 99"),
+            SnapshotError::MissingField("relbase")
+        );
+        assert_eq!(
+            parse("intcode-snapshot 1\neip 0\nrelbase 0\nmemory 99,x"),
+            SnapshotError::InvalidLine {
+                line: 4,
+                text: "99,x".to_string()
+            }
+        );
+        assert_eq!(
+            parse("intcode-snapshot 1\neip 0\nrelbase 0\nmemory 99\nfoo 1\nbar 2\nbaz 3"),
+            SnapshotError::InvalidLine {
+                line: 5,
+                text: "foo 1".to_string()
+            }
+        );
+        assert_eq!(
+            parse("intcode-snapshot 1\neip 0\nrelbase 0\nmemory 99\nsparse 0:1"),
+            SnapshotError::OverlappingMemory
+        );
+    }
+}
@@ -0,0 +1,198 @@
+use super::io::IntcodeOutput;
+use super::IntcodeComputer;
+use super::StopReason;
+use super::Word;
+use std::collections::VecDeque;
+use std::sync::mpsc::channel;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::Sender;
+use std::thread::JoinHandle;
+
+/// Sends each output value to every connected machine.
+struct Fanout(Vec<Sender<Word>>);
+
+impl IntcodeOutput for Fanout {
+    fn write(&mut self, value: Word) {
+        for sender in &self.0 {
+            sender.send(value).ok();
+        }
+    }
+}
+
+/// Computers connected by channels, each running on its own thread once
+/// spawned.
+///
+/// A machine stops when it halts, fails, or needs input after every machine
+/// and [`input`](Self::input) sender that could send it more has gone away. So
+/// when a machine stops for any reason, the machines downstream of it stop as
+/// soon as they have consumed everything it sent.
+///
+/// Nothing detects a deadlock: if every machine in a feedback loop waits for
+/// input, none of them ever stops and [`PipelineHandle::join`] blocks forever.
+/// Use a [`Network`](super::network::Network) to run programs that might do
+/// that, since it returns `NetworkStop::Deadlock` instead.
+#[derive(Default)]
+pub struct ThreadedPipeline {
+    machines: Vec<(IntcodeComputer, Receiver<Word>)>,
+    inputs: Vec<Sender<Word>>,
+    outputs: Vec<Vec<Sender<Word>>>,
+}
+
+/// The state of a machine after its thread has finished. Input it didn't read
+/// is left in `computer.input`.
+pub struct FinishedMachine {
+    pub computer: IntcodeComputer,
+    pub stop_reason: StopReason,
+}
+
+pub struct PipelineHandle {
+    threads: Vec<JoinHandle<FinishedMachine>>,
+}
+
+impl ThreadedPipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Machines that each send their output to the next one. With `feedback`,
+    /// the last machine also sends its output to the first one.
+    pub fn chain(computers: Vec<IntcodeComputer>, feedback: bool) -> Self {
+        let mut pipeline = Self::new();
+        let count = computers.len();
+        for computer in computers {
+            pipeline.add(computer);
+        }
+        for i in 1..count {
+            pipeline.connect(i - 1, i);
+        }
+        if feedback && count > 0 {
+            pipeline.connect(count - 1, 0);
+        }
+        pipeline
+    }
+
+    /// Adds a machine and returns its index. Input already queued in the
+    /// computer is read before anything sent to it through the pipeline.
+    pub fn add(&mut self, computer: IntcodeComputer) -> usize {
+        let (sender, receiver) = channel();
+        for value in &computer.input {
+            sender.send(*value).ok();
+        }
+        self.machines.push((computer, receiver));
+        self.inputs.push(sender);
+        self.outputs.push(Vec::new());
+        self.machines.len() - 1
+    }
+
+    pub fn connect(&mut self, from: usize, to: usize) {
+        let sender = self.inputs[to].clone();
+        self.outputs[from].push(sender);
+    }
+
+    /// A sender for feeding input to machine `to` from outside the pipeline.
+    pub fn input(&mut self, to: usize) -> Sender<Word> {
+        self.inputs[to].clone()
+    }
+
+    /// A receiver for the output of machine `from`.
+    pub fn output(&mut self, from: usize) -> Receiver<Word> {
+        let (sender, receiver) = channel();
+        self.outputs[from].push(sender);
+        receiver
+    }
+
+    pub fn spawn(self) -> PipelineHandle {
+        let threads = self
+            .machines
+            .into_iter()
+            .zip(self.outputs)
+            .map(|((computer, receiver), outputs)| {
+                std::thread::spawn(move || run(computer, receiver, Fanout(outputs)))
+            })
+            .collect();
+        PipelineHandle { threads }
+    }
+}
+
+fn run(computer: IntcodeComputer, input: Receiver<Word>, output: Fanout) -> FinishedMachine {
+    let pause_on_output = computer.pause_on_output;
+    let (mut machine, _, _) = computer.replace_io(input, output);
+    machine.pause_on_output = None;
+    let stop_reason = machine.resume();
+
+    let (mut computer, input, _) = machine.replace_io(VecDeque::new(), VecDeque::new());
+    computer.input = input.try_iter().collect();
+    computer.pause_on_output = pause_on_output;
+    FinishedMachine {
+        computer,
+        stop_reason,
+    }
+}
+
+impl PipelineHandle {
+    /// Waits for all machines to stop, and returns them in the order they were
+    /// added. Panics if a machine's thread panicked. Never returns if the
+    /// machines are deadlocked; see [`ThreadedPipeline`].
+    pub fn join(self) -> Vec<FinishedMachine> {
+        self.threads
+            .into_iter()
+            .map(|thread| {
+                thread
+                    .join()
+                    .unwrap_or_else(|err| std::panic::resume_unwind(err))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::IntcodeError;
+
+    fn amplifiers(program: &[Word], phases: &[Word]) -> Vec<IntcodeComputer> {
+        phases
+            .iter()
+            .map(|phase| {
+                let mut machine = IntcodeComputer::new(program.to_vec());
+                machine.input.push_back(*phase);
+                machine
+            })
+            .collect()
+    }
+
+    #[test]
+    fn runs_feedback_loop() {
+        let program = vec![
+            3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1,
+            28, 1005, 28, 6, 99, 0, 0, 5,
+        ];
+        let mut pipeline = ThreadedPipeline::chain(amplifiers(&program, &[9, 8, 7, 6, 5]), true);
+        pipeline.input(0).send(0).unwrap();
+        let output = pipeline.output(4);
+        let finished = pipeline.spawn().join();
+
+        assert!(finished
+            .iter()
+            .all(|machine| machine.stop_reason == StopReason::Halted));
+        assert_eq!(output.try_iter().last(), Some(139629729));
+    }
+
+    #[test]
+    fn stops_machines_downstream_of_an_error() {
+        let mut machines = amplifiers(&[3, 0, 4, 0, 3, 0, 4, 0, 3, 0, 99], &[1, 2, 3]);
+        machines[0] = IntcodeComputer::new(vec![3, 0, 4, 0, 99]);
+        machines[0].input.push_back(1);
+        machines[1] = IntcodeComputer::new(vec![3, 0, 4, 0, 42]);
+        let pipeline = ThreadedPipeline::chain(machines, false);
+        let finished = pipeline.spawn().join();
+
+        assert_eq!(finished[0].stop_reason, StopReason::Halted);
+        assert_eq!(
+            finished[1].stop_reason,
+            StopReason::Error(IntcodeError::InvalidOpcode { eip: 4, opcode: 42 })
+        );
+        assert_eq!(finished[2].stop_reason, StopReason::NeedsInput);
+        assert_eq!(finished[2].computer.prog[0], 1);
+    }
+}
@@ -0,0 +1,199 @@
+use super::memory::Memory;
+use super::opcode_info;
+use super::opcodes::OpcodeRegistry;
+use super::ParamKind;
+use super::Word;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TraceEntry {
+    pub eip: usize,
+    pub instruction: Word,
+    /// The values of the input parameters after resolving parameter modes.
+    pub operands: Vec<Word>,
+    /// The address and value written by the instruction, if any.
+    pub write: Option<(usize, Word)>,
+}
+
+impl TraceEntry {
+    pub fn opcode(&self) -> Word {
+        self.instruction % 100
+    }
+
+    /// Formats the entry as JSON, with the mnemonics of custom opcodes from
+    /// `opcodes`.
+    pub fn to_json(&self, opcodes: &OpcodeRegistry) -> String {
+        format!(
+            r#"{{"eip":{},"instruction":{},"opcode":{},"mnemonic":"{}","operands":[{}],"write":{}}}"#,
+            self.eip,
+            self.instruction,
+            self.opcode(),
+            opcode_info(self.opcode())
+                .map(|info| info.mnemonic)
+                .or_else(|| opcodes
+                    .get(self.opcode())
+                    .map(|custom| custom.mnemonic.as_str()))
+                .unwrap_or("?"),
+            self.operands
+                .iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<String>>()
+                .join(","),
+            match self.write {
+                Some((addr, value)) => format!(r#"{{"addr":{},"value":{}}}"#, addr, value),
+                None => "null".to_string(),
+            }
+        )
+    }
+}
+
+/// Resolves the operands of the instruction at `eip` without modifying
+/// memory. Returns the input values and the output address, if any.
+pub(super) fn resolve_operands(
+    prog: &Memory,
+    opcodes: &OpcodeRegistry,
+    eip: usize,
+    relbase: Word,
+) -> (Vec<Word>, Option<usize>) {
+    let read = |addr: Word| {
+        if addr >= 0 {
+            prog.get(addr as usize)
+        } else {
+            0
+        }
+    };
+    let instruction = read(eip as Word);
+
+    let mut operands = Vec::new();
+    let mut write_addr = None;
+    let params = opcode_info(instruction % 100)
+        .map(|info| info.params)
+        .or_else(|| {
+            opcodes
+                .get(instruction % 100)
+                .map(|custom| custom.params.as_slice())
+        });
+    if let Some(params) = params {
+        let mut modes = instruction / 100;
+        for (i, kind) in params.iter().enumerate() {
+            let param = read((eip + 1 + i) as Word);
+            let addr = match modes % 10 {
+                2 => relbase.saturating_add(param),
+                _ => param,
+            };
+            match kind {
+                ParamKind::Read => operands.push(if modes % 10 == 1 { param } else { read(addr) }),
+                ParamKind::Write if addr >= 0 => write_addr = Some(addr as usize),
+                ParamKind::Write => {}
+            }
+            modes /= 10;
+        }
+    }
+    (operands, write_addr)
+}
+
+/// Counts of executed instructions per address and per opcode.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Profile {
+    pub by_address: HashMap<usize, u64>,
+    pub by_opcode: BTreeMap<Word, u64>,
+}
+
+impl Profile {
+    pub fn record(&mut self, eip: usize, opcode: Word) {
+        *self.by_address.entry(eip).or_insert(0) += 1;
+        *self.by_opcode.entry(opcode).or_insert(0) += 1;
+    }
+
+    pub fn total(&self) -> u64 {
+        self.by_opcode.values().sum()
+    }
+
+    /// The `n` most executed addresses, most executed first.
+    pub fn hottest_addresses(&self, n: usize) -> Vec<(usize, u64)> {
+        let mut counts: Vec<(usize, u64)> = self
+            .by_address
+            .iter()
+            .map(|(addr, count)| (*addr, *count))
+            .collect();
+        counts.sort_by(|(a1, c1), (a2, c2)| c2.cmp(c1).then(a1.cmp(a2)));
+        counts.truncate(n);
+        counts
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let total = self.total();
+        let percent = |count: u64| 100.0 * count as f64 / total.max(1) as f64;
+
+        writeln!(f, "{} instructions executed", total)?;
+        writeln!(f)?;
+        writeln!(f, "By opcode:")?;
+        for (opcode, count) in &self.by_opcode {
+            writeln!(
+                f,
+                "  {:<4} {:>12} {:>6.2}%",
+                opcode_info(*opcode)
+                    .map(|info| info.mnemonic)
+                    .unwrap_or("?"),
+                count,
+                percent(*count)
+            )?;
+        }
+        writeln!(f)?;
+        writeln!(f, "Hottest addresses:")?;
+        for (addr, count) in self.hottest_addresses(20) {
+            writeln!(f, "  {:04} {:>12} {:>6.2}%", addr, count, percent(count))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intcode::opcodes::OpcodeRegistry;
+    use crate::intcode::IntcodeComputer;
+    use crate::intcode::StopReason;
+
+    #[test]
+    fn traces_resolved_operands_and_writes() {
+        let mut computer = IntcodeComputer::new(vec![109, 3, 3, 9, 22201, 0, 6, 0, 99, 0]);
+        computer.trace = Some(Vec::new());
+        assert_eq!(computer.run_mut(vec![4]), StopReason::Halted);
+
+        let trace: Vec<String> = computer
+            .trace
+            .unwrap()
+            .iter()
+            .map(|entry| entry.to_json(&OpcodeRegistry::new()))
+            .collect();
+        assert_eq!(
+            trace,
+            vec![
+                r#"{"eip":0,"instruction":109,"opcode":9,"mnemonic":"ARB","operands":[3],"write":null}"#,
+                r#"{"eip":2,"instruction":3,"opcode":3,"mnemonic":"IN","operands":[],"write":{"addr":9,"value":4}}"#,
+                r#"{"eip":4,"instruction":22201,"opcode":1,"mnemonic":"ADD","operands":[9,4],"write":{"addr":3,"value":13}}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn profiles_instruction_counts() {
+        let mut computer = IntcodeComputer::new(vec![
+            1101, 0, 3, 13, 1001, 13, -1, 13, 1005, 13, 4, 99, 0, 0,
+        ]);
+        computer.profile = Some(Default::default());
+        assert_eq!(computer.run_mut(None), StopReason::Halted);
+
+        let profile = computer.profile.unwrap();
+        assert_eq!(profile.total(), 7);
+        assert_eq!(profile.hottest_addresses(2), vec![(4, 3), (8, 3)]);
+        assert_eq!(
+            profile.by_opcode.into_iter().collect::<Vec<_>>(),
+            vec![(1, 4), (5, 3)]
+        );
+    }
+}
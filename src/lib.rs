@@ -1,4 +1,6 @@
 pub mod common;
 pub mod days;
 pub mod intcode;
+pub mod report;
+pub mod solver;
 pub mod util;
@@ -1,23 +1,49 @@
+use std::collections::VecDeque;
+use std::io::Write;
 use std::path::Path;
+use std::time::Duration;
 
 use clap::App;
 use clap::Arg;
+use clap::ArgMatches;
+use clap::SubCommand;
 
-mod common;
 mod crate_info;
-mod days;
-mod intcode;
-mod util;
 
-use crate::common::day_input_filename;
-use crate::common::get_file_lines;
+use adventofcode_2019::common::day_input_filename;
+use adventofcode_2019::common::get_file_lines;
+use adventofcode_2019::common::parse_at;
+use adventofcode_2019::common::split_columns;
+use adventofcode_2019::common::AocError;
+use adventofcode_2019::common::AocErrorKind;
+use adventofcode_2019::days;
+use adventofcode_2019::intcode::asm;
+use adventofcode_2019::intcode::cfg::ControlFlowGraph;
+use adventofcode_2019::intcode::debug::Debugger;
+use adventofcode_2019::intcode::disasm;
+use adventofcode_2019::intcode::fuzz;
+use adventofcode_2019::intcode::parse_program;
+use adventofcode_2019::intcode::play;
+use adventofcode_2019::intcode::play::Player;
+use adventofcode_2019::intcode::session::Session;
+use adventofcode_2019::intcode::snapshot::Snapshot;
+use adventofcode_2019::intcode::IntcodeComputer;
+use adventofcode_2019::intcode::StopReason;
+use adventofcode_2019::report::DayReport;
+use adventofcode_2019::report::Reporter;
+use adventofcode_2019::solver::DynSolver;
+use adventofcode_2019::solver::Part;
+use adventofcode_2019::solver::Registry;
+use adventofcode_2019::util::line_editor::LineEditor;
+use adventofcode_2019::util::pool;
+
 use crate::crate_info::crate_author;
 use crate::crate_info::crate_description;
 use crate::crate_info::crate_name;
 use crate::crate_info::crate_version;
-use crate::intcode::IntcodeComputer;
 
 fn main() -> Result<(), std::io::Error> {
+    let default_year = days::YEAR.to_string();
     let cli = App::new(crate_name())
         .version(crate_version())
         .about(crate_description())
@@ -25,96 +51,620 @@ fn main() -> Result<(), std::io::Error> {
         .arg(
             Arg::with_name("day")
                 .takes_value(true)
-                .help(r#"Day number (1 - 25) to run, or "intcode" to parse and run an Intcode program. If omitted, all days are run."#)
+                .help(r#"Day number (1 - 25) to run. If omitted, all days are run."#)
         )
         .arg(
             Arg::with_name("input-file")
                 .takes_value(true)
-                .help(r#"When <day> is not "intcode": Path to a file containing input for the chosen day. Use "-" for standard input; omit to use "./inputs/day<day>.in". When <day> is "intcode": Path to a file containing the Intcode program to run. Use "-" or omit for standard input. Input is read from the first line of standard input in the same format as an Intcode program; when program is also read from standard input, the program is read from the first line and the input from the second."#)
+                .help(r#"Path to a file containing input for the chosen day. Use "-" for standard input; omit to use "./inputs/day<day>.in"."#)
+        )
+        .arg(
+            Arg::with_name("year")
+                .long("year")
+                .takes_value(true)
+                .value_name("YEAR")
+                .default_value(&default_year)
+                .validator(|value| match value.parse::<u16>() {
+                    Ok(_) => Ok(()),
+                    Err(_) => Err(format!("Invalid year: {}", value)),
+                })
+                .help("Event year of the puzzles to run")
+        )
+        .arg(
+            Arg::with_name("part")
+                .long("part")
+                .takes_value(true)
+                .value_name("a|b")
+                .validator(|value| value.parse::<Part>().map(|_| ()))
+                .help("Solve only part A or part B")
+        )
+        .arg(
+            Arg::with_name("variant")
+                .long("variant")
+                .takes_value(true)
+                .value_name("NAME")
+                .help("Use the alternative solution NAME for days that have one")
+        )
+        .arg(
+            Arg::with_name("time")
+                .long("time")
+                .help("Report the time spent parsing the input and solving each part")
+        )
+        .arg(
+            Arg::with_name("format")
+                .long("format")
+                .takes_value(true)
+                .value_name("FORMAT")
+                .possible_values(&["text", "json", "csv"])
+                .default_value("text")
+                .help("Print the answers as text, JSON or CSV")
+        )
+        .arg(
+            Arg::with_name("jobs")
+                .long("jobs")
+                .short("j")
+                .takes_value(true)
+                .value_name("N")
+                .validator(|value| match value.parse::<usize>() {
+                    Ok(jobs) if jobs > 0 => Ok(()),
+                    _ => Err(format!("Invalid number of jobs: {}", value)),
+                })
+                .conflicts_with("day")
+                .help("Solve up to N days at the same time when running all days")
+        )
+        .subcommand(
+            SubCommand::with_name("intcode")
+                .about("Parse and run an Intcode program")
+                .args(&run_args())
+                .subcommand(
+                    SubCommand::with_name("run")
+                        .about("Run an Intcode program (the default)")
+                        .args(&run_args())
+                )
+                .subcommand(
+                    SubCommand::with_name("asm")
+                        .about("Assemble an Intcode program from mnemonics")
+                        .arg(
+                            Arg::with_name("source-file")
+                                .takes_value(true)
+                                .help(r#"Path to a file containing the assembly source. Use "-" or omit for standard input."#)
+                        )
+                )
+                .subcommand(
+                    SubCommand::with_name("debug")
+                        .about("Step through an Intcode program interactively")
+                        .arg(
+                            Arg::with_name("program-file")
+                                .takes_value(true)
+                                .required(true)
+                                .help(r#"Path to a file containing the Intcode program to debug. Debugger commands are read from standard input."#)
+                        )
+                )
+                .subcommand(
+                    SubCommand::with_name("play")
+                        .about("Run an ASCII Intcode program interactively")
+                        .arg(
+                            Arg::with_name("program-file")
+                                .takes_value(true)
+                                .required(true)
+                                .help(r#"Path to a file containing the Intcode program to run. Commands are read from the terminal with line editing and history."#)
+                        )
+                        .arg(
+                            Arg::with_name("transcript")
+                                .long("transcript")
+                                .takes_value(true)
+                                .value_name("FILE")
+                                .help("Write the program's output and the commands sent to it to FILE")
+                        )
+                        .arg(
+                            Arg::with_name("replay")
+                                .long("replay")
+                                .takes_value(true)
+                                .value_name("FILE")
+                                .help(r#"Send the commands recorded in the transcript FILE before reading from the terminal. Commands are the lines starting with "> "."#)
+                        )
+                        .arg(record_arg())
+                )
+                .subcommand(
+                    SubCommand::with_name("replay")
+                        .about("Check that an Intcode program still behaves as in a session recorded with --record")
+                        .arg(
+                            Arg::with_name("program-file")
+                                .takes_value(true)
+                                .required(true)
+                                .help("Path to a file containing the Intcode program to run")
+                        )
+                        .arg(
+                            Arg::with_name("session-file")
+                                .takes_value(true)
+                                .required(true)
+                                .help("Path to the recorded session")
+                        )
+                )
+                .subcommand(
+                    SubCommand::with_name("disasm")
+                        .about("Disassemble an Intcode program")
+                        .arg(
+                            Arg::with_name("program-file")
+                                .takes_value(true)
+                                .help(r#"Path to a file containing the Intcode program to disassemble. Use "-" or omit for standard input."#)
+                        )
+                )
+                .subcommand(
+                    SubCommand::with_name("cfg")
+                        .about("Print the control flow graph of an Intcode program in Graphviz DOT format")
+                        .arg(
+                            Arg::with_name("program-file")
+                                .takes_value(true)
+                                .help(r#"Path to a file containing the Intcode program to analyze. Use "-" or omit for standard input."#)
+                        )
+                )
+                .subcommand(
+                    SubCommand::with_name("fuzz")
+                        .about("Check that the Intcode engines agree on random programs")
+                        .arg(
+                            Arg::with_name("seed")
+                                .long("seed")
+                                .takes_value(true)
+                                .default_value("0")
+                                .validator(|value| value.parse::<u64>().map(|_| ()).map_err(|err| err.to_string()))
+                                .help("Seed for generating the programs")
+                        )
+                        .arg(
+                            Arg::with_name("count")
+                                .long("count")
+                                .takes_value(true)
+                                .default_value("10000")
+                                .validator(|value| value.parse::<usize>().map(|_| ()).map_err(|err| err.to_string()))
+                                .help("Number of programs to check")
+                        )
+                )
         );
 
     let matches = cli.get_matches();
 
-    if let Some(day) = matches.value_of("day") {
-        if day == "intcode" {
-            run_intcode(matches.value_of("input-file"))
-        } else {
-            run_day(
-                day.parse::<u8>()
-                    .unwrap_or_else(|_| panic!(format!("Invalid day number: {}", day))),
-                matches.value_of("input-file").map(Path::new),
-            )
+    match matches.subcommand() {
+        ("intcode", Some(intcode_matches)) => match intcode_matches.subcommand() {
+            ("asm", Some(asm_matches)) => run_asm(asm_matches.value_of("source-file")),
+            ("debug", Some(debug_matches)) => {
+                run_debug(debug_matches.value_of("program-file").unwrap())
+            }
+            ("play", Some(play_matches)) => run_play(play_matches),
+            ("replay", Some(replay_matches)) => run_replay(
+                replay_matches.value_of("program-file").unwrap(),
+                replay_matches.value_of("session-file").unwrap(),
+            ),
+            ("disasm", Some(disasm_matches)) => run_disasm(disasm_matches.value_of("program-file")),
+            ("cfg", Some(cfg_matches)) => run_cfg(cfg_matches.value_of("program-file")),
+            ("fuzz", Some(fuzz_matches)) => run_fuzz(fuzz_matches),
+            ("run", Some(run_matches)) => run_intcode(run_matches),
+            _ => run_intcode(intcode_matches),
+        },
+        _ => {
+            if let Err(err) = run_solutions(&matches) {
+                eprintln!("Error: {}", err);
+                std::process::exit(1);
+            }
+            Ok(())
         }
+    }
+}
+
+fn run_solutions(matches: &ArgMatches) -> Result<(), AocError> {
+    let registry = Registry::all();
+    let year = matches.value_of("year").unwrap().parse().unwrap();
+    let variant = matches.value_of("variant");
+    let part = matches.value_of("part").map(|part| part.parse().unwrap());
+    let format = matches.value_of("format").unwrap().parse().unwrap();
+    let mut reporter = Reporter::new(std::io::stdout(), format, matches.is_present("time"));
+    if let Some(day) = matches.value_of("day") {
+        let day = day
+            .parse::<u8>()
+            .map_err(|_| AocError::new(AocErrorKind::InvalidDay(day.to_string())))?;
+        let solver = find_solver(&registry, year, day, variant)
+            .ok_or_else(|| AocError::new(AocErrorKind::UnknownDay { year, day }))?;
+        run_day(
+            &mut reporter,
+            solver,
+            matches.value_of("input-file").map(Path::new),
+            part,
+        )?;
     } else {
-        run_all_days()
+        let jobs = matches
+            .value_of("jobs")
+            .map_or(1, |jobs| jobs.parse().unwrap());
+        run_all_days(&mut reporter, &registry, year, variant, part, jobs)?;
     }
+    reporter.finish()?;
+    Ok(())
 }
 
-fn run_day(day: u8, input_path: Option<&Path>) -> Result<(), std::io::Error> {
-    println!();
-    println!("=== Day {: >2} ===", day);
+fn run_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::with_name("program-file")
+            .takes_value(true)
+            .conflicts_with("resume")
+            .help(r#"Path to a file containing the Intcode program to run. Use "-" or omit for standard input. Input is read from the first line of standard input in the same format as an Intcode program; when program is also read from standard input, the program is read from the first line and the input from the second."#),
+        Arg::with_name("trace")
+            .long("trace")
+            .takes_value(true)
+            .value_name("FILE")
+            .help("Write every executed instruction to FILE as JSON lines"),
+        Arg::with_name("profile")
+            .long("profile")
+            .help("Print instruction counts per opcode and address to standard error"),
+        Arg::with_name("resume")
+            .long("resume")
+            .takes_value(true)
+            .value_name("SNAPSHOT")
+            .help("Continue running the machine saved in SNAPSHOT instead of a program file. Input is read from the first line of standard input."),
+        Arg::with_name("snapshot")
+            .long("snapshot")
+            .takes_value(true)
+            .value_name("FILE")
+            .help("Save the machine state to FILE when the program stops, for use with --resume. Running out of input is then not an error."),
+        Arg::with_name("max-steps")
+            .long("max-steps")
+            .takes_value(true)
+            .value_name("N")
+            .validator(|value| value.parse::<usize>().map(|_| ()).map_err(|err| err.to_string()))
+            .help("Stop the program with an error after executing N instructions"),
+        Arg::with_name("timeout")
+            .long("timeout")
+            .takes_value(true)
+            .value_name("SECONDS")
+            .validator(|value| match parse_seconds(&value) {
+                Some(_) => Ok(()),
+                None => Err(format!("Invalid number of seconds: {}", value)),
+            })
+            .help("Stop the program with an error after running for SECONDS seconds"),
+        record_arg(),
+    ]
+}
 
-    let day_func = days::get_solver(day).unwrap_or_else(|| panic!(format!("Unknown day: {}", day)));
-    let lines = input_path
-        .map(get_file_lines)
-        .unwrap_or_else(|| get_file_lines(&day_input_filename(day)))?;
-    let solution = day_func(&lines);
+fn parse_seconds(value: &str) -> Option<Duration> {
+    value
+        .parse()
+        .ok()
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+}
 
-    println!("A: {}", solution.0);
-    println!("B: {}", solution.1);
+fn record_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("record")
+        .long("record")
+        .takes_value(true)
+        .value_name("FILE")
+        .help("Write every value the program reads and writes to FILE, for use with intcode replay")
+}
 
-    Ok(())
+/// Returns the solution `variant` if the day has one, and the default solution
+/// otherwise.
+fn find_solver<'a>(
+    registry: &'a Registry,
+    year: u16,
+    day: u8,
+    variant: Option<&str>,
+) -> Option<&'a dyn DynSolver> {
+    variant
+        .and_then(|variant| registry.variant(year, day, variant))
+        .or_else(|| registry.get(year, day))
 }
 
-fn run_all_days() -> Result<(), std::io::Error> {
-    for day in days::all_numbers() {
-        run_day(day, None)?
+fn run_day<W: Write>(
+    reporter: &mut Reporter<W>,
+    solver: &dyn DynSolver,
+    input_path: Option<&Path>,
+    part: Option<Part>,
+) -> Result<(), AocError> {
+    let report = solve_day(solver, input_path, part, reporter.timed())?;
+    Ok(reporter.write_day(&report)?)
+}
+
+fn solve_day(
+    solver: &dyn DynSolver,
+    input_path: Option<&Path>,
+    part: Option<Part>,
+    timed: bool,
+) -> Result<DayReport, AocError> {
+    let day = solver.info().day;
+    let path = input_path
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| day_input_filename(day));
+    get_file_lines(&path)
+        .map_err(AocError::from)
+        .and_then(|lines| DayReport::run(solver, &lines, part, timed))
+        .map_err(|err| err.in_day(day).in_file(&path))
+}
+
+/// Solves the days on `jobs` threads, and reports each day as soon as it and
+/// all days before it are done.
+fn run_all_days<W: Write>(
+    reporter: &mut Reporter<W>,
+    registry: &Registry,
+    year: u16,
+    variant: Option<&str>,
+    part: Option<Part>,
+    jobs: usize,
+) -> Result<(), AocError> {
+    let solvers: Vec<&dyn DynSolver> = registry
+        .days(year)
+        .into_iter()
+        .map(|day| find_solver(registry, year, day, variant).unwrap())
+        .collect();
+    let timed = reporter.timed();
+    pool::map_ordered(
+        &solvers,
+        jobs,
+        |solver| solve_day(*solver, None, part, timed),
+        |report| Ok(reporter.write_day(&report?)?),
+    )
+}
+
+/// Parses the program input given on line `line_number` of standard input.
+fn parse_inputs(line: Option<&String>, line_number: usize) -> Vec<i64> {
+    match line {
+        Some(line) if !line.trim().is_empty() => split_columns(line, ",")
+            .map(|(column, word)| parse_at(word.trim(), line_number, column))
+            .collect::<Result<Vec<i64>, AocError>>()
+            .unwrap_or_else(|err| {
+                eprintln!("Input error: {}", err.in_file(Path::new("-")));
+                std::process::exit(1);
+            }),
+        _ => Vec::new(),
     }
-    Ok(())
 }
 
-fn run_intcode(input_file: Option<&str>) -> Result<(), std::io::Error> {
-    let lines = get_file_lines(Path::new(input_file.unwrap_or("-")))?;
-    let inputs: Vec<i64> = if input_file == None || input_file == Some("-") {
-        lines
-            .get(1)
-            .map(|line| {
-                line.split(',')
-                    .map(|s| s.parse())
-                    .collect::<Result<Vec<i64>, std::num::ParseIntError>>()
-                    .expect(
-                        "Invalid integer in intcode program input (second line of standard input)",
-                    )
-            })
-            .unwrap_or_default()
+/// Parses the program on the first line of `lines`, read from `source`, and
+/// exits if it is invalid.
+fn load_program(lines: &[String], source: &str) -> Vec<i64> {
+    parse_program(lines).unwrap_or_else(|err| {
+        eprintln!("Program error: {}", err.in_file(Path::new(source)));
+        std::process::exit(1);
+    })
+}
+
+fn run_intcode(matches: &ArgMatches) -> Result<(), std::io::Error> {
+    let input_file = matches.value_of("program-file");
+    let trace_file = matches.value_of("trace").map(Path::new);
+    let snapshot_file = matches.value_of("snapshot").map(Path::new);
+
+    let (mut computer, inputs) = if let Some(resume_file) = matches.value_of("resume") {
+        let snapshot = match std::fs::read_to_string(resume_file)?.parse::<Snapshot>() {
+            Ok(snapshot) => snapshot,
+            Err(err) => {
+                eprintln!("Snapshot error: {}", err);
+                std::process::exit(1);
+            }
+        };
+        let inputs = parse_inputs(get_file_lines(Path::new("-"))?.first(), 1);
+        (IntcodeComputer::restore(snapshot), inputs)
     } else {
-        get_file_lines(Path::new("-"))?
-            .get(0)
-            .map(|line| {
-                line.split(',')
-                    .map(|s| s.parse())
-                    .collect::<Result<Vec<i64>, std::num::ParseIntError>>()
-                    .expect("Invalid integer in intcode program input (standard input)")
-            })
-            .unwrap_or_default()
+        let lines = get_file_lines(Path::new(input_file.unwrap_or("-")))?;
+        let inputs = if input_file.is_none() || input_file == Some("-") {
+            parse_inputs(lines.get(1), 2)
+        } else {
+            parse_inputs(get_file_lines(Path::new("-"))?.first(), 1)
+        };
+        let program = load_program(&lines, input_file.unwrap_or("-"));
+        (IntcodeComputer::new(program), inputs)
     };
-    let computer: IntcodeComputer = lines[0..1].into();
-    let computer = computer.run(inputs);
-    if computer.expects_input() {
-        Err(std::io::Error::new(
+
+    if trace_file.is_some() {
+        computer.trace = Some(Vec::new());
+    }
+    if matches.is_present("profile") {
+        computer.profile = Some(Default::default());
+    }
+    if matches.is_present("record") {
+        computer.session = Some(Session::new());
+    }
+    computer.step_budget = matches.value_of("max-steps").map(|n| n.parse().unwrap());
+    computer.timeout = matches.value_of("timeout").and_then(parse_seconds);
+    let stop_reason = computer.run_mut(inputs);
+
+    if let (Some(path), Some(trace)) = (trace_file, &computer.trace) {
+        let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        for entry in trace {
+            writeln!(file, "{}", entry.to_json(&computer.opcodes))?;
+        }
+    }
+    if let Some(profile) = &computer.profile {
+        eprint!("{}", profile);
+    }
+    if let (Some(path), Some(session)) = (matches.value_of("record"), &computer.session) {
+        std::fs::write(path, session.to_string())?;
+    }
+
+    if stop_reason == StopReason::NeedsInput && snapshot_file.is_none() {
+        return Err(std::io::Error::new(
             std::io::ErrorKind::UnexpectedEof,
             "Not enough input",
-        ))
-    } else {
-        println!(
-            "{}",
-            computer
-                .output
-                .into_iter()
-                .map(|i| i.to_string())
-                .collect::<Vec<String>>()
-                .join(",")
-        );
-        Ok(())
+        ));
+    }
+
+    println!(
+        "{}",
+        computer
+            .output
+            .drain(..)
+            .map(|i| i.to_string())
+            .collect::<Vec<String>>()
+            .join(",")
+    );
+
+    if let Some(path) = snapshot_file {
+        std::fs::write(path, computer.snapshot().to_string())?;
+    }
+
+    match stop_reason {
+        StopReason::Error(err) => {
+            eprintln!("Intcode error: {}", err);
+            std::process::exit(1);
+        }
+        StopReason::BudgetExhausted => {
+            eprintln!(
+                "Intcode program stopped after {} steps",
+                matches.value_of("max-steps").unwrap()
+            );
+            std::process::exit(1);
+        }
+        StopReason::TimedOut => {
+            eprintln!(
+                "Intcode program timed out after {} seconds",
+                matches.value_of("timeout").unwrap()
+            );
+            std::process::exit(1);
+        }
+        _ => Ok(()),
+    }
+}
+
+fn run_asm(source_file: Option<&str>) -> Result<(), std::io::Error> {
+    let lines = get_file_lines(Path::new(source_file.unwrap_or("-")))?;
+    match asm::assemble(&lines.join("\n")) {
+        Ok(program) => {
+            println!(
+                "{}",
+                program
+                    .iter()
+                    .map(|i| i.to_string())
+                    .collect::<Vec<String>>()
+                    .join(",")
+            );
+            Ok(())
+        }
+        Err(err) => {
+            eprintln!("Assembly error: {}", err);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn run_debug(program_file: &str) -> Result<(), std::io::Error> {
+    let lines = get_file_lines(Path::new(program_file))?;
+    let mut debugger = Debugger::new(IntcodeComputer::new(load_program(&lines, program_file)));
+    let stdin = std::io::stdin();
+    let mut stdout = std::io::stdout();
+
+    println!(r#"Type "help" for a list of commands."#);
+    loop {
+        print!("(intcode) ");
+        stdout.flush()?;
+
+        let mut line = String::new();
+        if stdin.read_line(&mut line)? == 0 || !debugger.execute(&line, &mut stdout)? {
+            return Ok(());
+        }
+    }
+}
+
+fn run_play(matches: &ArgMatches) -> Result<(), std::io::Error> {
+    let program_file = matches.value_of("program-file").unwrap();
+    let lines = get_file_lines(Path::new(program_file))?;
+    let mut commands: VecDeque<String> = match matches.value_of("replay") {
+        Some(path) => play::transcript_commands(&std::fs::read_to_string(path)?).into(),
+        None => VecDeque::new(),
+    };
+    let transcript = match matches.value_of("transcript") {
+        Some(path) => Some(std::io::LineWriter::new(std::fs::File::create(path)?)),
+        None => None,
+    };
+    let mut computer = IntcodeComputer::new(load_program(&lines, program_file));
+    if matches.is_present("record") {
+        computer.session = Some(Session::new());
+    }
+    let mut player = Player::new(computer, transcript);
+    let mut editor = LineEditor::new();
+    let mut stdout = std::io::stdout();
+
+    loop {
+        let stop_reason = player.run(&mut stdout)?;
+        // Saved after every run, since the player can quit at any prompt
+        if let (Some(path), Some(session)) =
+            (matches.value_of("record"), &player.machine.computer.session)
+        {
+            std::fs::write(path, session.to_string())?;
+        }
+        match stop_reason {
+            StopReason::NeedsInput => {}
+            StopReason::Error(err) => {
+                eprintln!("Intcode error: {}", err);
+                std::process::exit(1);
+            }
+            _ => return Ok(()),
+        }
+
+        let command = match commands.pop_front() {
+            Some(command) => {
+                println!("> {}", command);
+                editor.add_history(&command);
+                command
+            }
+            None => match editor.read_line("> ")? {
+                Some(command) => command,
+                None => return Ok(()),
+            },
+        };
+        player.send_command(&command)?;
+    }
+}
+
+fn run_replay(program_file: &str, session_file: &str) -> Result<(), std::io::Error> {
+    let lines = get_file_lines(Path::new(program_file))?;
+    let session = match std::fs::read_to_string(session_file)?.parse::<Session>() {
+        Ok(session) => session,
+        Err(err) => {
+            eprintln!("Session error: {}", err);
+            std::process::exit(1);
+        }
+    };
+    match IntcodeComputer::new(load_program(&lines, program_file)).replay(&session) {
+        Ok(_) => {
+            println!(
+                "Replayed {} inputs and {} outputs in {} steps",
+                session.inputs().count(),
+                session.outputs().count(),
+                session.steps
+            );
+            Ok(())
+        }
+        Err(divergence) => {
+            eprintln!("{}", divergence);
+            std::process::exit(1);
+        }
     }
 }
+
+fn run_cfg(program_file: Option<&str>) -> Result<(), std::io::Error> {
+    let lines = get_file_lines(Path::new(program_file.unwrap_or("-")))?;
+    print!(
+        "{}",
+        ControlFlowGraph::build(&load_program(&lines, program_file.unwrap_or("-"))).to_dot()
+    );
+    Ok(())
+}
+
+fn run_fuzz(matches: &ArgMatches) -> Result<(), std::io::Error> {
+    let seed = matches.value_of("seed").unwrap().parse().unwrap();
+    let count = matches.value_of("count").unwrap().parse().unwrap();
+
+    match fuzz::fuzz(seed, count) {
+        None => {
+            println!("Checked {} programs", count);
+            Ok(())
+        }
+        Some((case, divergence)) => {
+            eprintln!("{}", divergence);
+            print!("{}", case);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn run_disasm(program_file: Option<&str>) -> Result<(), std::io::Error> {
+    let lines = get_file_lines(Path::new(program_file.unwrap_or("-")))?;
+    for line in disasm::disassemble(&load_program(&lines, program_file.unwrap_or("-"))) {
+        println!("{}", line);
+    }
+    Ok(())
+}
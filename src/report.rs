@@ -0,0 +1,379 @@
+use crate::common::AocError;
+use crate::solver::DynSolver;
+use crate::solver::Info;
+use crate::solver::Part;
+use std::io::Write;
+use std::str::FromStr;
+use std::time::Duration;
+use std::time::Instant;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Format {
+    Text,
+    Json,
+    Csv,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            _ => Err(format!("Invalid format: {}", s)),
+        }
+    }
+}
+
+/// Wall-clock time spent parsing the input and solving each part.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Timings {
+    pub parse: Duration,
+    pub part_a: Option<Duration>,
+    pub part_b: Option<Duration>,
+}
+
+impl Timings {
+    pub fn total(&self) -> Duration {
+        self.parse + self.part_a.unwrap_or_default() + self.part_b.unwrap_or_default()
+    }
+}
+
+/// The answers for one day, and how long they took if they were timed.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DayReport {
+    pub info: Info,
+    pub part_a: Option<String>,
+    pub part_b: Option<String>,
+    pub timings: Option<Timings>,
+}
+
+impl DayReport {
+    /// Solves `part`, or both parts if `None`. When `timed`, the parts are
+    /// solved one at a time so that each can be timed on its own, even for
+    /// solvers that otherwise share work between them.
+    pub fn run(
+        solver: &dyn DynSolver,
+        lines: &[String],
+        part: Option<Part>,
+        timed: bool,
+    ) -> Result<Self, AocError> {
+        let mut report = DayReport {
+            info: solver.info(),
+            part_a: None,
+            part_b: None,
+            timings: None,
+        };
+
+        let start = Instant::now();
+        let parsed = solver.parse(lines)?;
+        let mut timings = Timings {
+            parse: start.elapsed(),
+            ..Timings::default()
+        };
+
+        if part.is_none() && !timed {
+            let (a, b) = parsed.solve()?;
+            report.part_a = Some(a);
+            report.part_b = Some(b);
+            return Ok(report);
+        }
+
+        for p in &[Part::A, Part::B] {
+            if part.map(|part| part == *p).unwrap_or(true) {
+                let start = Instant::now();
+                let answer = parsed.part(*p)?;
+                let elapsed = start.elapsed();
+                match p {
+                    Part::A => {
+                        report.part_a = Some(answer);
+                        timings.part_a = Some(elapsed);
+                    }
+                    Part::B => {
+                        report.part_b = Some(answer);
+                        timings.part_b = Some(elapsed);
+                    }
+                }
+            }
+        }
+        if timed {
+            report.timings = Some(timings);
+        }
+        Ok(report)
+    }
+}
+
+fn millis(duration: Duration) -> String {
+    format!("{:.3}", duration.as_secs_f64() * 1000.0)
+}
+
+fn json_string(s: &str) -> String {
+    let mut result = String::with_capacity(s.len() + 2);
+    result.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            c if (c as u32) < 0x20 => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// Writes day reports to `out` as they come in.
+///
+/// The JSON format is an object with the list of days, and the total time when
+/// timed. The CSV format has one row per day, with the timing columns only when
+/// timed. Times are in milliseconds.
+pub struct Reporter<W> {
+    out: W,
+    format: Format,
+    timed: bool,
+    days: usize,
+    total: Duration,
+}
+
+impl<W> Reporter<W>
+where
+    W: Write,
+{
+    pub fn new(out: W, format: Format, timed: bool) -> Self {
+        Reporter {
+            out,
+            format,
+            timed,
+            days: 0,
+            total: Duration::default(),
+        }
+    }
+
+    pub fn timed(&self) -> bool {
+        self.timed
+    }
+
+    pub fn write_day(&mut self, report: &DayReport) -> std::io::Result<()> {
+        let timings = report.timings.filter(|_| self.timed);
+        match self.format {
+            Format::Text => self.write_text(report, timings)?,
+            Format::Json => self.write_json(report, timings)?,
+            Format::Csv => self.write_csv(report, timings)?,
+        }
+        self.days += 1;
+        self.total += timings.map(|t| t.total()).unwrap_or_default();
+        self.out.flush()
+    }
+
+    fn write_text(&mut self, report: &DayReport, timings: Option<Timings>) -> std::io::Result<()> {
+        writeln!(self.out)?;
+        writeln!(self.out, "=== Day {: >2} ===", report.info.day)?;
+        if let Some(a) = &report.part_a {
+            writeln!(self.out, "A: {}", a)?;
+        }
+        if let Some(b) = &report.part_b {
+            writeln!(self.out, "B: {}", b)?;
+        }
+        if let Some(timings) = timings {
+            let mut times = vec![format!("parse {} ms", millis(timings.parse))];
+            times.extend(timings.part_a.map(|t| format!("A {} ms", millis(t))));
+            times.extend(timings.part_b.map(|t| format!("B {} ms", millis(t))));
+            writeln!(self.out, "Time: {}", times.join(", "))?;
+        }
+        Ok(())
+    }
+
+    fn write_json(&mut self, report: &DayReport, timings: Option<Timings>) -> std::io::Result<()> {
+        let answer = |answer: &Option<String>| match answer {
+            Some(answer) => json_string(answer),
+            None => "null".to_string(),
+        };
+        let time = |time: Option<Duration>| match time {
+            Some(time) => millis(time),
+            None => "null".to_string(),
+        };
+
+        writeln!(
+            self.out,
+            "{}",
+            if self.days == 0 { r#"{"days":["# } else { "," }
+        )?;
+        write!(
+            self.out,
+            r#"{{"year":{},"day":{},"title":{},"variant":{},"a":{},"b":{}"#,
+            report.info.year,
+            report.info.day,
+            json_string(report.info.title),
+            json_string(report.info.variant),
+            answer(&report.part_a),
+            answer(&report.part_b),
+        )?;
+        if let Some(timings) = timings {
+            write!(
+                self.out,
+                r#","time_ms":{{"parse":{},"a":{},"b":{}}}"#,
+                millis(timings.parse),
+                time(timings.part_a),
+                time(timings.part_b),
+            )?;
+        }
+        write!(self.out, "}}")
+    }
+
+    fn write_csv(&mut self, report: &DayReport, timings: Option<Timings>) -> std::io::Result<()> {
+        if self.days == 0 {
+            write!(self.out, "year,day,title,variant,a,b")?;
+            if self.timed {
+                write!(self.out, ",parse_ms,a_ms,b_ms")?;
+            }
+            writeln!(self.out)?;
+        }
+        let mut fields = vec![
+            report.info.year.to_string(),
+            report.info.day.to_string(),
+            csv_field(report.info.title),
+            csv_field(report.info.variant),
+            csv_field(report.part_a.as_deref().unwrap_or("")),
+            csv_field(report.part_b.as_deref().unwrap_or("")),
+        ];
+        if let Some(timings) = timings {
+            fields.push(millis(timings.parse));
+            fields.push(timings.part_a.map(millis).unwrap_or_default());
+            fields.push(timings.part_b.map(millis).unwrap_or_default());
+        }
+        writeln!(self.out, "{}", fields.join(","))
+    }
+
+    /// Writes the total time and whatever else the format needs after the
+    /// last day.
+    pub fn finish(mut self) -> std::io::Result<W> {
+        match self.format {
+            Format::Text if self.timed => {
+                writeln!(self.out)?;
+                writeln!(self.out, "Total time: {} ms", millis(self.total))?;
+            }
+            Format::Text | Format::Csv => {}
+            Format::Json => {
+                if self.days == 0 {
+                    write!(self.out, r#"{{"days":["#)?;
+                } else {
+                    writeln!(self.out)?;
+                }
+                write!(self.out, "]")?;
+                if self.timed {
+                    write!(self.out, r#","total_ms":{}"#, millis(self.total))?;
+                }
+                writeln!(self.out, "}}")?;
+            }
+        }
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::days;
+
+    fn reports() -> Vec<DayReport> {
+        let info = |day, title| Info {
+            year: days::YEAR,
+            day,
+            title,
+            variant: "default",
+            uses_intcode: false,
+        };
+        vec![
+            DayReport {
+                info: info(1, "The Tyranny of the Rocket Equation"),
+                part_a: Some("3262356".to_string()),
+                part_b: None,
+                timings: Some(Timings {
+                    parse: Duration::from_micros(50),
+                    part_a: Some(Duration::from_micros(1250)),
+                    part_b: None,
+                }),
+            },
+            DayReport {
+                info: info(8, "Space Image Format"),
+                part_a: Some("1965".to_string()),
+                part_b: Some("\n#  #\n \"#,".to_string()),
+                timings: Some(Timings {
+                    parse: Duration::from_millis(2),
+                    part_a: Some(Duration::from_millis(3)),
+                    part_b: Some(Duration::from_millis(4)),
+                }),
+            },
+        ]
+    }
+
+    fn write(format: Format, timed: bool) -> String {
+        let mut reporter = Reporter::new(Vec::new(), format, timed);
+        for report in reports() {
+            reporter.write_day(&report).unwrap();
+        }
+        String::from_utf8(reporter.finish().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn writes_text() {
+        assert_eq!(
+            write(Format::Text, true),
+            "\n=== Day  1 ===\n\
+             A: 3262356\n\
+             Time: parse 0.050 ms, A 1.250 ms\n\
+             \n=== Day  8 ===\n\
+             A: 1965\n\
+             B: \n#  #\n \"#,\n\
+             Time: parse 2.000 ms, A 3.000 ms, B 4.000 ms\n\
+             \nTotal time: 10.300 ms\n"
+        );
+        assert!(!write(Format::Text, false).contains("Time"));
+    }
+
+    #[test]
+    fn writes_json() {
+        assert_eq!(
+            write(Format::Json, true),
+            r##"{"days":[
+{"year":2019,"day":1,"title":"The Tyranny of the Rocket Equation","variant":"default","a":"3262356","b":null,"time_ms":{"parse":0.050,"a":1.250,"b":null}},
+{"year":2019,"day":8,"title":"Space Image Format","variant":"default","a":"1965","b":"\n#  #\n \"#,","time_ms":{"parse":2.000,"a":3.000,"b":4.000}}
+],"total_ms":10.300}
+"##
+        );
+        assert_eq!(
+            Reporter::new(Vec::new(), Format::Json, false)
+                .finish()
+                .unwrap(),
+            b"{\"days\":[]}\n"
+        );
+    }
+
+    #[test]
+    fn writes_csv() {
+        assert_eq!(
+            write(Format::Csv, true),
+            r##"year,day,title,variant,a,b,parse_ms,a_ms,b_ms
+2019,1,The Tyranny of the Rocket Equation,default,3262356,,0.050,1.250,
+2019,8,Space Image Format,default,1965,"
+#  #
+ ""#,",2.000,3.000,4.000
+"##
+        );
+        assert!(write(Format::Csv, false).starts_with("year,day,title,variant,a,b\n2019,1,"));
+    }
+}
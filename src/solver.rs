@@ -0,0 +1,305 @@
+use crate::common::AocError;
+use crate::common::Solution;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// The name of the solution that [`Registry::get`] prefers when a day has
+/// several.
+pub const DEFAULT_VARIANT: &str = "default";
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Info {
+    pub year: u16,
+    pub day: u8,
+    pub title: &'static str,
+    /// Distinguishes alternative solutions to the same puzzle.
+    pub variant: &'static str,
+    pub uses_intcode: bool,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Part {
+    A,
+    B,
+}
+
+impl fmt::Display for Part {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::A => write!(f, "A"),
+            Self::B => write!(f, "B"),
+        }
+    }
+}
+
+impl FromStr for Part {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "a" | "A" => Ok(Self::A),
+            "b" | "B" => Ok(Self::B),
+            _ => Err(format!("Invalid part: {}", s)),
+        }
+    }
+}
+
+/// A solution to one puzzle. The input is parsed once and shared by both
+/// parts.
+pub trait Solver: Send + Sync {
+    type Input;
+
+    fn info(&self) -> Info;
+
+    fn parse(&self, lines: &[String]) -> Result<Self::Input, AocError>;
+
+    fn part_a(&self, input: &Self::Input) -> Result<String, AocError>;
+
+    fn part_b(&self, input: &Self::Input) -> Result<String, AocError>;
+
+    /// Solves both parts. Solvers whose part B builds on the work of part A
+    /// override this to do that work only once.
+    fn solve(&self, input: &Self::Input) -> Result<Solution, AocError> {
+        Ok((self.part_a(input)?, self.part_b(input)?))
+    }
+}
+
+/// A [`Solver`] with its input type hidden, so that solvers for different
+/// days can be stored together.
+pub trait DynSolver: Send + Sync {
+    fn info(&self) -> Info;
+
+    /// Errors from this and from the returned input carry the day number.
+    fn parse<'a>(&'a self, lines: &[String]) -> Result<Box<dyn ParsedInput + 'a>, AocError>;
+}
+
+/// Input parsed by a [`DynSolver`], ready to be solved.
+pub trait ParsedInput {
+    fn part_a(&self) -> Result<String, AocError>;
+
+    fn part_b(&self) -> Result<String, AocError>;
+
+    fn solve(&self) -> Result<Solution, AocError>;
+
+    /// Solves only one part, without the work needed for the other.
+    fn part(&self, part: Part) -> Result<String, AocError> {
+        match part {
+            Part::A => self.part_a(),
+            Part::B => self.part_b(),
+        }
+    }
+}
+
+struct Parsed<'a, S: Solver> {
+    solver: &'a S,
+    input: S::Input,
+}
+
+impl<S: Solver> Parsed<'_, S> {
+    fn in_day(&self, err: AocError) -> AocError {
+        err.in_day(Solver::info(self.solver).day)
+    }
+}
+
+impl<S: Solver> ParsedInput for Parsed<'_, S> {
+    fn part_a(&self) -> Result<String, AocError> {
+        self.solver
+            .part_a(&self.input)
+            .map_err(|err| self.in_day(err))
+    }
+
+    fn part_b(&self) -> Result<String, AocError> {
+        self.solver
+            .part_b(&self.input)
+            .map_err(|err| self.in_day(err))
+    }
+
+    fn solve(&self) -> Result<Solution, AocError> {
+        self.solver
+            .solve(&self.input)
+            .map_err(|err| self.in_day(err))
+    }
+}
+
+impl<S: Solver> DynSolver for S {
+    fn info(&self) -> Info {
+        Solver::info(self)
+    }
+
+    fn parse<'a>(&'a self, lines: &[String]) -> Result<Box<dyn ParsedInput + 'a>, AocError> {
+        let input = Solver::parse(self, lines).map_err(|err| err.in_day(Solver::info(self).day))?;
+        Ok(Box::new(Parsed {
+            solver: self,
+            input,
+        }))
+    }
+}
+
+impl dyn DynSolver + '_ {
+    /// Parses the input and solves both parts.
+    pub fn solve(&self, lines: &[String]) -> Result<Solution, AocError> {
+        self.parse(lines)?.solve()
+    }
+}
+
+/// Solvers by year and day, in the order they were registered.
+#[derive(Default)]
+pub struct Registry {
+    solvers: BTreeMap<(u16, u8), Vec<Box<dyn DynSolver>>>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a registry with the solvers of all years.
+    pub fn all() -> Self {
+        let mut registry = Self::new();
+        crate::days::register(&mut registry);
+        registry
+    }
+
+    /// Panics if a solver with the same year, day and variant is already
+    /// registered.
+    pub fn register<S: DynSolver + 'static>(&mut self, solver: S) {
+        let info = solver.info();
+        let variants = self.solvers.entry((info.year, info.day)).or_default();
+        assert!(
+            variants.iter().all(|s| s.info().variant != info.variant),
+            "Solver {:?} for {} day {} is already registered",
+            info.variant,
+            info.year,
+            info.day
+        );
+        variants.push(Box::new(solver));
+    }
+
+    /// Returns the default solution for a day, or the first one registered if
+    /// none is named [`DEFAULT_VARIANT`].
+    pub fn get(&self, year: u16, day: u8) -> Option<&dyn DynSolver> {
+        let variants = self.solvers.get(&(year, day))?;
+        variants
+            .iter()
+            .find(|s| s.info().variant == DEFAULT_VARIANT)
+            .or_else(|| variants.first())
+            .map(|s| s.as_ref())
+    }
+
+    pub fn variant(&self, year: u16, day: u8, variant: &str) -> Option<&dyn DynSolver> {
+        self.variants(year, day)
+            .find(|s| s.info().variant == variant)
+    }
+
+    pub fn variants(&self, year: u16, day: u8) -> impl Iterator<Item = &dyn DynSolver> {
+        self.solvers
+            .get(&(year, day))
+            .into_iter()
+            .flatten()
+            .map(|s| s.as_ref())
+    }
+
+    pub fn years(&self) -> Vec<u16> {
+        let mut years: Vec<u16> = self.solvers.keys().map(|(year, _)| *year).collect();
+        years.dedup();
+        years
+    }
+
+    pub fn days(&self, year: u16) -> Vec<u8> {
+        self.solvers
+            .keys()
+            .filter(|(y, _)| *y == year)
+            .map(|(_, day)| *day)
+            .collect()
+    }
+
+    /// The default solution of every day, in order.
+    pub fn iter(&self) -> impl Iterator<Item = &dyn DynSolver> {
+        self.solvers
+            .keys()
+            .filter_map(move |(year, day)| self.get(*year, *day))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Sum {
+        variant: &'static str,
+    }
+
+    impl Solver for Sum {
+        type Input = Vec<i64>;
+
+        fn info(&self) -> Info {
+            Info {
+                year: 2018,
+                day: 1,
+                title: "Chronal Calibration",
+                variant: self.variant,
+                uses_intcode: false,
+            }
+        }
+
+        fn parse(&self, lines: &[String]) -> Result<Vec<i64>, AocError> {
+            lines
+                .iter()
+                .enumerate()
+                .map(|(i, line)| crate::common::parse_at(line, i + 1, 1))
+                .collect()
+        }
+
+        fn part_a(&self, input: &Vec<i64>) -> Result<String, AocError> {
+            Ok(input.iter().sum::<i64>().to_string())
+        }
+
+        fn part_b(&self, input: &Vec<i64>) -> Result<String, AocError> {
+            Ok(input.len().to_string())
+        }
+    }
+
+    #[test]
+    fn finds_solvers_by_year_day_and_variant() {
+        let mut registry = Registry::all();
+        registry.register(Sum { variant: "slow" });
+        registry.register(Sum {
+            variant: DEFAULT_VARIANT,
+        });
+
+        assert_eq!(registry.years(), vec![2018, 2019]);
+        assert_eq!(registry.days(2018), vec![1]);
+        assert_eq!(registry.days(2019), (1..=25).collect::<Vec<u8>>());
+        assert_eq!(registry.variants(2018, 1).count(), 2);
+        assert_eq!(
+            registry.get(2018, 1).unwrap().info().variant,
+            DEFAULT_VARIANT
+        );
+        assert_eq!(
+            registry.variant(2018, 1, "slow").unwrap().info().variant,
+            "slow"
+        );
+        assert!(registry.get(2018, 2).is_none());
+        assert_eq!(registry.iter().count(), 26);
+
+        let lines = vec!["+1".to_string(), "-3".to_string(), "+5".to_string()];
+        let parsed = registry.get(2018, 1).unwrap().parse(&lines).unwrap();
+        assert_eq!(parsed.part_a().unwrap(), "3");
+        assert_eq!(parsed.part("b".parse().unwrap()).unwrap(), "3");
+        assert_eq!(parsed.solve().unwrap(), ("3".to_string(), "3".to_string()));
+        assert_eq!("c".parse::<Part>(), Err("Invalid part: c".to_string()));
+
+        let lines = vec!["+1".to_string(), "three".to_string()];
+        let err = registry.get(2018, 1).unwrap().parse(&lines).err().unwrap();
+        assert_eq!((err.day, err.line, err.column), (Some(1), Some(2), Some(1)));
+    }
+
+    #[test]
+    #[should_panic(expected = "already registered")]
+    fn rejects_duplicate_variants() {
+        let mut registry = Registry::new();
+        registry.register(Sum { variant: "slow" });
+        registry.register(Sum { variant: "slow" });
+    }
+}
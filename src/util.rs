@@ -1,5 +1,7 @@
 pub mod iter;
+pub mod line_editor;
 pub mod pascal;
+pub mod pool;
 
 use std::cmp::Ordering;
 
@@ -77,6 +79,7 @@ where
 }
 
 #[cfg(test)]
+#[allow(clippy::identity_op)]
 mod tests {
     use super::iter::Countable;
     use super::Permutations;
@@ -0,0 +1,365 @@
+use std::collections::VecDeque;
+use std::io::IsTerminal;
+use std::io::Read;
+use std::io::Write;
+use std::process::Command;
+use std::process::Stdio;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Key {
+    Char(char),
+    Enter,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    KillLine,
+    KillToEnd,
+    Interrupt,
+    EndOfFile,
+    Other,
+}
+
+/// Reads key presses. Bytes are read as many at a time as are available, so
+/// that a lone Esc key can be told apart from the escape sequences that
+/// terminals send all at once.
+struct KeyReader<R> {
+    input: R,
+    pending: VecDeque<u8>,
+}
+
+impl<R: Read> KeyReader<R> {
+    fn new(input: R) -> Self {
+        KeyReader {
+            input,
+            pending: VecDeque::new(),
+        }
+    }
+
+    fn fill(&mut self) -> std::io::Result<()> {
+        if self.pending.is_empty() {
+            let mut bytes = [0; 64];
+            let count = self.input.read(&mut bytes)?;
+            self.pending.extend(&bytes[..count]);
+        }
+        Ok(())
+    }
+
+    /// Returns `None` at end of input.
+    fn read_byte(&mut self) -> std::io::Result<Option<u8>> {
+        self.fill()?;
+        Ok(self.pending.pop_front())
+    }
+
+    /// Takes the next byte if it has already arrived and matches `predicate`.
+    fn take_pending_if(&mut self, predicate: impl Fn(u8) -> bool) -> Option<u8> {
+        match self.pending.front() {
+            Some(byte) if predicate(*byte) => self.pending.pop_front(),
+            _ => None,
+        }
+    }
+
+    /// Reads one key press, decoding UTF-8 text and the escape sequences sent
+    /// by common terminals for arrow keys and the like.
+    fn read_key(&mut self) -> std::io::Result<Option<Key>> {
+        let byte = match self.read_byte()? {
+            Some(byte) => byte,
+            None => return Ok(None),
+        };
+        Ok(Some(match byte {
+            b'\r' | b'\n' => Key::Enter,
+            0x7f | 0x08 => Key::Backspace,
+            0x01 => Key::Home,
+            0x02 => Key::Left,
+            0x03 => Key::Interrupt,
+            0x04 => Key::EndOfFile,
+            0x05 => Key::End,
+            0x06 => Key::Right,
+            0x0b => Key::KillToEnd,
+            0x0e => Key::Down,
+            0x10 => Key::Up,
+            0x15 => Key::KillLine,
+            0x1b => {
+                // A lone Esc, or Esc and some other key, does nothing
+                if self
+                    .take_pending_if(|byte| byte == b'[' || byte == b'O')
+                    .is_none()
+                {
+                    return Ok(Some(Key::Other));
+                }
+                match self.read_byte()? {
+                    Some(b'A') => Key::Up,
+                    Some(b'B') => Key::Down,
+                    Some(b'C') => Key::Right,
+                    Some(b'D') => Key::Left,
+                    Some(b'H') => Key::Home,
+                    Some(b'F') => Key::End,
+                    Some(digit @ b'0'..=b'9') => {
+                        let mut code = vec![digit];
+                        while let Some(byte) = self.read_byte()? {
+                            if byte == b'~' {
+                                break;
+                            }
+                            code.push(byte);
+                        }
+                        match code.as_slice() {
+                            b"1" | b"7" => Key::Home,
+                            b"3" => Key::Delete,
+                            b"4" | b"8" => Key::End,
+                            _ => Key::Other,
+                        }
+                    }
+                    _ => Key::Other,
+                }
+            }
+            byte if byte.is_ascii_graphic() || byte == b' ' => Key::Char(byte as char),
+            byte if byte >= 0xc0 => {
+                let len = match byte {
+                    0xc0..=0xdf => 2,
+                    0xe0..=0xef => 3,
+                    _ => 4,
+                };
+                let mut bytes = vec![byte];
+                while bytes.len() < len {
+                    self.fill()?;
+                    match self.take_pending_if(|byte| byte & 0xc0 == 0x80) {
+                        Some(byte) => bytes.push(byte),
+                        None => break,
+                    }
+                }
+                match std::str::from_utf8(&bytes)
+                    .ok()
+                    .and_then(|s| s.chars().next())
+                {
+                    Some(c) => Key::Char(c),
+                    None => Key::Other,
+                }
+            }
+            _ => Key::Other,
+        }))
+    }
+}
+
+/// Puts the terminal in raw mode while it exists, using `stty`.
+struct RawMode {
+    saved: String,
+}
+
+impl RawMode {
+    fn enable() -> Option<RawMode> {
+        let stty = |args: &[&str]| {
+            Command::new("stty")
+                .args(args)
+                .stdin(Stdio::inherit())
+                .stderr(Stdio::null())
+                .output()
+                .ok()
+                .filter(|output| output.status.success())
+        };
+        let saved = String::from_utf8(stty(&["-g"])?.stdout).ok()?;
+        stty(&["raw", "-echo"])?;
+        Some(RawMode {
+            saved: saved.trim().to_string(),
+        })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        Command::new("stty")
+            .arg(&self.saved)
+            .stdin(Stdio::inherit())
+            .status()
+            .ok();
+    }
+}
+
+/// Reads lines from a terminal with cursor movement and command history.
+/// Falls back to reading plain lines when standard input is not a terminal.
+#[derive(Debug, Default)]
+pub struct LineEditor {
+    pub history: Vec<String>,
+}
+
+impl LineEditor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_history(&mut self, line: &str) {
+        if !line.is_empty() && self.history.last().map(String::as_str) != Some(line) {
+            self.history.push(line.to_string());
+        }
+    }
+
+    /// Returns `None` at end of input.
+    pub fn read_line(&mut self, prompt: &str) -> std::io::Result<Option<String>> {
+        let mut stdout = std::io::stdout();
+        let stdin = std::io::stdin();
+
+        let raw_mode = if stdin.is_terminal() {
+            RawMode::enable()
+        } else {
+            None
+        };
+        let line = match raw_mode {
+            Some(_) => self.edit(prompt, &mut stdin.lock(), &mut stdout)?,
+            None => {
+                write!(stdout, "{}", prompt)?;
+                stdout.flush()?;
+                let mut line = String::new();
+                match stdin.read_line(&mut line)? {
+                    0 => None,
+                    _ => Some(line.trim_end_matches(&['\r', '\n'][..]).to_string()),
+                }
+            }
+        };
+
+        if let Some(line) = &line {
+            self.add_history(line);
+        }
+        Ok(line)
+    }
+
+    /// Reads key presses from `input` until Enter, showing the line being
+    /// edited on `out`.
+    fn edit<R: Read, W: Write>(
+        &mut self,
+        prompt: &str,
+        input: &mut R,
+        out: &mut W,
+    ) -> std::io::Result<Option<String>> {
+        let mut keys = KeyReader::new(input);
+        let mut line: Vec<char> = Vec::new();
+        let mut cursor = 0;
+        let mut history_index = self.history.len();
+
+        loop {
+            write!(out, "\r{}{}\x1b[K", prompt, line.iter().collect::<String>())?;
+            if cursor < line.len() {
+                write!(out, "\x1b[{}D", line.len() - cursor)?;
+            }
+            out.flush()?;
+
+            let key = match keys.read_key()? {
+                Some(key) => key,
+                None if line.is_empty() => return Ok(None),
+                None => Key::Enter,
+            };
+            match key {
+                Key::Char(c) => {
+                    line.insert(cursor, c);
+                    cursor += 1;
+                }
+                Key::Enter => {
+                    write!(out, "\r\n")?;
+                    return Ok(Some(line.into_iter().collect()));
+                }
+                Key::Backspace if cursor > 0 => {
+                    cursor -= 1;
+                    line.remove(cursor);
+                }
+                Key::Delete if cursor < line.len() => {
+                    line.remove(cursor);
+                }
+                Key::Left if cursor > 0 => cursor -= 1,
+                Key::Right if cursor < line.len() => cursor += 1,
+                Key::Home => cursor = 0,
+                Key::End => cursor = line.len(),
+                Key::Up | Key::Down => {
+                    history_index = match key {
+                        Key::Up => history_index.saturating_sub(1),
+                        _ => (history_index + 1).min(self.history.len()),
+                    };
+                    line = self
+                        .history
+                        .get(history_index)
+                        .map(|entry| entry.chars().collect())
+                        .unwrap_or_default();
+                    cursor = line.len();
+                }
+                Key::KillLine => {
+                    line.clear();
+                    cursor = 0;
+                }
+                Key::KillToEnd => line.truncate(cursor),
+                Key::Interrupt | Key::EndOfFile if line.is_empty() => {
+                    write!(out, "\r\n")?;
+                    return Ok(None);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(editor: &mut LineEditor, keys: &str) -> Option<String> {
+        editor
+            .edit("> ", &mut keys.as_bytes(), &mut Vec::new())
+            .unwrap()
+    }
+
+    #[test]
+    fn edits_line() {
+        let mut editor = LineEditor::new();
+        assert_eq!(
+            edit(&mut editor, "nrth\x1b[D\x1b[D\x1b[Do\x1b[F!\x7f\r"),
+            Some("north".to_string())
+        );
+        assert_eq!(
+            edit(
+                &mut editor,
+                "take\x01\x0bdrop x\x1b[H\x1b[3~\x1b[3~\x1b[3~\x1b[3~\x15west\n"
+            ),
+            Some("west".to_string())
+        );
+        assert_eq!(edit(&mut editor, "\x04"), None);
+        assert_eq!(edit(&mut editor, "sou"), Some("sou".to_string()));
+    }
+
+    #[test]
+    fn ignores_lone_escape() {
+        let mut editor = LineEditor::new();
+        let mut keys = "ab\x1b".as_bytes().chain("c\x1bd\r".as_bytes());
+        assert_eq!(
+            editor.edit("> ", &mut keys, &mut Vec::new()).unwrap(),
+            Some("abcd".to_string())
+        );
+    }
+
+    #[test]
+    fn edits_non_ascii_text() {
+        let mut editor = LineEditor::new();
+        assert_eq!(
+            edit(&mut editor, "caf\u{e9} \u{2603}\x1b[D\x7f\r"),
+            Some("caf\u{e9}\u{2603}".to_string())
+        );
+    }
+
+    #[test]
+    fn recalls_history() {
+        let mut editor = LineEditor::new();
+        editor.add_history("north");
+        editor.add_history("take mug");
+        editor.add_history("take mug");
+        assert_eq!(editor.history, vec!["north", "take mug"]);
+
+        assert_eq!(
+            edit(&mut editor, "\x1b[A\x1b[A\x1b[A\r"),
+            Some("north".to_string())
+        );
+        assert_eq!(
+            edit(&mut editor, "\x1b[A\x1b[A\x1b[B\x7f\x7f\x7fcake\r"),
+            Some("take cake".to_string())
+        );
+        assert_eq!(edit(&mut editor, "x\x1b[A\x1b[B\r"), Some("".to_string()));
+    }
+}
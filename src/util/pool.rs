@@ -0,0 +1,98 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::channel;
+
+/// Calls `f` on every item on up to `jobs` threads, and passes the results to
+/// `emit` in the order of `items`, each as soon as it and all results before
+/// it are done. Stops handing out items and returns the error if `emit` fails.
+pub fn map_ordered<T, R, X, F, E>(items: &[T], jobs: usize, f: F, mut emit: E) -> Result<(), X>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+    E: FnMut(R) -> Result<(), X>,
+{
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = channel();
+    std::thread::scope(|scope| {
+        for _ in 0..jobs.clamp(1, items.len().max(1)) {
+            let sender = sender.clone();
+            let (next, f) = (&next, &f);
+            scope.spawn(move || loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                match items.get(index) {
+                    Some(item) if sender.send((index, f(item))).is_ok() => {}
+                    _ => break,
+                }
+            });
+        }
+        drop(sender);
+
+        let mut pending = BTreeMap::new();
+        let mut emitted = 0;
+        for (index, result) in receiver {
+            pending.insert(index, result);
+            while let Some(result) = pending.remove(&emitted) {
+                emitted += 1;
+                emit(result)?;
+            }
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn emits_results_in_order() {
+        let items: Vec<u64> = (0..20).collect();
+        for jobs in &[1, 4, 100] {
+            let mut results = Vec::new();
+            map_ordered(
+                &items,
+                *jobs,
+                |i| {
+                    std::thread::sleep(Duration::from_millis((20 - i) % 7));
+                    i * i
+                },
+                |result| {
+                    results.push(result);
+                    Ok::<(), ()>(())
+                },
+            )
+            .unwrap();
+            assert_eq!(results, items.iter().map(|i| i * i).collect::<Vec<u64>>());
+        }
+    }
+
+    #[test]
+    fn stops_at_first_error() {
+        let items: Vec<u64> = (0..1000).collect();
+        let calls = AtomicUsize::new(0);
+        let mut results = Vec::new();
+        let result = map_ordered(
+            &items,
+            2,
+            |i| {
+                calls.fetch_add(1, Ordering::Relaxed);
+                std::thread::sleep(Duration::from_millis(1));
+                *i
+            },
+            |i| {
+                if i == 3 {
+                    Err(i)
+                } else {
+                    results.push(i);
+                    Ok(())
+                }
+            },
+        );
+        assert_eq!(result, Err(3));
+        assert_eq!(results, vec![0, 1, 2]);
+        assert!(calls.load(Ordering::Relaxed) < items.len());
+    }
+}
@@ -1,31 +1,56 @@
 use adventofcode_2019::common::day_input_filename;
 use adventofcode_2019::common::get_file_lines;
+use adventofcode_2019::common::AocError;
 use adventofcode_2019::days;
+use adventofcode_2019::intcode::fuzz;
+use adventofcode_2019::intcode::fuzz::FuzzCase;
+use adventofcode_2019::solver::Registry;
 
-fn test_day(day: u8, correct_a: &str, correct_b: &str) -> Result<(), std::io::Error> {
-    let solve = days::get_solver(day).unwrap();
+fn test_day(day: u8, correct_a: &str, correct_b: &str) -> Result<(), AocError> {
+    let registry = Registry::all();
     let input_lines = get_file_lines(&day_input_filename(day))?;
-    let (solution_a, solution_b) = solve(&input_lines);
+    for solver in registry.variants(days::YEAR, day) {
+        let parsed = solver.parse(&input_lines)?;
+        let variant = solver.info().variant;
+        assert_eq!(
+            parsed.solve()?,
+            (parsed.part_a()?, parsed.part_b()?),
+            "Parts solved together and separately differ for day {} ({})",
+            day,
+            variant
+        );
+        check_solution(day, variant, parsed.solve()?, correct_a, correct_b);
+    }
+    Ok(())
+}
+
+fn check_solution(
+    day: u8,
+    variant: &str,
+    (solution_a, solution_b): (String, String),
+    correct_a: &str,
+    correct_b: &str,
+) {
     assert_eq!(
         solution_a.as_str(),
         correct_a,
-        "Incorrect solution for day {}a",
-        day
+        "Incorrect solution for day {}a ({})",
+        day,
+        variant
     );
     assert_eq!(
         solution_b.as_str(),
         correct_b,
-        "Incorrect solution for day {}b",
-        day
+        "Incorrect solution for day {}b ({})",
+        day,
+        variant
     );
-
-    Ok(())
 }
 
 macro_rules! test_day {
     ($name: ident, $day: literal, $sol_a: literal, $sol_b: literal) => {
         #[test]
-        fn $name() -> Result<(), std::io::Error> {
+        fn $name() -> Result<(), AocError> {
             test_day($day, $sol_a, $sol_b)
         }
     };
@@ -78,3 +103,62 @@ test_day!(day22, 22, "4775", "37889219674304");
 test_day!(day23, 23, "18513", "13286");
 test_day!(day24, 24, "17863711", "1937");
 test_day!(day25, 25, "35332", "-");
+
+#[test]
+fn intcode_engines_agree_on_corpus() -> Result<(), std::io::Error> {
+    let mut paths = std::fs::read_dir("tests/intcode-corpus")?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    paths.sort();
+    assert!(!paths.is_empty());
+
+    for path in paths {
+        let case: FuzzCase = std::fs::read_to_string(&path)?
+            .parse()
+            .unwrap_or_else(|err| panic!("Invalid corpus file {}: {}", path.display(), err));
+        if let Err(divergence) = fuzz::compare(&case) {
+            panic!("{}: {}", path.display(), divergence);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn every_day_rejects_malformed_input() {
+    let registry = Registry::all();
+    let lines = vec!["This is not a puzzle input".to_string()];
+    for day in registry.days(days::YEAR) {
+        for solver in registry.variants(days::YEAR, day) {
+            match solver.parse(&lines) {
+                Ok(_) => panic!(
+                    "Malformed input accepted for day {} ({})",
+                    day,
+                    solver.info().variant
+                ),
+                Err(err) => assert_eq!(err.day, Some(day), "{}", err),
+            }
+        }
+    }
+
+    let bad_programs = [
+        (11, "104,1,104,5,99"),
+        (13, "104,1,104,2,99"),
+        (17, "104,35,104,94,104,10,104,10,99"),
+        (19, "99"),
+        (23, "104,99,104,1,104,2,99"),
+        (25, "99"),
+    ];
+    for (day, program) in &bad_programs {
+        let lines = vec![program.to_string()];
+        for solver in registry.variants(days::YEAR, *day) {
+            match solver.parse(&lines).and_then(|parsed| parsed.solve()) {
+                Ok(_) => panic!(
+                    "Bad program output accepted for day {} ({})",
+                    day,
+                    solver.info().variant
+                ),
+                Err(err) => assert_eq!(err.day, Some(*day), "{}", err),
+            }
+        }
+    }
+}